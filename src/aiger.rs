@@ -0,0 +1,626 @@
+use crate::encodings::{ClauseSink, ProblemSink};
+use crate::heuristics::heuristics::Heuristics;
+use crate::sat_solver::*;
+use crate::verify::Model;
+use std::fs;
+
+// And-Inverter Graphs in the AIGER format, ASCII (`aag`) or binary (`aig`),
+// with the sections of AIGER 1.9: inputs, latches, outputs, bad-state
+// properties and invariant constraints. Justice and fairness properties are
+// read past and ignored.
+//
+// A literal is twice a variable, plus one if it is negated; variable 0 is the
+// constant false. Every AND gate in the cone of influence of the checked
+// property and the constraints gets a variable of its own and the three
+// clauses of its Tseitin encoding. As input, the circuit is checked in its
+// first time step: the latches hold their reset values (a latch whose reset is
+// itself starts free), the constraints hold, and the property (a bad state, or
+// an output when there are none) is true.
+
+#[derive(Debug)]
+pub struct Latch {
+    pub literal: u32,
+    pub next: u32,
+    // 0, 1, or the latch's own literal for an unknown initial value
+    pub reset: u32,
+}
+
+#[derive(Debug)]
+pub struct And {
+    pub lhs: u32,
+    pub rhs0: u32,
+    pub rhs1: u32,
+}
+
+/// A run of the circuit: the initial values of the latches and the values
+/// of the inputs in every step.
+pub struct Trace {
+    pub latches: Vec<bool>,
+    pub inputs: Vec<Vec<bool>>,
+}
+
+#[derive(Debug)]
+pub struct Aig {
+    pub max_variable: u32,
+    pub inputs: Vec<u32>,
+    pub latches: Vec<Latch>,
+    pub outputs: Vec<u32>,
+    pub bad: Vec<u32>,
+    pub constraints: Vec<u32>,
+    // ordered so that a gate comes after the gates it reads
+    pub ands: Vec<And>,
+    pub input_names: Vec<Option<String>>,
+    pub latch_names: Vec<Option<String>>,
+    // the gate defining each variable, if it is one
+    definitions: Vec<Option<usize>>,
+}
+
+// A cursor over the file: ASCII lines, and the variable-length integers of
+// the binary AND section.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at_end(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    fn line(&mut self) -> Result<&'a str, String> {
+        if self.at_end() {
+            return Err(String::from("unexpected end of file"));
+        }
+        let rest = &self.data[self.position..];
+        let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        self.position += (end + 1).min(rest.len());
+        self.line += 1;
+        return std::str::from_utf8(&rest[..end]).map_err(|_| format!("line {}: not text", self.line));
+    }
+
+    // the numbers of a line, which must have between `min` and `max`
+    fn numbers(&mut self, min: usize, max: usize) -> Result<Vec<u32>, String> {
+        let line = self.line()?;
+        let numbers = line
+            .split_whitespace()
+            .map(|w| w.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| format!("line {}: bad number in '{}'", self.line, line))?;
+        if numbers.len() < min || numbers.len() > max {
+            return Err(format!("line {}: expected {} numbers, found '{}'", self.line, min, line));
+        }
+        return Ok(numbers);
+    }
+
+    fn varint(&mut self) -> Result<u32, String> {
+        let mut value: u64 = 0;
+        for shift in (0..35).step_by(7) {
+            let Some(byte) = self.data.get(self.position) else {
+                return Err(String::from("unexpected end of file in the AND gates"));
+            };
+            self.position += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| String::from("AND gate delta too large"));
+            }
+        }
+        return Err(String::from("AND gate delta too large"));
+    }
+}
+
+pub fn read_aiger(path: &str) -> Result<Aig, String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_aiger(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+fn parse_aiger(data: &[u8]) -> Result<Aig, String> {
+    let mut r = Reader { data, position: 0, line: 0 };
+    let header = r.line()?;
+    let fields: Vec<&str> = header.split_whitespace().collect();
+    let binary = match fields.first() {
+        Some(&"aag") => false,
+        Some(&"aig") => true,
+        _ => return Err(String::from("line 1: expected an 'aag' or 'aig' header")),
+    };
+    let counts = fields[1..]
+        .iter()
+        .map(|w| w.parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| format!("line 1: bad header '{}'", header))?;
+    if counts.len() < 5 || counts.len() > 9 {
+        return Err(format!("line 1: bad header '{}'", header));
+    }
+    let count = |i: usize| counts.get(i).copied().unwrap_or(0);
+    let (m, i, l, o, a) = (count(0), count(1), count(2), count(3), count(4));
+    let (b, c, j, f) = (count(5), count(6), count(7), count(8));
+    if binary && m != i + l + a {
+        return Err(format!("line 1: M = {} is not I + L + A in a binary file", m));
+    }
+    let check = |literal: u32, line: usize| -> Result<u32, String> {
+        if literal as u64 > 2 * m as u64 + 1 {
+            return Err(format!("line {}: literal {} is above the maximum variable {}", line, literal, m));
+        }
+        return Ok(literal);
+    };
+
+    let mut aig = Aig {
+        max_variable: m,
+        inputs: Vec::new(),
+        latches: Vec::new(),
+        outputs: Vec::new(),
+        bad: Vec::new(),
+        constraints: Vec::new(),
+        ands: Vec::new(),
+        input_names: vec![None; i as usize],
+        latch_names: vec![None; l as usize],
+        definitions: vec![None; m as usize + 1],
+    };
+    for k in 0..i {
+        let literal = if binary { 2 * (k + 1) } else { check(r.numbers(1, 1)?[0], r.line)? };
+        aig.inputs.push(literal);
+    }
+    for k in 0..l {
+        let numbers = r.numbers(if binary { 1 } else { 2 }, if binary { 2 } else { 3 })?;
+        let (literal, rest) = if binary { (2 * (i + k + 1), &numbers[..]) } else { (numbers[0], &numbers[1..]) };
+        let latch = Latch {
+            literal: check(literal, r.line)?,
+            next: check(rest[0], r.line)?,
+            reset: rest.get(1).copied().unwrap_or(0),
+        };
+        if latch.reset > 1 && latch.reset != latch.literal {
+            return Err(format!("line {}: a reset must be 0, 1 or the latch itself", r.line));
+        }
+        aig.latches.push(latch);
+    }
+    for (n, list) in [(o, &mut aig.outputs), (b, &mut aig.bad), (c, &mut aig.constraints)] {
+        for _ in 0..n {
+            list.push(check(r.numbers(1, 1)?[0], r.line)?);
+        }
+    }
+    // justice properties, a size each and then their literals, and fairness
+    // constraints
+    let mut sizes = 0;
+    for _ in 0..j {
+        sizes += r.numbers(1, 1)?[0];
+    }
+    for _ in 0..sizes + f {
+        check(r.numbers(1, 1)?[0], r.line)?;
+    }
+    for k in 0..a {
+        let and = if binary {
+            let lhs = 2 * (i + l + k + 1);
+            let rhs0 = lhs.checked_sub(r.varint()?).ok_or("bad AND gate delta")?;
+            let rhs1 = rhs0.checked_sub(r.varint()?).ok_or("bad AND gate delta")?;
+            And { lhs, rhs0, rhs1 }
+        } else {
+            let numbers = r.numbers(3, 3)?;
+            And {
+                lhs: check(numbers[0], r.line)?,
+                rhs0: check(numbers[1], r.line)?,
+                rhs1: check(numbers[2], r.line)?,
+            }
+        };
+        aig.ands.push(and);
+    }
+    aig.sort_ands()?;
+
+    // the symbol table, up to the comment section
+    while !r.at_end() {
+        let line = r.line()?;
+        if line == "c" {
+            break;
+        }
+        let (kind, rest) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        let Some((position, name)) = rest.split_once(' ') else {
+            return Err(format!("line {}: bad symbol '{}'", r.line, line));
+        };
+        let position: usize = position.parse().map_err(|_| format!("line {}: bad symbol '{}'", r.line, line))?;
+        let names = match kind {
+            "i" => &mut aig.input_names,
+            "l" => &mut aig.latch_names,
+            "o" | "b" | "c" | "j" | "f" => continue,
+            _ => return Err(format!("line {}: bad symbol '{}'", r.line, line)),
+        };
+        match names.get_mut(position) {
+            Some(slot) => *slot = Some(name.to_string()),
+            None => return Err(format!("line {}: no {}{} for the symbol", r.line, kind, position)),
+        }
+    }
+    return Ok(aig);
+}
+
+impl Aig {
+    // Checks that every variable is defined once and that the gates form no
+    // cycle, and puts every gate after those it reads.
+    fn sort_ands(&mut self) -> Result<(), String> {
+        let mut defined = vec![false; self.max_variable as usize + 1];
+        defined[0] = true;
+        let sources = self.inputs.iter().chain(self.latches.iter().map(|l| &l.literal));
+        for literal in sources.chain(self.ands.iter().map(|a| &a.lhs)) {
+            if literal & 1 == 1 || *literal == 0 {
+                return Err(format!("{} cannot be defined", literal));
+            }
+            if std::mem::replace(&mut defined[(literal / 2) as usize], true) {
+                return Err(format!("variable {} is defined twice", literal / 2));
+            }
+        }
+        let mut gate = vec![None; self.max_variable as usize + 1];
+        for (k, and) in self.ands.iter().enumerate() {
+            gate[(and.lhs / 2) as usize] = Some(k);
+        }
+        for and in self.ands.iter() {
+            for rhs in [and.rhs0, and.rhs1] {
+                if !defined[(rhs / 2) as usize] {
+                    return Err(format!("variable {} is used but not defined", rhs / 2));
+                }
+            }
+        }
+
+        // depth first, with an explicit stack as circuits can be deep
+        let mut order = Vec::with_capacity(self.ands.len());
+        // 0: not visited, 1: on the stack, 2: done
+        let mut state = vec![0u8; self.ands.len()];
+        for root in 0..self.ands.len() {
+            let mut stack = vec![(root, false)];
+            while let Some((k, expanded)) = stack.pop() {
+                if expanded {
+                    state[k] = 2;
+                    order.push(k);
+                    continue;
+                }
+                match state[k] {
+                    2 => continue,
+                    1 => return Err(format!("the AND gates form a cycle through {}", self.ands[k].lhs)),
+                    _ => {}
+                }
+                state[k] = 1;
+                stack.push((k, true));
+                for rhs in [self.ands[k].rhs0, self.ands[k].rhs1] {
+                    if let Some(g) = gate[(rhs / 2) as usize] {
+                        if state[g] != 2 {
+                            stack.push((g, false));
+                        }
+                    }
+                }
+            }
+        }
+        let mut ands: Vec<Option<And>> = std::mem::take(&mut self.ands).into_iter().map(Some).collect();
+        self.ands = order.into_iter().map(|k| ands[k].take().unwrap()).collect();
+        for (k, and) in self.ands.iter().enumerate() {
+            self.definitions[(and.lhs / 2) as usize] = Some(k);
+        }
+        return Ok(());
+    }
+
+    /// The properties that can be checked: the bad states, or the outputs
+    /// of a circuit without any.
+    pub fn properties(&self) -> &[u32] {
+        if self.bad.is_empty() {
+            &self.outputs
+        } else {
+            &self.bad
+        }
+    }
+
+    /// Writes the clauses of the AND gates in the cone of influence of
+    /// `roots`. `literals` holds the literal of each variable written so far,
+    /// and must hold those of the inputs and latches in the cone; the gates
+    /// get fresh variables.
+    pub fn encode(&self, sink: &mut impl ClauseSink, literals: &mut [Option<Literal>], roots: &[u32]) {
+        let mut in_cone = vec![false; self.ands.len()];
+        let mut stack: Vec<u32> = roots.iter().map(|l| l / 2).collect();
+        while let Some(v) = stack.pop() {
+            if v == 0 && literals[0].is_none() {
+                let f = Literal {
+                    variable: sink.fresh_variable(),
+                    polarity: Polarity::On,
+                };
+                sink.add_clause(&[!f]);
+                literals[0] = Some(f);
+            }
+            if let Some(k) = self.definitions[v as usize] {
+                if !in_cone[k] && literals[v as usize].is_none() {
+                    in_cone[k] = true;
+                    stack.push(self.ands[k].rhs0 / 2);
+                    stack.push(self.ands[k].rhs1 / 2);
+                }
+            }
+        }
+        for (k, and) in self.ands.iter().enumerate() {
+            if !in_cone[k] {
+                continue;
+            }
+            let g = Literal {
+                variable: sink.fresh_variable(),
+                polarity: Polarity::On,
+            };
+            literals[(and.lhs / 2) as usize] = Some(g);
+            let a = literal(literals, and.rhs0);
+            let b = literal(literals, and.rhs1);
+            // a gate may read the same literal twice, or a literal and its
+            // negation; the clauses repeat no literal and hold no tautology
+            sink.add_clause(&[!g, a]);
+            if b == a {
+                sink.add_clause(&[g, !a]);
+            } else if b == !a {
+                sink.add_clause(&[!g, b]);
+            } else {
+                sink.add_clause(&[!g, b]);
+                sink.add_clause(&[g, !a, !b]);
+            }
+        }
+    }
+
+    /// The values of all variables, given those of the inputs and the
+    /// latches.
+    pub fn simulate(&self, inputs: &[bool], latches: &[bool]) -> Vec<bool> {
+        let mut values = vec![false; self.max_variable as usize + 1];
+        for (l, value) in self.inputs.iter().zip(inputs) {
+            values[(l / 2) as usize] = *value;
+        }
+        for (latch, value) in self.latches.iter().zip(latches) {
+            values[(latch.literal / 2) as usize] = *value;
+        }
+        for and in self.ands.iter() {
+            values[(and.lhs / 2) as usize] = value(&values, and.rhs0) && value(&values, and.rhs1);
+        }
+        return values;
+    }
+
+    /// Simulates a trace: whether the constraints hold in every step and
+    /// `property` is true in the last one.
+    pub fn check_trace(&self, trace: &Trace, property: u32) -> Result<(), String> {
+        let mut latches = trace.latches.clone();
+        for (step, inputs) in trace.inputs.iter().enumerate() {
+            let values = self.simulate(inputs, &latches);
+            if let Some(k) = self.constraints.iter().position(|c| !value(&values, *c)) {
+                return Err(format!("the solution violates constraint {} in step {}", k, step));
+            }
+            if step + 1 == trace.inputs.len() && !value(&values, property) {
+                return Err(format!("the solution does not make the property true in step {}", step));
+            }
+            latches = self.latches.iter().map(|l| value(&values, l.next)).collect();
+        }
+        return Ok(());
+    }
+}
+
+/// The literal of an AIGER literal whose variable has been encoded.
+pub fn literal(literals: &[Option<Literal>], l: u32) -> Literal {
+    let x = literals[(l / 2) as usize].expect("variable not encoded");
+    return if l & 1 == 1 { !x } else { x };
+}
+
+/// The value of an AIGER literal under the values of the variables.
+pub fn value(values: &[bool], l: u32) -> bool {
+    values[(l / 2) as usize] != (l & 1 == 1)
+}
+
+/// A circuit and the property to check in its first time step. The inputs
+/// are variables 1 to I of the problem, and the latches I+1 to I+L.
+pub struct AigerInput {
+    pub aig: Aig,
+    // an index into the properties
+    pub property: usize,
+}
+
+impl AigerInput {
+    pub fn read(path: &str, property: usize) -> Result<AigerInput, String> {
+        let aig = read_aiger(path)?;
+        let count = aig.properties().len();
+        if property >= count {
+            return Err(match count {
+                0 => format!("{}: the circuit has no output and no bad-state property", path),
+                _ => format!("{}: property {} does not exist, there are {}", path, property, count),
+            });
+        }
+        return Ok(AigerInput { aig, property });
+    }
+
+    pub fn variables(&self) -> u32 {
+        (self.aig.inputs.len() + self.aig.latches.len()) as u32
+    }
+
+    pub fn name(&self, v: u32) -> String {
+        let i = self.aig.inputs.len();
+        let k = v as usize - 1;
+        return match k.checked_sub(i) {
+            None => self.aig.input_names[k].clone().unwrap_or_else(|| format!("i{}", k)),
+            Some(k) => self.aig.latch_names[k].clone().unwrap_or_else(|| format!("l{}", k)),
+        };
+    }
+
+    pub fn load(&self, heuristics: &mut impl Heuristics) -> Problem {
+        let aig = &self.aig;
+        let mut problem = Problem::new();
+        // the gate variables come after the inputs and latches
+        let mut sink = ProblemSink {
+            problem: &mut problem,
+            heuristics,
+            next_variable: self.variables(),
+        };
+        let mut literals = vec![None; aig.max_variable as usize + 1];
+        for (k, l) in aig.inputs.iter().chain(aig.latches.iter().map(|l| &l.literal)).enumerate() {
+            literals[(l / 2) as usize] = Some(Literal::from_dimacs(k as i32 + 1));
+        }
+        for latch in aig.latches.iter() {
+            let l = literal(&literals, latch.literal);
+            match latch.reset {
+                0 => sink.add_clause(&[!l]),
+                1 => sink.add_clause(&[l]),
+                _ => {}
+            }
+        }
+        let mut roots = vec![aig.properties()[self.property]];
+        roots.extend(aig.constraints.iter());
+        aig.encode(&mut sink, &mut literals, &roots);
+        for root in roots {
+            sink.add_clause(&[literal(&literals, root)]);
+        }
+        return problem;
+    }
+
+    // the values of the inputs and the initial values of the latches in a
+    // model; a variable missing from it is false
+    fn start(&self, model: &Model) -> (Vec<bool>, Vec<bool>) {
+        let value = |v: usize| model.get(&(v as u32 + 1)).copied().unwrap_or(false);
+        let i = self.aig.inputs.len();
+        let inputs = (0..i).map(value).collect();
+        let latches = self
+            .aig
+            .latches
+            .iter()
+            .enumerate()
+            .map(|(k, latch)| match latch.reset {
+                0 => false,
+                1 => true,
+                _ => value(i + k),
+            })
+            .collect();
+        return (inputs, latches);
+    }
+
+    /// Simulates the circuit on a model, whose latches must have their reset
+    /// values.
+    pub fn check(&self, model: &Model) -> Result<(), String> {
+        let (inputs, latches) = self.start(model);
+        let i = self.aig.inputs.len();
+        for (k, value) in latches.iter().enumerate() {
+            if model.get(&((i + k) as u32 + 1)).copied().unwrap_or(false) != *value {
+                return Err(format!("the solution does not give latch {} its reset value {}", k, *value as u8));
+            }
+        }
+        let trace = Trace {
+            latches,
+            inputs: vec![inputs],
+        };
+        return self.aig.check_trace(&trace, self.aig.properties()[self.property]);
+    }
+
+    /// Writes the result in the AIGER witness format.
+    pub fn write_witness(&self, path: &str, model: Option<&Model>) -> Result<(), String> {
+        let start = model.map(|model| self.start(model));
+        let trace = start.as_ref().map(|(inputs, latches)| Trace {
+            latches: latches.clone(),
+            inputs: vec![inputs.clone()],
+        });
+        // the property cannot hold in the first step; with latches, it might
+        // in a later one
+        return write_witness(path, self.property, trace.as_ref(), self.aig.latches.is_empty());
+    }
+}
+
+/// Writes a result in the AIGER witness format: `1`, the property, the
+/// initial latch values and the inputs of every step, and `.` for a
+/// counterexample; without one, `0` if the property is `proved`, `2` if it is
+/// unknown.
+pub fn write_witness(path: &str, property: usize, trace: Option<&Trace>, proved: bool) -> Result<(), String> {
+    let bits = |values: &[bool]| values.iter().map(|b| if *b { '1' } else { '0' }).collect::<String>();
+    let text = match trace {
+        Some(trace) => {
+            let mut text = format!("1\nb{}\n{}\n", property, bits(&trace.latches));
+            for inputs in trace.inputs.iter() {
+                text += &bits(inputs);
+                text += "\n";
+            }
+            text + ".\n"
+        }
+        None => format!("{}\nb{}\n.\n", if proved { 0 } else { 2 }, property),
+    };
+    return fs::write(path, text).map_err(|e| format!("{}: {}", path, e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::check_translation;
+    use crate::input::Translated;
+    use crate::rng::Rng;
+    use crate::testing::temp_file;
+
+    // the number of models of the circuit in `data`, checking `property`
+    fn count_models(data: &[u8], property: usize) -> u64 {
+        let path = temp_file("aiger.aig", data);
+        let input = AigerInput::read(&path, property).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        return check_translation(&Translated::Aiger(input), "vsids");
+    }
+
+    #[test]
+    fn small_circuits_have_their_models() {
+        // an AND of two inputs, and its negation
+        assert_eq!(count_models(b"aag 3 2 0 1 1\n2\n4\n6\n6 2 4\n", 0), 1);
+        assert_eq!(count_models(b"aag 3 2 0 1 1\n2\n4\n7\n6 2 4\n", 0), 3);
+        // constant outputs
+        assert_eq!(count_models(b"aag 1 1 0 2 0\n2\n0\n1\n", 0), 0);
+        assert_eq!(count_models(b"aag 1 1 0 2 0\n2\n0\n1\n", 1), 2);
+        // latches reset to 0, 1 and themselves, the bad state their AND
+        // under the constraint that the input is true
+        let text = b"aag 6 1 3 0 2 1 1\n2\n4 2 0\n6 2 1\n8 2 8\n12\n2\n10 4 6\n12 10 8\n";
+        assert_eq!(count_models(text, 0), 0);
+        let text = b"aag 6 1 3 0 2 1 1\n2\n4 2 1\n6 2 1\n8 2 8\n12\n2\n10 4 6\n12 10 8\n";
+        assert_eq!(count_models(text, 0), 1);
+        // the same in binary: the gates as the deltas 10-6, 6-4, 12-10, 10-8
+        let mut binary = b"aig 6 1 3 0 2 1 1\n2 1\n2 1\n2 8\n12\n2\n".to_vec();
+        binary.extend([4, 2, 2, 2]);
+        assert_eq!(count_models(&binary, 0), 1);
+    }
+
+    // A random circuit, ASCII and binary: inputs, latches and gates numbered
+    // in that order, with a bad state property and a constraint.
+    fn random_circuit(rng: &mut Rng) -> (String, Vec<u8>) {
+        let (i, l, a) = (1 + rng.below(3) as u32, rng.below(3) as u32, rng.below(6) as u32);
+        let m = i + l + a;
+        let mut any = |below: u32| rng.below(2 * below as u64 + 2) as u32;
+        let latches: Vec<(u32, u32)> = (0..l)
+            .map(|k| {
+                let reset = [0, 1, 2 * (i + k + 1)][any(1) as usize % 3];
+                (any(m), reset)
+            })
+            .collect();
+        let ands: Vec<(u32, u32)> = (0..a)
+            .map(|k| {
+                let lhs = 2 * (i + l + k + 1);
+                let (x, y) = (any(lhs / 2 - 1), any(lhs / 2 - 1));
+                (x.max(y), x.min(y))
+            })
+            .collect();
+        let (bad, constraint) = (any(m), any(m));
+
+        let header = format!("{} {} {} 0 {} 1 1\n", m, i, l, a);
+        let mut text = format!("aag {}", header);
+        let mut binary = format!("aig {}", header);
+        for k in 0..i {
+            text += &format!("{}\n", 2 * (k + 1));
+        }
+        for (k, (next, reset)) in latches.iter().enumerate() {
+            text += &format!("{} {} {}\n", 2 * (i + k as u32 + 1), next, reset);
+            binary += &format!("{} {}\n", next, reset);
+        }
+        text += &format!("{}\n{}\n", bad, constraint);
+        binary += &format!("{}\n{}\n", bad, constraint);
+        let mut binary = binary.into_bytes();
+        for (k, (rhs0, rhs1)) in ands.iter().enumerate() {
+            let lhs = 2 * (i + l + k as u32 + 1);
+            text += &format!("{} {} {}\n", lhs, rhs0, rhs1);
+            for mut delta in [lhs - rhs0, rhs0 - rhs1] {
+                while delta >= 0x80 {
+                    binary.push((delta & 0x7f) as u8 | 0x80);
+                    delta >>= 7;
+                }
+                binary.push(delta as u8);
+            }
+        }
+        return (text, binary);
+    }
+
+    #[test]
+    fn random_circuits_have_their_models() {
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let (text, binary) = random_circuit(&mut rng);
+            assert_eq!(count_models(text.as_bytes(), 0), count_models(&binary, 0), "{}", text);
+        }
+    }
+}
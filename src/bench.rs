@@ -0,0 +1,248 @@
+use crate::heuristics::registry::{self, HeuristicsConfig};
+use crate::runner::{self, Outcome, Run};
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Runs every configuration on every labelled instance and reports one row per
+// run plus PAR-2 and solved counts per configuration. Replaces run.sh and the
+// log scraping in plotter.py.
+
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// Directory of instances expected to be satisfiable (repeatable)
+    #[arg(long, value_name = "DIR")]
+    pub sat: Vec<String>,
+
+    /// Directory of instances expected to be unsatisfiable (repeatable)
+    #[arg(long, value_name = "DIR")]
+    pub unsat: Vec<String>,
+
+    /// Configuration to run, a heuristics name optionally followed by a
+    /// --heuristics-config file, e.g. vsids:tuned.toml (repeatable)
+    #[arg(long = "config", value_name = "NAME[:FILE]", default_value = "vsids")]
+    pub configs: Vec<String>,
+
+    /// Time limit per run in seconds
+    #[arg(long, default_value_t = 60.0)]
+    pub timeout: f64,
+
+    /// Number of runs in parallel
+    #[arg(long, default_value_t = 1)]
+    pub threads: usize,
+
+    /// Write one row per run as CSV to FILE ("-" for stdout)
+    #[arg(long, value_name = "FILE")]
+    pub csv: Option<String>,
+
+    /// Write the runs and the per-configuration summary as JSON to FILE ("-" for stdout)
+    #[arg(long, value_name = "FILE")]
+    pub json: Option<String>,
+
+    /// Seed passed to every run
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    #[arg(long)]
+    pub no_bcp: bool,
+}
+
+struct Config {
+    label: String,
+    heuristics: String,
+    options: HeuristicsConfig,
+}
+
+fn parse_config(spec: &str) -> Result<Config, String> {
+    let (heuristics, options) = match spec.split_once(':') {
+        Some((name, path)) => (name, HeuristicsConfig::from_file(path)?),
+        None => (spec, HeuristicsConfig::default()),
+    };
+    registry::validate(&options)?;
+    if !registry::HEURISTICS.iter().any(|e| e.name == heuristics) {
+        return Err(format!("unknown heuristics '{}', see --list-heuristics", heuristics));
+    }
+    return Ok(Config {
+        label: String::from(spec),
+        heuristics: String::from(heuristics),
+        options,
+    });
+}
+
+#[derive(Debug, Serialize)]
+struct Record {
+    config: String,
+    instance: String,
+    expected: &'static str,
+    result: &'static str,
+    correct: bool,
+    time: f64,
+    par2: f64,
+    #[serde(flatten)]
+    counters: BTreeMap<&'static str, u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    config: String,
+    instances: usize,
+    solved: usize,
+    sat: usize,
+    unsat: usize,
+    timeouts: usize,
+    wrong: usize,
+    // mean penalised run time, in seconds
+    par2: f64,
+}
+
+fn summarise(config: &Config, records: &[Record]) -> Summary {
+    let runs: Vec<&Record> = records.iter().filter(|r| r.config == config.label).collect();
+    let count = |result: &str| runs.iter().filter(|r| r.result == result && r.correct).count();
+    let sat = count(Outcome::Sat.as_str());
+    let unsat = count(Outcome::Unsat.as_str());
+    return Summary {
+        config: config.label.clone(),
+        instances: runs.len(),
+        solved: sat + unsat,
+        sat,
+        unsat,
+        timeouts: runs.iter().filter(|r| r.result == Outcome::Timeout.as_str()).count(),
+        wrong: runs.iter().filter(|r| !r.correct).count(),
+        par2: runs.iter().map(|r| r.par2).sum::<f64>() / runs.len().max(1) as f64,
+    };
+}
+
+// quotes a CSV field if needed
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        return format!("\"{}\"", s.replace('"', "\"\""));
+    }
+    return String::from(s);
+}
+
+fn write_csv(w: &mut dyn Write, records: &[Record]) -> io::Result<()> {
+    let counters: Vec<&str> = records.first().map(|r| r.counters.keys().copied().collect()).unwrap_or_default();
+    writeln!(w, "config,instance,expected,result,correct,time,par2,{}", counters.join(","))?;
+    for r in records.iter() {
+        let values: Vec<String> = r.counters.values().map(|v| v.to_string()).collect();
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{},{}",
+            csv_field(&r.config),
+            csv_field(&r.instance),
+            r.expected,
+            r.result,
+            r.correct,
+            r.time,
+            r.par2,
+            values.join(",")
+        )?;
+    }
+    return Ok(());
+}
+
+fn write_output(path: &str, write: impl Fn(&mut dyn Write) -> io::Result<()>) -> Result<(), String> {
+    let result = if path == "-" {
+        write(&mut io::stdout().lock())
+    } else {
+        fs::File::create(path).and_then(|mut f| write(&mut f))
+    };
+    return result.map_err(|e| format!("{}: {}", path, e));
+}
+
+pub fn bench(args: &BenchArgs) -> Result<(), String> {
+    let configs = args.configs.iter().map(|c| parse_config(c)).collect::<Result<Vec<_>, _>>()?;
+    let mut instances = Vec::<(String, Outcome)>::new();
+    for dir in args.sat.iter() {
+        instances.extend(runner::list_instances(dir)?.into_iter().map(|f| (f, Outcome::Sat)));
+    }
+    for dir in args.unsat.iter() {
+        instances.extend(runner::list_instances(dir)?.into_iter().map(|f| (f, Outcome::Unsat)));
+    }
+    if instances.is_empty() {
+        return Err(String::from("no instances, pass --sat DIR and/or --unsat DIR"));
+    }
+
+    let timeout = runner::timeout(args.timeout)?;
+    let jobs: Vec<(&Config, &(String, Outcome))> =
+        configs.iter().flat_map(|c| instances.iter().map(move |i| (c, i))).collect();
+    let next_job = AtomicUsize::new(0);
+    let records = Mutex::new(Vec::<(usize, Record)>::new());
+
+    thread::scope(|s| {
+        for _ in 0..args.threads.max(1) {
+            s.spawn(|| loop {
+                let j = next_job.fetch_add(1, Ordering::Relaxed);
+                let Some((config, (instance, expected))) = jobs.get(j) else {
+                    return;
+                };
+                let run = Run {
+                    input: instance,
+                    use_bcp: !args.no_bcp,
+                    seed: args.seed,
+                    timeout,
+                };
+                // names were checked by parse_config
+                let result = registry::dispatch(&config.heuristics, &config.options, run).unwrap();
+                info!(target: "bench", "{} {} {} in {:?}", config.label, instance, result.outcome.as_str(), result.profiler.duration());
+                let correct = result.outcome == *expected || result.outcome == Outcome::Timeout;
+                let record = Record {
+                    config: config.label.clone(),
+                    instance: instance.clone(),
+                    expected: expected.as_str(),
+                    result: result.outcome.as_str(),
+                    correct,
+                    time: result.profiler.duration().as_secs_f64(),
+                    // a wrong answer counts as unsolved
+                    par2: if correct { result.par(timeout, 2.0) } else { 2.0 * args.timeout },
+                    counters: result.profiler.counters().into_iter().collect(),
+                };
+                records.lock().unwrap().push((j, record));
+            });
+        }
+    });
+
+    // report in job order, whatever order the threads finished in
+    let mut records = records.into_inner().unwrap();
+    records.sort_by_key(|(j, _)| *j);
+    let records: Vec<Record> = records.into_iter().map(|(_, r)| r).collect();
+    let summaries: Vec<Summary> = configs.iter().map(|c| summarise(c, &records)).collect();
+
+    if let Some(path) = &args.csv {
+        write_output(path, |w| write_csv(w, &records))?;
+    }
+    if let Some(path) = &args.json {
+        write_output(path, |w| {
+            let json = serde_json::json!({ "runs": records, "summary": summaries });
+            serde_json::to_writer_pretty(&mut *w, &json)?;
+            writeln!(w)
+        })?;
+    }
+
+    // keep stdout clean when a table is written there
+    let mut out: Box<dyn Write> = if args.csv.as_deref() == Some("-") || args.json.as_deref() == Some("-") {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    let header = ["config", "instances", "solved", "sat", "unsat", "timeouts", "wrong", "par2"];
+    _ = writeln!(out, "{:<24} {:>9} {:>7} {:>5} {:>5} {:>8} {:>5} {:>10}", header[0], header[1], header[2], header[3], header[4], header[5], header[6], header[7]);
+    for s in summaries.iter() {
+        _ = writeln!(
+            out,
+            "{:<24} {:>9} {:>7} {:>5} {:>5} {:>8} {:>5} {:>10.3}",
+            s.config, s.instances, s.solved, s.sat, s.unsat, s.timeouts, s.wrong, s.par2
+        );
+    }
+
+    let wrong: usize = summaries.iter().map(|s| s.wrong).sum();
+    if wrong > 0 {
+        return Err(format!("{} runs disagree with the expected result", wrong));
+    }
+    return Ok(());
+}
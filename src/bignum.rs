@@ -0,0 +1,152 @@
+use core::fmt;
+use std::cmp::Ordering;
+use std::ops::{AddAssign, Mul, Shl};
+
+// Unsigned integers of any size, for model counts: a formula with more than 64
+// free variables already has more models than a u64 holds. Only what the
+// counters need is here: addition, multiplication, shifts and decimal output.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BigUint {
+    // base 2^32, least significant limb first, no leading zero limbs
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { limbs: Vec::new() }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.limbs.as_slice() {
+            [] => Some(0),
+            [lo] => Some(*lo as u64),
+            [lo, hi] => Some(*lo as u64 | (*hi as u64) << 32),
+            _ => None,
+        }
+    }
+
+    fn trimmed(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+
+    // divides in place by a small divisor, returns the remainder
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let x = (rem << 32) | *limb as u64;
+            *limb = (x / divisor as u64) as u32;
+            rem = x % divisor as u64;
+        }
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        rem as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(x: u64) -> Self {
+        BigUint {
+            limbs: vec![x as u32, (x >> 32) as u32],
+        }
+        .trimmed()
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        let mut carry = 0u64;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let x = *limb as u64 + other.limbs.get(i).copied().unwrap_or(0) as u64 + carry;
+            *limb = x as u32;
+            carry = x >> 32;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+impl Mul<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let x = limbs[i + j] as u64 + *a as u64 * *b as u64 + carry;
+                limbs[i + j] = x as u32;
+                carry = x >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint { limbs }.trimmed()
+    }
+}
+
+impl Shl<usize> for BigUint {
+    type Output = BigUint;
+
+    fn shl(self, bits: usize) -> BigUint {
+        if self.is_zero() {
+            return self;
+        }
+        let (words, bits) = (bits / 32, bits % 32);
+        let mut limbs = vec![0u32; words];
+        let mut carry = 0u32;
+        for limb in self.limbs.iter() {
+            limbs.push((limb << bits) | carry);
+            carry = if bits == 0 { 0 } else { limb >> (32 - bits) };
+        }
+        limbs.push(carry);
+        BigUint { limbs }.trimmed()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // nine decimal digits at a time, least significant group first
+        let mut x = self.clone();
+        let mut groups = Vec::new();
+        loop {
+            groups.push(x.div_rem_small(1_000_000_000));
+            if x.is_zero() {
+                break;
+            }
+        }
+        write!(f, "{}", groups.pop().unwrap())?;
+        for g in groups.iter().rev() {
+            write!(f, "{:09}", g)?;
+        }
+        Ok(())
+    }
+}
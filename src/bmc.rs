@@ -0,0 +1,237 @@
+use crate::aiger::{self, Aig, Trace};
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::{self, HeuristicsConfig, HeuristicsVisitor};
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use crate::verify::Model;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+// Bounded model checking of an AIGER circuit: is the property (a bad state,
+// or an output) true after k steps, for k = 0, 1, ..., K?
+//
+// A single incremental solver holds the unrolled circuit. Bound k adds time
+// frame k: fresh variables for the inputs, the latches of the previous frame's
+// next-state functions (their reset values in frame 0), and the gates in the
+// cone of influence of the property and the constraints. The constraints hold
+// in every frame; the property is only assumed for the frame being checked.
+// When bound k has no counterexample, the property is false in frame k for
+// good, which only cuts off counterexamples that are not the shallowest.
+
+#[derive(clap::Args, Debug)]
+pub struct BmcArgs {
+    /// The AIGER file (.aag or .aig)
+    pub input: String,
+
+    /// Check the bounds 0 to K
+    #[arg(long, short = 'k', default_value_t = 20)]
+    pub bound: usize,
+
+    /// The bad-state property to check, or the output if there are none
+    #[arg(long, default_value_t = 0)]
+    pub property: usize,
+
+    #[arg(long, default_value_t = String::from("vsids"))]
+    pub heuristics: String,
+
+    #[arg(long)]
+    pub no_bcp: bool,
+
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Write the result in the AIGER witness format to FILE
+    #[arg(long, value_name = "FILE")]
+    pub witness: Option<String>,
+}
+
+// The unrolled circuit: the literal of every AIGER variable encoded so far in
+// each frame.
+struct Unrolling<'a> {
+    aig: &'a Aig,
+    frames: Vec<Vec<Option<Literal>>>,
+}
+
+impl Unrolling<'_> {
+    // Adds the next frame, with the gates needed for `roots`.
+    fn add_frame(&mut self, solver: &mut Solver<impl Heuristics>, roots: &[u32]) {
+        let aig = self.aig;
+        let mut literals = vec![None; aig.max_variable as usize + 1];
+        for i in aig.inputs.iter() {
+            literals[(i / 2) as usize] = Some(fresh_literal(solver));
+        }
+        match self.frames.last_mut() {
+            None => {
+                for latch in aig.latches.iter() {
+                    let l = fresh_literal(solver);
+                    match latch.reset {
+                        0 => solver.add_clause(&[!l]),
+                        1 => solver.add_clause(&[l]),
+                        _ => {}
+                    }
+                    literals[(latch.literal / 2) as usize] = Some(l);
+                }
+            }
+            Some(previous) => {
+                let next: Vec<u32> = aig.latches.iter().map(|l| l.next).collect();
+                aig.encode(solver, previous, &next);
+                for latch in aig.latches.iter() {
+                    literals[(latch.literal / 2) as usize] = Some(aiger::literal(previous, latch.next));
+                }
+                // one constant for all frames
+                literals[0] = previous[0];
+            }
+        }
+        aig.encode(solver, &mut literals, roots);
+        self.frames.push(literals);
+    }
+
+    // the inputs of every frame and the initial latch values in a model; a
+    // variable missing from it is false
+    fn trace(&self, model: &Model) -> Trace {
+        let value = |l: Literal| model.get(&l.variable.index).copied().unwrap_or(false) == (l.polarity == Polarity::On);
+        let latches = self.aig.latches.iter().map(|l| value(aiger::literal(&self.frames[0], l.literal))).collect();
+        let inputs = self
+            .frames
+            .iter()
+            .map(|frame| self.aig.inputs.iter().map(|i| value(aiger::literal(frame, *i))).collect())
+            .collect();
+        return Trace { latches, inputs };
+    }
+}
+
+fn fresh_literal(solver: &mut Solver<impl Heuristics>) -> Literal {
+    Literal {
+        variable: solver.add_variable(),
+        polarity: Polarity::On,
+    }
+}
+
+struct Check<'a> {
+    args: &'a BmcArgs,
+    aig: &'a Aig,
+}
+
+impl HeuristicsVisitor for Check<'_> {
+    // the shallowest counterexample
+    type Output = Result<Option<Trace>, String>;
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+        let mut h = H::with_options(options);
+        h.set_use_bcp(!self.args.no_bcp);
+        h.set_seed(self.args.seed);
+        let mut solver = Solver::new(Problem::new(), h);
+        let mut unrolling = Unrolling { aig: self.aig, frames: Vec::new() };
+        let property = self.aig.properties()[self.args.property];
+        let mut roots = vec![property];
+        roots.extend(self.aig.constraints.iter());
+        let interrupt = AtomicBool::new(false);
+        for k in 0..=self.args.bound {
+            let start = Instant::now();
+            unrolling.add_frame(&mut solver, &roots);
+            let frame = &unrolling.frames[k];
+            for c in self.aig.constraints.iter() {
+                solver.add_clause(&[aiger::literal(frame, *c)]);
+            }
+            let bad = aiger::literal(frame, property);
+            let solution = solver.solve(&[bad], &interrupt);
+            println!("BOUND {}: {} ({:.3}s)", k, if solution.is_some() { "SAT" } else { "UNSAT" }, start.elapsed().as_secs_f64());
+            if let Some(s) = solution {
+                let trace = unrolling.trace(&crate::enumerate::model(&s));
+                self.aig.check_trace(&trace, property)?;
+                return Ok(Some(trace));
+            }
+            solver.add_clause(&[!bad]);
+        }
+        return Ok(None);
+    }
+}
+
+/// Prints the result of every bound, then the shallowest counterexample or
+/// that there is none up to the last bound.
+pub fn bmc(args: &BmcArgs) -> Result<(), String> {
+    let aig = aiger::read_aiger(&args.input)?;
+    let count = aig.properties().len();
+    if args.property >= count {
+        return Err(format!("{}: property {} does not exist, there are {}", args.input, args.property, count));
+    }
+    let check = Check { args, aig: &aig };
+    let trace = registry::dispatch(&args.heuristics, &HeuristicsConfig::default(), check)??;
+    match &trace {
+        Some(trace) => {
+            println!("RESULT: COUNTEREXAMPLE AT {}", trace.inputs.len() - 1);
+            print_values("INIT:", &trace.latches, &aig.latch_names, "l");
+            for (step, inputs) in trace.inputs.iter().enumerate() {
+                print_values(&format!("STEP {}:", step), inputs, &aig.input_names, "i");
+            }
+        }
+        None => println!("RESULT: NO COUNTEREXAMPLE UP TO {}", args.bound),
+    }
+    if let Some(path) = &args.witness {
+        // without latches, bound 0 covers every run
+        aiger::write_witness(path, args.property, trace.as_ref(), aig.latches.is_empty())?;
+    }
+    return Ok(());
+}
+
+// the values under the names of the symbol table, `i0`, `l0`... without one
+fn print_values(label: &str, values: &[bool], names: &[Option<String>], prefix: &str) {
+    print!("{}", label);
+    for (k, value) in values.iter().enumerate() {
+        match &names[k] {
+            Some(name) => print!(" {}={}", name, *value as u8),
+            None => print!(" {}{}={}", prefix, k, *value as u8),
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::registry::HEURISTICS;
+    use crate::testing::temp_file;
+
+    // the depth of the shallowest counterexample up to `bound`, under every
+    // heuristics
+    fn depth(text: &str, bound: usize) -> Option<usize> {
+        let path = temp_file("bmc.aag", text);
+        let aig = aiger::read_aiger(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut depths = Vec::new();
+        for entry in HEURISTICS.iter() {
+            let args = BmcArgs {
+                input: path.clone(),
+                bound,
+                property: 0,
+                heuristics: String::from(entry.name),
+                no_bcp: false,
+                seed: 0,
+                witness: None,
+            };
+            let check = Check { args: &args, aig: &aig };
+            let trace = registry::dispatch(entry.name, &HeuristicsConfig::default(), check).unwrap().unwrap();
+            depths.push(trace.map(|t| t.inputs.len() - 1));
+        }
+        assert!(depths.iter().all(|d| *d == depths[0]), "{:?}", depths);
+        return depths[0];
+    }
+
+    #[test]
+    fn counterexamples_are_the_shallowest() {
+        // a latch that copies the input: bad one step after the input is set
+        assert_eq!(depth("aag 2 1 1 0 0 1\n2\n4 2 0\n4\n", 5), Some(1));
+        // a two-bit counter from 0, bad at 3
+        let counter = "aag 6 0 2 1 4\n2 3\n4 11\n12\n6 2 5\n8 3 4\n10 7 9\n12 2 4\n";
+        assert_eq!(depth(counter, 10), Some(3));
+        assert_eq!(depth(counter, 2), None);
+    }
+
+    #[test]
+    fn safe_circuits_have_no_counterexample() {
+        // the second latch copies the first, which stays 0
+        assert_eq!(depth("aag 2 0 2 1 0\n2 2\n4 2\n4\n", 8), None);
+        // the input can never set a latch that is reset to 0 and kept off
+        assert_eq!(depth("aag 3 1 1 1 1\n2\n4 6\n4\n6 2 4\n", 8), None);
+    }
+}
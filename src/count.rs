@@ -0,0 +1,74 @@
+use crate::verify;
+use std::time::Instant;
+
+// Model counting (#SAT): the number of satisfying assignments of a formula
+// over all the variables of its `p cnf` line.
+
+// exact counting with components and caching
+mod exact;
+
+// estimates with random XOR hashes
+mod approx;
+
+#[derive(clap::Args, Debug)]
+pub struct CountArgs {
+    /// The CNF file
+    pub input: String,
+
+    /// Estimate the count with random XOR hashes instead of counting exactly
+    #[arg(long)]
+    pub approximate: bool,
+
+    /// With --approximate: the estimate is within a factor 1+EPSILON of the
+    /// count...
+    #[arg(long, default_value_t = 0.8)]
+    pub epsilon: f64,
+
+    /// ...with probability at least 1-DELTA
+    #[arg(long, default_value_t = 0.2)]
+    pub delta: f64,
+
+    /// Seed for the hashes of --approximate
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Size of the component cache in MB; it is cleared when full
+    #[arg(long, default_value_t = 512)]
+    pub cache_mb: usize,
+}
+
+/// Prints the count in the format of the model counting competition
+/// (`s mc <count>`), after a few comment lines of statistics.
+pub fn count(args: &CountArgs) -> Result<(), String> {
+    let cnf = verify::read_cnf(&args.input)?;
+    let used = cnf.max_variable();
+    let num_vars = cnf.declared_variables.unwrap_or(0).max(used);
+    let start = Instant::now();
+
+    let n = if args.approximate {
+        if args.epsilon <= 0.0 || args.delta <= 0.0 || args.delta >= 1.0 {
+            return Err(String::from("--epsilon must be positive and --delta between 0 and 1"));
+        }
+        println!("c approximate count, epsilon {} delta {} seed {}", args.epsilon, args.delta, args.seed);
+        let options = approx::Options {
+            epsilon: args.epsilon,
+            delta: args.delta,
+            seed: args.seed,
+            cache_bytes: args.cache_mb << 20,
+        };
+        approx::approx_count(&cnf, num_vars, &options)
+    } else {
+        let mut counter = exact::Counter::new(num_vars, &cnf.clauses, args.cache_mb << 20);
+        for (vars, parity) in cnf.xor_constraints() {
+            counter.add_xor(&vars, parity);
+        }
+        let n = counter.count();
+        let s = &counter.stats;
+        println!("c decisions {} conflicts {}", s.decisions, s.conflicts);
+        println!("c components {} cache hits {}", s.components, s.cache_hits);
+        n
+    };
+    println!("c time {:.3}s", start.elapsed().as_secs_f64());
+    println!("s mc {}", n);
+    return Ok(());
+}
@@ -0,0 +1,123 @@
+use super::exact::Counter;
+use crate::bignum::BigUint;
+use crate::rng::Rng;
+use crate::verify::Cnf;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Approximate model counting with random XOR hashes (ApproxMC, Chakraborty,
+// Meel and Vardi 2013/2016). m random XOR constraints split the models into
+// 2^m cells of about equal size; once a cell holds fewer than `threshold`
+// models, its size times 2^m estimates the count. The median of `rounds`
+// such estimates is within a factor 1+epsilon of the count with probability
+// at least 1-delta.
+//
+// Cells are counted by the exact counter with the XORs as constraints and
+// `threshold` as its limit, so a large cell is abandoned early. The XORs are
+// brought into reduced row echelon form first and the counter never branches
+// on their pivots, which the other variables of their row imply.
+
+pub struct Options {
+    pub epsilon: f64,
+    pub delta: f64,
+    pub seed: u64,
+    pub cache_bytes: usize,
+}
+
+struct Xor {
+    vars: Vec<u32>,
+    parity: bool,
+}
+
+// Gauss-Jordan elimination over GF(2): the XORs in reduced row echelon form,
+// each with a pivot variable no other row contains, or None if they
+// contradict each other.
+fn eliminate(xors: &[Xor]) -> Option<Vec<(Xor, u32)>> {
+    let mut rows: Vec<(BTreeSet<u32>, bool)> =
+        xors.iter().map(|x| (x.vars.iter().copied().collect(), x.parity)).collect();
+    let mut pivots = Vec::new();
+    for i in 0..rows.len() {
+        let Some(pivot) = rows[i].0.first().copied() else {
+            if rows[i].1 {
+                return None;
+            }
+            continue;
+        };
+        let (row, parity) = rows[i].clone();
+        for (j, other) in rows.iter_mut().enumerate() {
+            if j != i && other.0.contains(&pivot) {
+                other.0 = other.0.symmetric_difference(&row).copied().collect();
+                other.1 ^= parity;
+            }
+        }
+        pivots.push((i, pivot));
+    }
+    return Some(
+        pivots
+            .into_iter()
+            .map(|(i, pivot)| (Xor { vars: rows[i].0.iter().copied().collect(), parity: rows[i].1 }, pivot))
+            .collect(),
+    );
+}
+
+// the number of models in the cell given by `xors`, up to `threshold`
+fn cell_size(cnf: &Cnf, num_vars: u32, xors: &[Xor], threshold: u64, options: &Options) -> u64 {
+    let Some(xors) = eliminate(xors) else {
+        return 0;
+    };
+    let mut counter = Counter::new(num_vars, &cnf.clauses, options.cache_bytes);
+    for (vars, parity) in cnf.xor_constraints() {
+        counter.add_xor(&vars, parity);
+    }
+    for (xor, pivot) in xors.iter() {
+        counter.add_xor(&xor.vars, xor.parity);
+        counter.set_implied(*pivot);
+    }
+    counter.set_limit(threshold);
+    return counter.count().to_u64().map_or(threshold, |n| n.min(threshold));
+}
+
+pub fn approx_count(cnf: &Cnf, num_vars: u32, options: &Options) -> BigUint {
+    let eps = options.epsilon;
+    let threshold = (1.0 + 9.84 * (1.0 + eps / (1.0 + eps)) * (1.0 + 1.0 / eps).powi(2)).ceil() as u64;
+    let rounds = (17.0 * (3.0 / options.delta).log2()).ceil() as usize;
+    let mut rng = Rng::new(options.seed);
+
+    // hashes range over the variables up to the largest one in a clause;
+    // every variable after it doubles the count
+    let used = cnf.max_variable();
+    let vars: Vec<u32> = (1..=used).collect();
+    let free = (num_vars - used) as usize;
+
+    let all = cell_size(cnf, used, &[], threshold, options);
+    if all < threshold {
+        return BigUint::from(all) << free;
+    }
+
+    let mut estimates = Vec::new();
+    let mut m = 1;
+    for _ in 0..rounds {
+        // the first m of the same XORs make the cells for every m, so cell
+        // sizes shrink as m grows
+        let xors: Vec<Xor> = (0..vars.len())
+            .map(|_| Xor {
+                vars: vars.iter().copied().filter(|_| rng.below(2) == 1).collect(),
+                parity: rng.below(2) == 1,
+            })
+            .collect();
+        let mut sizes = BTreeMap::<usize, u64>::new();
+        let mut size =
+            |m: usize| *sizes.entry(m).or_insert_with(|| cell_size(cnf, used, &xors[..m], threshold, options));
+        // start from the m of the previous round, then walk to the smallest
+        // m whose cell is below the threshold
+        m = m.clamp(1, vars.len());
+        while m < vars.len() && size(m) >= threshold {
+            m += 1;
+        }
+        while m > 1 && size(m - 1) < threshold {
+            m -= 1;
+        }
+        estimates.push(BigUint::from(size(m)) << m);
+    }
+    estimates.sort();
+    return estimates.swap_remove(estimates.len() / 2) << free;
+}
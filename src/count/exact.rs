@@ -0,0 +1,424 @@
+use crate::bignum::BigUint;
+use std::collections::HashMap;
+
+// Exact model counting by DPLL search over the clauses as integer lists.
+// After each decision and its unit propagation the open constraints are split
+// into components that share no unassigned variable; the count of a formula
+// is the product of the counts of its components, times two for every
+// variable left in no open constraint. Component counts are cached under their
+// residual constraints, so a component met again on another branch is not
+// searched twice.
+//
+// Besides clauses, the counter takes XOR constraints (for the cells of the
+// approximate counter), and a limit: once a count reaches it, the search
+// stops and reports some number at least as large.
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub decisions: u64,
+    pub conflicts: u64,
+    pub components: u64,
+    pub cache_hits: u64,
+}
+
+enum Status {
+    Satisfied,
+    Conflict,
+    Unit(i32),
+    Open,
+}
+
+struct Constraint {
+    // for an XOR, the variables
+    literals: Vec<i32>,
+    // Some(p) for the XOR of the variables being p, None for a clause
+    parity: Option<bool>,
+}
+
+pub struct Counter {
+    constraints: Vec<Constraint>,
+    // the constraints each variable occurs in
+    occurrences: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    // assigned variables, in order
+    trail: Vec<u32>,
+    cache: HashMap<Vec<i32>, BigUint>,
+    // bytes taken by the keys of the cache, and the most it may take
+    cache_bytes: usize,
+    cache_limit: usize,
+    limit: Option<BigUint>,
+    // variables implied by others (e.g. the pivots of XORs), never branched on
+    implied: Vec<bool>,
+    // scratch space for components() and the branching scores
+    constraint_stamp: Vec<u64>,
+    var_stamp: Vec<u64>,
+    stamp: u64,
+    score: Vec<u32>,
+    pub stats: Stats,
+}
+
+impl Counter {
+    /// `num_vars` may exceed the variables of `clauses`; the others are free.
+    /// The cache is cleared whenever its keys take more than `cache_limit`
+    /// bytes.
+    pub fn new(num_vars: u32, clauses: &[Vec<i32>], cache_limit: usize) -> Self {
+        let size = num_vars as usize + 1;
+        let mut counter = Counter {
+            constraints: Vec::new(),
+            occurrences: vec![Vec::new(); size],
+            values: vec![None; size],
+            trail: Vec::new(),
+            cache: HashMap::new(),
+            cache_bytes: 0,
+            cache_limit,
+            limit: None,
+            implied: vec![false; size],
+            constraint_stamp: Vec::new(),
+            var_stamp: vec![0; size],
+            stamp: 0,
+            score: vec![0; size],
+            stats: Stats::default(),
+        };
+        for clause in clauses.iter() {
+            let mut c = clause.clone();
+            c.sort_by_key(|l| (l.unsigned_abs(), *l));
+            c.dedup();
+            // a tautology constrains nothing
+            if !c.windows(2).any(|w| w[0] == -w[1]) {
+                counter.push(Constraint { literals: c, parity: None });
+            }
+        }
+        return counter;
+    }
+
+    /// Adds the constraint that an odd (`parity` true) or even number of
+    /// `vars` is true.
+    pub fn add_xor(&mut self, vars: &[u32], parity: bool) {
+        let mut vars: Vec<i32> = vars.iter().map(|v| *v as i32).collect();
+        vars.sort();
+        vars.dedup();
+        self.push(Constraint { literals: vars, parity: Some(parity) });
+    }
+
+    /// Marks a variable as determined by the others, e.g. the pivot of an
+    /// XOR in reduced row echelon form: the search does not branch on it.
+    pub fn set_implied(&mut self, var: u32) {
+        self.implied[var as usize] = true;
+    }
+
+    /// Stops counting once the count reaches `limit`.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = Some(BigUint::from(limit));
+    }
+
+    fn push(&mut self, c: Constraint) {
+        for l in c.literals.iter() {
+            self.occurrences[l.unsigned_abs() as usize].push(self.constraints.len());
+        }
+        self.constraints.push(c);
+        self.constraint_stamp.push(0);
+    }
+
+    fn value(&self, l: i32) -> Option<bool> {
+        self.values[l.unsigned_abs() as usize].map(|v| v == (l > 0))
+    }
+
+    fn reached_limit(&self, n: &BigUint) -> bool {
+        self.limit.as_ref().is_some_and(|limit| n >= limit)
+    }
+
+    fn status(&self, c: usize) -> Status {
+        let c = &self.constraints[c];
+        let mut open = None;
+        let mut count = 0;
+        // the parity still needed from the unassigned variables
+        let mut parity = c.parity.unwrap_or(false);
+        for l in c.literals.iter() {
+            match self.value(*l) {
+                Some(true) if c.parity.is_none() => return Status::Satisfied,
+                Some(true) => parity = !parity,
+                Some(false) => {}
+                None => {
+                    open = Some(*l);
+                    count += 1;
+                }
+            }
+        }
+        return match (count, open, c.parity) {
+            (0, _, Some(_)) if !parity => Status::Satisfied,
+            (0, _, _) => Status::Conflict,
+            (1, Some(l), None) => Status::Unit(l),
+            (1, Some(v), Some(_)) => Status::Unit(if parity { v } else { -v }),
+            _ => Status::Open,
+        };
+    }
+
+    fn assign(&mut self, l: i32) {
+        self.values[l.unsigned_abs() as usize] = Some(l > 0);
+        self.trail.push(l.unsigned_abs());
+    }
+
+    fn undo(&mut self, to: usize) {
+        while self.trail.len() > to {
+            let v = self.trail.pop().unwrap();
+            self.values[v as usize] = None;
+        }
+    }
+
+    // unit propagation of the assignments on the trail from `from` on;
+    // returns false on a conflict
+    fn propagate(&mut self, from: usize) -> bool {
+        let mut head = from;
+        while head < self.trail.len() {
+            let v = self.trail[head] as usize;
+            head += 1;
+            for i in 0..self.occurrences[v].len() {
+                match self.status(self.occurrences[v][i]) {
+                    Status::Conflict => return false,
+                    Status::Unit(l) => self.assign(l),
+                    Status::Satisfied | Status::Open => {}
+                }
+            }
+        }
+        return true;
+    }
+
+    // the open constraints among `constraints`, grouped into components,
+    // each with its unassigned variables
+    fn components(&mut self, constraints: &[usize]) -> Vec<(Vec<usize>, Vec<u32>)> {
+        self.stamp += 1;
+        let member = self.stamp;
+        let open: Vec<usize> =
+            constraints.iter().copied().filter(|c| !matches!(self.status(*c), Status::Satisfied)).collect();
+        for c in open.iter() {
+            self.constraint_stamp[*c] = member;
+        }
+        self.stamp += 1;
+        let seen = self.stamp;
+        let mut components = Vec::new();
+        for start in open.iter().copied() {
+            if self.constraint_stamp[start] == seen {
+                continue;
+            }
+            self.constraint_stamp[start] = seen;
+            let (mut cs, mut vs) = (Vec::new(), Vec::new());
+            let mut todo = vec![start];
+            while let Some(c) = todo.pop() {
+                cs.push(c);
+                for l in self.constraints[c].literals.iter() {
+                    let v = l.unsigned_abs() as usize;
+                    if self.values[v].is_some() || self.var_stamp[v] == seen {
+                        continue;
+                    }
+                    self.var_stamp[v] = seen;
+                    vs.push(v as u32);
+                    for other in self.occurrences[v].iter() {
+                        if self.constraint_stamp[*other] == member {
+                            self.constraint_stamp[*other] = seen;
+                            todo.push(*other);
+                        }
+                    }
+                }
+            }
+            components.push((cs, vs));
+        }
+        return components;
+    }
+
+    // The residual constraints of a component, sorted, separated by zeros.
+    // An XOR starts with i32::MAX or i32::MIN for the parity still needed.
+    fn key(&self, constraints: &[usize]) -> Vec<i32> {
+        let mut residual: Vec<Vec<i32>> = constraints
+            .iter()
+            .map(|c| {
+                let c = &self.constraints[*c];
+                let mut r = Vec::new();
+                let mut parity = c.parity.unwrap_or(false);
+                for l in c.literals.iter() {
+                    match self.value(*l) {
+                        None => r.push(*l),
+                        Some(v) => parity ^= v,
+                    }
+                }
+                if c.parity.is_some() {
+                    r.insert(0, if parity { i32::MAX } else { i32::MIN });
+                }
+                r
+            })
+            .collect();
+        residual.sort();
+        let mut key = Vec::new();
+        for c in residual {
+            key.extend(c);
+            key.push(0);
+        }
+        return key;
+    }
+
+    // the count of the formula made of `constraints` over `vars`, under the
+    // current assignment (which has no conflict)
+    fn count_residual(&mut self, constraints: &[usize], vars: &[u32]) -> BigUint {
+        let components = self.components(constraints);
+        let covered: usize = components.iter().map(|(_, vs)| vs.len()).sum();
+        let unassigned = vars.iter().filter(|v| self.values[**v as usize].is_none()).count();
+        let mut total = BigUint::from(1) << (unassigned - covered);
+        // every component is counted, even past the limit: a later one may
+        // have no model at all
+        for (cs, vs) in components {
+            let n = self.count_component(cs, vs);
+            if n.is_zero() {
+                return n;
+            }
+            total = &total * &n;
+        }
+        return total;
+    }
+
+    fn count_component(&mut self, constraints: Vec<usize>, vars: Vec<u32>) -> BigUint {
+        let key = self.key(&constraints);
+        if let Some(n) = self.cache.get(&key) {
+            self.stats.cache_hits += 1;
+            return n.clone();
+        }
+        self.stats.components += 1;
+
+        // branch on the variable with the most occurrences in the component
+        for c in constraints.iter() {
+            for l in self.constraints[*c].literals.iter() {
+                if self.value(*l).is_none() {
+                    self.score[l.unsigned_abs() as usize] += 1;
+                }
+            }
+        }
+        let var = *vars
+            .iter()
+            .max_by_key(|v| (!self.implied[**v as usize], self.score[**v as usize], std::cmp::Reverse(**v)))
+            .unwrap();
+        for v in vars.iter() {
+            self.score[*v as usize] = 0;
+        }
+
+        let mut total = BigUint::zero();
+        for l in [var as i32, -(var as i32)] {
+            self.stats.decisions += 1;
+            let mark = self.trail.len();
+            self.assign(l);
+            if self.propagate(mark) {
+                total += &self.count_residual(&constraints, &vars);
+            } else {
+                self.stats.conflicts += 1;
+            }
+            self.undo(mark);
+            if self.reached_limit(&total) {
+                // possibly not the whole count, so not cached
+                return total;
+            }
+        }
+
+        self.cache_bytes += key.len() * std::mem::size_of::<i32>();
+        if self.cache_bytes > self.cache_limit {
+            self.cache.clear();
+            self.cache_bytes = key.len() * std::mem::size_of::<i32>();
+        }
+        self.cache.insert(key, total.clone());
+        return total;
+    }
+
+    /// The number of models over all `num_vars` variables. With a limit, a
+    /// count at or above the limit is only a lower bound.
+    pub fn count(&mut self) -> BigUint {
+        // unit clauses first
+        for c in 0..self.constraints.len() {
+            match self.status(c) {
+                Status::Conflict => return BigUint::zero(),
+                Status::Unit(l) => {
+                    let mark = self.trail.len();
+                    self.assign(l);
+                    if !self.propagate(mark) {
+                        return BigUint::zero();
+                    }
+                }
+                Status::Satisfied | Status::Open => {}
+            }
+        }
+        let constraints: Vec<usize> = (0..self.constraints.len()).collect();
+        let vars: Vec<u32> = (1..self.values.len() as u32).collect();
+        return self.count_residual(&constraints, &vars);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enumerate::Enumerate;
+    use crate::heuristics::registry::{self, HeuristicsConfig};
+    use crate::rng::Rng;
+    use crate::verify::{self, Model};
+
+    // A random formula of clauses over distinct variables and XORs, as DIMACS
+    // with `x` lines.
+    fn random_formula(rng: &mut Rng, n: u32) -> String {
+        let mut lines = Vec::new();
+        let clauses = rng.below(3 * n as u64);
+        let xors = rng.below(3);
+        for c in 0..clauses + xors {
+            let mut variables: Vec<u64> = (0..n as u64).collect();
+            rng.shuffle(&mut variables);
+            let size = 1 + rng.below(3.min(n as u64)) as usize;
+            let literals: Vec<String> = variables[..size]
+                .iter()
+                .map(|v| format!("{}{}", if rng.below(2) == 0 { "" } else { "-" }, v + 1))
+                .collect();
+            let prefix = if c < clauses { "" } else { "x" };
+            lines.push(format!("{}{} 0", prefix, literals.join(" ")));
+        }
+        return format!("p cnf {} {}\n{}\n", n, lines.len(), lines.join("\n"));
+    }
+
+    // The exact count, the number of models `enumerate` lists, and the number
+    // of assignments the model checker accepts agree.
+    #[test]
+    fn count_matches_enumeration() {
+        let mut rng = Rng::new(0);
+        let path = std::env::temp_dir().join(format!("count_test_{}.cnf", std::process::id()));
+        let input = path.to_string_lossy().to_string();
+        for _ in 0..200 {
+            let n = 1 + rng.below(7) as u32;
+            std::fs::write(&path, random_formula(&mut rng, n)).unwrap();
+            let cnf = verify::read_cnf(&input).unwrap();
+
+            // a cache too small to keep anything, and one large enough
+            let mut counts = Vec::new();
+            for cache_limit in [0, 1 << 20] {
+                let mut counter = Counter::new(n, &cnf.clauses, cache_limit);
+                for (vars, parity) in cnf.xor_constraints() {
+                    counter.add_xor(&vars, parity);
+                }
+                counts.push(counter.count().to_u64().unwrap());
+            }
+
+            let visitor = Enumerate {
+                input: &input,
+                translated: None,
+                use_bcp: true,
+                seed: 0,
+                variables: n,
+                limit: None,
+                projection: None,
+                on_model: |_: &Model| {},
+            };
+            let (enumerated, _) = registry::dispatch("vsids", &HeuristicsConfig::default(), visitor).unwrap();
+
+            let checked = (0..1u32 << n)
+                .filter(|mask| {
+                    let model: Model = (1..=n).map(|v| (v, mask >> (v - 1) & 1 == 1)).collect();
+                    verify::check(&cnf, &model).unwrap().ok()
+                })
+                .count() as u64;
+            let text = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(counts, vec![checked, checked], "{}", text);
+            assert_eq!(enumerated, checked, "{}", text);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,444 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::{self, HeuristicsConfig, HeuristicsVisitor};
+use crate::parser;
+use crate::profiler::{Phase, SolverProfiler};
+use crate::rng::Rng;
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use log::{info, trace};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+////////////////////////////////////////////////////////
+// Cube phase: split the problem into sub-problems
+////////////////////////////////////////////////////////
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeScore {
+    /// Occurrences of the variable among unresolved clauses
+    Dlis,
+    /// March_cu-style mixed diff over the clauses reduced by a lookahead
+    March,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CubeOptions {
+    pub depth: usize,
+    // stop splitting once at least this many cubes exist
+    pub count: Option<usize>,
+    pub score: CubeScore,
+}
+
+// Only the most frequent free variables are looked ahead on, like march's
+// preselection; looking ahead on every variable at every node is too slow.
+const PRESELECT: usize = 32;
+
+type Assignment = BTreeMap<Variable, Polarity>;
+
+fn literal_state(a: &Assignment, l: &Literal) -> LiteralState {
+    match a.get(&l.variable) {
+        None => LiteralState::Unknown,
+        Some(p) if *p == l.polarity => LiteralState::Sat,
+        Some(_) => LiteralState::Unsat,
+    }
+}
+
+/// Naive unit propagation until fixpoint. Returns false on conflict.
+fn propagate(clauses: &[Vec<Literal>], a: &mut Assignment) -> bool {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for c in clauses {
+            let mut unknown = None;
+            let mut unknown_count = 0;
+            let mut sat = false;
+            for l in c {
+                match literal_state(a, l) {
+                    LiteralState::Sat => {
+                        sat = true;
+                        break;
+                    }
+                    LiteralState::Unknown => {
+                        unknown = Some(*l);
+                        unknown_count += 1;
+                    }
+                    LiteralState::Unsat => {}
+                }
+            }
+            if sat {
+                continue;
+            }
+            match (unknown_count, unknown) {
+                (0, _) => return false,
+                (1, Some(l)) => {
+                    a.insert(l.variable, l.polarity);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+    }
+    true
+}
+
+// number of free literals of every unresolved clause, None for satisfied ones
+fn clause_sizes(clauses: &[Vec<Literal>], a: &Assignment) -> Vec<Option<usize>> {
+    clauses
+        .iter()
+        .map(|c| {
+            let mut free = 0;
+            for l in c {
+                match literal_state(a, l) {
+                    LiteralState::Sat => return None,
+                    LiteralState::Unknown => free += 1,
+                    LiteralState::Unsat => {}
+                }
+            }
+            Some(free)
+        })
+        .collect()
+}
+
+enum LookaheadResult {
+    Failed,
+    // number of clauses that got shorter but are not satisfied
+    Reduced(u64),
+}
+
+fn look_ahead(
+    clauses: &[Vec<Literal>],
+    a: &Assignment,
+    sizes: &[Option<usize>],
+    l: Literal,
+) -> LookaheadResult {
+    let mut tentative = a.clone();
+    tentative.insert(l.variable, l.polarity);
+    if !propagate(clauses, &mut tentative) {
+        return LookaheadResult::Failed;
+    }
+    let reduced = clause_sizes(clauses, &tentative)
+        .iter()
+        .zip(sizes.iter())
+        .filter(|(after, before)| matches!((after, before), (Some(x), Some(y)) if x < y))
+        .count();
+    LookaheadResult::Reduced(reduced as u64)
+}
+
+enum Node {
+    Refuted,
+    Solved,
+    Split(Variable),
+}
+
+/// Picks the splitting variable of one node. Failed literals found along the
+/// way are fixed in `a`, which may in turn refute or solve the node.
+fn pick_split_variable(clauses: &[Vec<Literal>], a: &mut Assignment, score: CubeScore) -> Node {
+    loop {
+        let sizes = clause_sizes(clauses, a);
+        let mut occurrences = BTreeMap::<Variable, u64>::new();
+        for (c, size) in clauses.iter().zip(sizes.iter()) {
+            if size.is_some() {
+                for l in c.iter().filter(|l| !a.contains_key(&l.variable)) {
+                    *occurrences.entry(l.variable).or_insert(0) += 1;
+                }
+            }
+        }
+        if occurrences.is_empty() {
+            return Node::Solved;
+        }
+
+        let mut candidates: Vec<(u64, Variable)> =
+            occurrences.iter().map(|(v, n)| (*n, *v)).collect();
+        candidates.sort_by(|x, y| y.cmp(x));
+        if score == CubeScore::Dlis {
+            return Node::Split(candidates[0].1);
+        }
+        candidates.truncate(PRESELECT);
+
+        let mut best: Option<(u64, Variable)> = None;
+        let mut fixed_failed_literal = false;
+        for (_, v) in candidates {
+            let pos = Literal { variable: v, polarity: Polarity::On };
+            let neg = Literal { variable: v, polarity: Polarity::Off };
+            let look_pos = look_ahead(clauses, a, &sizes, pos);
+            let look_neg = look_ahead(clauses, a, &sizes, neg);
+            match (&look_pos, &look_neg) {
+                (LookaheadResult::Failed, LookaheadResult::Failed) => return Node::Refuted,
+                (LookaheadResult::Failed, _) | (_, LookaheadResult::Failed) => {
+                    // one side fails: the other one is implied
+                    let forced = if matches!(look_pos, LookaheadResult::Failed) { neg } else { pos };
+                    trace!(target: "cube", "failed literal {:?}", !forced);
+                    a.insert(forced.variable, forced.polarity);
+                    if !propagate(clauses, a) {
+                        return Node::Refuted;
+                    }
+                    fixed_failed_literal = true;
+                    break;
+                }
+                (LookaheadResult::Reduced(dp), LookaheadResult::Reduced(dn)) => {
+                    let (dp, dn) = (*dp, *dn);
+                    let mixed = 1024 * dp * dn + dp + dn;
+                    if best.is_none_or(|(s, _)| mixed > s) {
+                        best = Some((mixed, v));
+                    }
+                }
+            }
+        }
+        if fixed_failed_literal {
+            continue;
+        }
+        return Node::Split(best.unwrap().1);
+    }
+}
+
+/// Splits the problem into cubes (conjunctions of literals) by a breadth-first
+/// lookahead. Sub-problems refuted during cubing are dropped, so the returned
+/// cubes together cover all remaining models.
+pub fn make_cubes(problem: &Problem, options: &CubeOptions) -> Vec<Vec<Literal>> {
+    let clauses: Vec<Vec<Literal>> = problem
+        .list_of_clauses
+        .iter()
+        .map(|rc| rc.borrow().list_of_literals.clone())
+        .collect();
+
+    let mut root = Assignment::new();
+    if !propagate(&clauses, &mut root) {
+        info!(target: "cube", "problem refuted by unit propagation");
+        return vec![];
+    }
+
+    let mut cubes = Vec::<Vec<Literal>>::new();
+    let mut frontier = VecDeque::from([(Vec::<Literal>::new(), root)]);
+    let mut refuted = 0;
+    while let Some((cube, mut a)) = frontier.pop_front() {
+        let enough = options
+            .count
+            .is_some_and(|n| cubes.len() + frontier.len() + 1 >= n);
+        if cube.len() >= options.depth || enough {
+            cubes.push(cube);
+            continue;
+        }
+        match pick_split_variable(&clauses, &mut a, options.score) {
+            Node::Refuted => refuted += 1,
+            Node::Solved => cubes.push(cube),
+            Node::Split(v) => {
+                for polarity in [Polarity::On, Polarity::Off] {
+                    let l = Literal { variable: v, polarity };
+                    let mut child = a.clone();
+                    child.insert(v, polarity);
+                    if !propagate(&clauses, &mut child) {
+                        refuted += 1;
+                        continue;
+                    }
+                    let mut child_cube = cube.clone();
+                    child_cube.push(l);
+                    frontier.push_back((child_cube, child));
+                }
+            }
+        }
+    }
+    info!(target: "cube", "{} cubes, {} sub-problems refuted while cubing", cubes.len(), refuted);
+    cubes
+}
+
+/// Writes cubes in iCNF style, one `a <lits> 0` line per cube.
+pub fn write_cubes(filename: &str, cubes: &[Vec<Literal>]) -> Result<(), String> {
+    let error = |why: std::io::Error| format!("cannot write {}: {}", filename, why);
+    let file = File::create(filename).map_err(error)?;
+    let mut w = BufWriter::new(file);
+    writeln!(w, "p inccnf").map_err(error)?;
+    for cube in cubes {
+        let lits: Vec<String> = cube.iter().map(|l| l.to_dimacs().to_string()).collect();
+        writeln!(w, "a {} 0", lits.join(" ")).map_err(error)?;
+    }
+    return w.flush().map_err(error);
+}
+
+/// Reads the `a <lits> 0` lines of an iCNF file; everything else is ignored.
+pub fn read_cubes(filename: &str) -> Result<Vec<Vec<Literal>>, String> {
+    let mut buffer = String::new();
+    File::open(filename)
+        .and_then(|mut f| f.read_to_string(&mut buffer))
+        .map_err(|why| format!("cannot read {}: {}", filename, why))?;
+
+    buffer
+        .lines()
+        .map(|s| s.trim())
+        .filter(|s| s.starts_with('a'))
+        .map(|s| {
+            s[1..]
+                .split_whitespace()
+                .map(|x| {
+                    // i32::MIN has no negation
+                    x.parse::<i32>()
+                        .ok()
+                        .filter(|l| *l != i32::MIN)
+                        .ok_or_else(|| format!("{}: bad literal '{}' in cube", filename, x))
+                })
+                .take_while(|x| x.as_ref().map_or(true, |x| *x != 0))
+                .map(|x| x.map(Literal::from_dimacs))
+                .collect()
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////
+// Conquer phase: solve every cube
+////////////////////////////////////////////////////////
+
+struct Conquer<'a> {
+    input: &'a String,
+    use_bcp: bool,
+    seed: u64,
+    cubes: &'a [Vec<Literal>],
+    threads: usize,
+}
+
+impl HeuristicsVisitor for Conquer<'_> {
+    type Output = (Option<SolutionStack>, SolverProfiler);
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+        let Conquer { input, use_bcp, seed, cubes, threads } = self;
+        let next_cube = &AtomicUsize::new(0);
+        let found = &AtomicBool::new(false);
+        // the statistics add up over all workers, the duration is wall-clock
+        let mut total = SolverProfiler::new();
+        total.reset_start_time();
+
+        // one seed per worker, drawn from `seed`
+        let mut rng = Rng::new(seed);
+
+        let solution = thread::scope(|s| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    let options = options.clone();
+                    let seed = rng.next_u64();
+                    s.spawn(move || {
+                        let mut h = H::with_options(options);
+                        h.set_use_bcp(use_bcp);
+                        h.set_seed(seed);
+                        let mut prof = SolverProfiler::new();
+                        prof.begin_phase(Phase::Parse);
+                        let problem = parser::parse(input, &mut h);
+                        prof.end_phase(Phase::Parse);
+                        let mut solver = Solver::new(problem, h);
+                        solver.profiler = prof;
+                        loop {
+                            let i = next_cube.fetch_add(1, Ordering::Relaxed);
+                            if i >= cubes.len() || found.load(Ordering::Relaxed) {
+                                return (None, solver.profiler);
+                            }
+                            let solution = solver.solve(&cubes[i], found);
+                            if found.load(Ordering::Relaxed) && solution.is_none() {
+                                return (None, solver.profiler);
+                            }
+                            trace!(target: "cube", "cube {} is {}", i, if solution.is_some() { "SAT" } else { "UNSAT" });
+                            if solution.is_some() {
+                                info!(target: "cube", "cube {} is SAT", i);
+                                found.store(true, Ordering::Relaxed);
+                                return (solution, solver.profiler);
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            let mut solution = None;
+            for w in workers {
+                let (s, prof) = w.join().unwrap();
+                total.merge(&prof);
+                solution = solution.or(s);
+            }
+            solution
+        });
+        total.calc_duration_till_now();
+        (solution, total)
+    }
+}
+
+/// Solves each cube incrementally under assumptions on `threads` worker
+/// threads. Returns the first model found, or None once every cube is UNSAT.
+pub fn conquer(
+    input: &String,
+    heuristics: &str,
+    config: &HeuristicsConfig,
+    use_bcp: bool,
+    seed: u64,
+    cubes: &[Vec<Literal>],
+    threads: usize,
+) -> Result<(Option<SolutionStack>, SolverProfiler), String> {
+    registry::dispatch(heuristics, config, Conquer { input, use_bcp, seed, cubes, threads })
+}
+
+/// Writes one self-contained DIMACS file per cube into `dir` (the original
+/// clauses plus one unit clause per cube literal), for an external scheduler.
+pub fn write_jobs(problem: &Problem, cubes: &[Vec<Literal>], dir: &str) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|why| format!("cannot create {}: {}", dir, why))?;
+    for (i, cube) in cubes.iter().enumerate() {
+        let path = Path::new(dir).join(format!("cube_{:05}.cnf", i));
+        write_job(problem, cube, i, cubes.len(), &path)
+            .map_err(|why| format!("cannot write {}: {}", path.display(), why))?;
+    }
+    info!(target: "cube", "wrote {} jobs to {}", cubes.len(), dir);
+    return Ok(());
+}
+
+// the job of cube `i` of `count`
+fn write_job(problem: &Problem, cube: &[Literal], i: usize, count: usize, path: &Path) -> std::io::Result<()> {
+    let num_vars = problem.list_of_variables.keys().map(|v| v.index).max().unwrap_or(0);
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "c cube {} of {}", i, count)?;
+    let constraints = problem.list_of_clauses.len() + problem.xor_constraints.constraints.len();
+    writeln!(w, "p cnf {} {}", num_vars, constraints + cube.len())?;
+    for rc in problem.list_of_clauses.iter() {
+        for l in rc.borrow().list_of_literals.iter() {
+            write!(w, "{} ", l.to_dimacs())?;
+        }
+        writeln!(w, "0")?;
+    }
+    for x in problem.xor_constraints.constraints.iter() {
+        if x.variables.is_empty() && !x.parity {
+            continue;
+        }
+        // an even parity is written with the first variable negated
+        write!(w, "x")?;
+        for (i, v) in x.variables.iter().enumerate() {
+            let negated = i == 0 && !x.parity;
+            write!(w, "{} ", if negated { -(v.index as i32) } else { v.index as i32 })?;
+        }
+        writeln!(w, "0")?;
+    }
+    for l in cube {
+        writeln!(w, "{} 0", l.to_dimacs())?;
+    }
+    return w.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_file;
+
+    #[test]
+    fn cubes_read_back_as_written() {
+        let cubes: Vec<Vec<Literal>> =
+            vec![vec![], vec![Literal::from_dimacs(1)], vec![Literal::from_dimacs(-2), Literal::from_dimacs(3)]];
+        let path = temp_file("cubes.icnf", "");
+        write_cubes(&path, &cubes).unwrap();
+        assert_eq!(read_cubes(&path).unwrap(), cubes);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bad_cubes_are_errors() {
+        let path = temp_file("cubes.icnf", "p inccnf\na 1 two 0\n");
+        assert!(read_cubes(&path).unwrap_err().contains("bad literal 'two'"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_cubes(&path).unwrap_err().starts_with("cannot read"));
+        assert!(write_cubes("/nonexistent/cubes.icnf", &[]).unwrap_err().starts_with("cannot write"));
+    }
+}
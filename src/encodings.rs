@@ -0,0 +1,705 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use std::collections::BTreeMap;
+
+// CNF encodings of cardinality and pseudo-Boolean constraints, for building
+// formulas in code rather than reading DIMACS. An encoding takes a list of
+// literals, writes its clauses into a ClauseSink, which also hands out the
+// auxiliary variables it needs, and returns those auxiliary variables.
+//
+// The encodings only forbid the assignments violating the constraint: an
+// auxiliary variable may be true when it need not be, never the other way
+// round. So every model of the constraint extends to the auxiliary variables,
+// but not always in a single way.
+
+/// Where the clauses of an encoding go.
+pub trait ClauseSink {
+    /// A variable not used anywhere yet.
+    fn fresh_variable(&mut self) -> Variable;
+    fn add_clause(&mut self, literals: &[Literal]);
+}
+
+/// Builds a Problem before solving, telling the heuristics about every
+/// clause. Fresh variables are numbered on from `next_variable`.
+pub struct ProblemSink<'a, H> {
+    pub problem: &'a mut Problem,
+    pub heuristics: &'a mut H,
+    // the last variable handed out, or the largest variable in use
+    pub next_variable: u32,
+}
+
+impl<'a, H: Heuristics> ProblemSink<'a, H> {
+    /// Fresh variables come after every variable of the problem so far.
+    pub fn new(problem: &'a mut Problem, heuristics: &'a mut H) -> Self {
+        let next_variable = problem.list_of_variables.keys().next_back().map_or(0, |v| v.index);
+        return ProblemSink { problem, heuristics, next_variable };
+    }
+}
+
+impl<H: Heuristics> ClauseSink for ProblemSink<'_, H> {
+    fn fresh_variable(&mut self) -> Variable {
+        self.next_variable += 1;
+        return Variable { index: self.next_variable };
+    }
+
+    fn add_clause(&mut self, literals: &[Literal]) {
+        let clause = self.problem.add_clause(literals);
+        self.heuristics.add_parsed_clause(&clause.borrow());
+    }
+}
+
+// Between solve calls: a fresh variable is registered with the problem and the
+// heuristics right away, so the next one is different even before any clause
+// uses it.
+impl<H: Heuristics> ClauseSink for Solver<H> {
+    fn fresh_variable(&mut self) -> Variable {
+        return Solver::add_variable(self);
+    }
+
+    fn add_clause(&mut self, literals: &[Literal]) {
+        Solver::add_clause(self, literals);
+    }
+}
+
+// Passes the clauses on and keeps a list of the auxiliary variables.
+struct Recorder<'a, S> {
+    sink: &'a mut S,
+    auxiliary: Vec<Variable>,
+}
+
+impl<S: ClauseSink> ClauseSink for Recorder<'_, S> {
+    fn fresh_variable(&mut self) -> Variable {
+        let v = self.sink.fresh_variable();
+        self.auxiliary.push(v);
+        return v;
+    }
+
+    fn add_clause(&mut self, literals: &[Literal]) {
+        self.sink.add_clause(literals);
+    }
+}
+
+// the auxiliary variables of an encoding
+fn record<S: ClauseSink>(sink: &mut S, encode: impl FnOnce(&mut Recorder<S>)) -> Vec<Variable> {
+    let mut recorder = Recorder { sink, auxiliary: Vec::new() };
+    encode(&mut recorder);
+    return recorder.auxiliary;
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmoEncoding {
+    /// A binary clause per pair of literals, no auxiliary variables
+    Pairwise,
+    /// A ladder of n-1 auxiliary variables, the i-th true once one of the
+    /// first i literals is
+    Ladder,
+    /// Klieber and Kwon's commander encoding, groups of three with one
+    /// commander variable each, recursively
+    Commander,
+    /// Chen's product encoding, the literals on a grid with one variable per
+    /// row and column, recursively
+    Product,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardinalityEncoding {
+    /// Sinz's sequential counter, k auxiliary variables per literal
+    Sequential,
+    /// Bailleux and Boufkhad's totalizer, a tree of unary adders
+    Totalizer,
+    /// Batcher's odd-even merge sorting network, cut down to the comparators
+    /// the (k+1)-th output depends on
+    Network,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PbEncoding {
+    /// A decision diagram over the terms, from the largest coefficient down
+    Bdd,
+    /// Binary adders summing the terms, and a comparator with the bound
+    Adder,
+}
+
+fn fresh_literal(sink: &mut impl ClauseSink) -> Literal {
+    Literal {
+        variable: sink.fresh_variable(),
+        polarity: Polarity::On,
+    }
+}
+
+// A constraint that cannot hold: the empty clause, which the solver does not
+// take, written as a fresh variable that is both true and false.
+fn add_contradiction(sink: &mut impl ClauseSink) {
+    let x = fresh_literal(sink);
+    sink.add_clause(&[x]);
+    sink.add_clause(&[!x]);
+}
+
+/// At most one of `literals` is true.
+pub fn at_most_one(sink: &mut impl ClauseSink, literals: &[Literal], encoding: AmoEncoding) -> Vec<Variable> {
+    return record(sink, |sink| match encoding {
+        AmoEncoding::Pairwise => pairwise(sink, literals),
+        AmoEncoding::Ladder => ladder(sink, literals),
+        AmoEncoding::Commander => commander(sink, literals),
+        AmoEncoding::Product => product(sink, literals),
+    });
+}
+
+/// Exactly one of `literals` is true.
+pub fn exactly_one(sink: &mut impl ClauseSink, literals: &[Literal], encoding: AmoEncoding) -> Vec<Variable> {
+    if literals.is_empty() {
+        return record(sink, |sink| add_contradiction(sink));
+    }
+    sink.add_clause(literals);
+    return at_most_one(sink, literals, encoding);
+}
+
+/// At most `k` of `literals` are true.
+pub fn at_most_k(
+    sink: &mut impl ClauseSink,
+    literals: &[Literal],
+    k: usize,
+    encoding: CardinalityEncoding,
+) -> Vec<Variable> {
+    return record(sink, |sink| at_most_k_clauses(sink, literals, k, encoding));
+}
+
+/// At least `k` of `literals` are true, i.e. at most n-k of them are false.
+pub fn at_least_k(
+    sink: &mut impl ClauseSink,
+    literals: &[Literal],
+    k: usize,
+    encoding: CardinalityEncoding,
+) -> Vec<Variable> {
+    return record(sink, |sink| at_least_k_clauses(sink, literals, k, encoding));
+}
+
+/// Exactly `k` of `literals` are true.
+pub fn exactly_k(
+    sink: &mut impl ClauseSink,
+    literals: &[Literal],
+    k: usize,
+    encoding: CardinalityEncoding,
+) -> Vec<Variable> {
+    return record(sink, |sink| {
+        at_most_k_clauses(sink, literals, k, encoding);
+        at_least_k_clauses(sink, literals, k, encoding);
+    });
+}
+
+fn pairwise(sink: &mut impl ClauseSink, x: &[Literal]) {
+    for (i, a) in x.iter().enumerate() {
+        for b in x[i + 1..].iter() {
+            sink.add_clause(&[!*a, !*b]);
+        }
+    }
+}
+
+// s[i] is true once one of x[0..=i] is: x[i] -> s[i], s[i-1] -> s[i] and
+// x[i] -> not s[i-1].
+fn ladder(sink: &mut impl ClauseSink, x: &[Literal]) {
+    if x.len() < 2 {
+        return;
+    }
+    let s: Vec<Literal> = (0..x.len() - 1).map(|_| fresh_literal(sink)).collect();
+    sink.add_clause(&[!x[0], s[0]]);
+    for i in 1..x.len() - 1 {
+        sink.add_clause(&[!x[i], s[i]]);
+        sink.add_clause(&[!s[i - 1], s[i]]);
+        sink.add_clause(&[!x[i], !s[i - 1]]);
+    }
+    sink.add_clause(&[!x[x.len() - 1], !s[x.len() - 2]]);
+}
+
+// Each group of three has a commander that is true if one of the group is;
+// at most one literal per group and at most one commander are true.
+fn commander(sink: &mut impl ClauseSink, x: &[Literal]) {
+    const GROUP: usize = 3;
+    if x.len() <= GROUP + 1 {
+        pairwise(sink, x);
+        return;
+    }
+    let mut commanders = Vec::new();
+    for group in x.chunks(GROUP) {
+        pairwise(sink, group);
+        let c = fresh_literal(sink);
+        for l in group.iter() {
+            sink.add_clause(&[!*l, c]);
+        }
+        let mut some = vec![!c];
+        some.extend(group);
+        sink.add_clause(&some);
+        commanders.push(c);
+    }
+    commander(sink, &commanders);
+}
+
+// The literals fill a p x q grid row by row; a true literal makes its row
+// and its column variable true, and at most one row and one column are.
+fn product(sink: &mut impl ClauseSink, x: &[Literal]) {
+    if x.len() <= 4 {
+        pairwise(sink, x);
+        return;
+    }
+    let p = (x.len() as f64).sqrt().ceil() as usize;
+    let q = x.len().div_ceil(p);
+    let rows: Vec<Literal> = (0..x.len().div_ceil(q)).map(|_| fresh_literal(sink)).collect();
+    let columns: Vec<Literal> = (0..q).map(|_| fresh_literal(sink)).collect();
+    for (i, l) in x.iter().enumerate() {
+        sink.add_clause(&[!*l, rows[i / q]]);
+        sink.add_clause(&[!*l, columns[i % q]]);
+    }
+    product(sink, &rows);
+    product(sink, &columns);
+}
+
+fn at_most_k_clauses(sink: &mut impl ClauseSink, literals: &[Literal], k: usize, encoding: CardinalityEncoding) {
+    if k >= literals.len() {
+        return;
+    }
+    if k == 0 {
+        for l in literals.iter() {
+            sink.add_clause(&[!*l]);
+        }
+        return;
+    }
+    match encoding {
+        CardinalityEncoding::Sequential => sequential_counter(sink, literals, k),
+        CardinalityEncoding::Totalizer => totalizer(sink, literals, k),
+        CardinalityEncoding::Network => sorting_network(sink, literals, k),
+    }
+}
+
+fn at_least_k_clauses(sink: &mut impl ClauseSink, literals: &[Literal], k: usize, encoding: CardinalityEncoding) {
+    if k == 0 {
+        return;
+    }
+    if k > literals.len() {
+        add_contradiction(sink);
+        return;
+    }
+    if k == 1 {
+        sink.add_clause(literals);
+        return;
+    }
+    let negated: Vec<Literal> = literals.iter().map(|l| !*l).collect();
+    at_most_k_clauses(sink, &negated, literals.len() - k, encoding);
+}
+
+// s[i][j] is true if at least j+1 of the first i+1 literals are; 0 < k < n.
+fn sequential_counter(sink: &mut impl ClauseSink, x: &[Literal], k: usize) {
+    let n = x.len();
+    let s: Vec<Vec<Literal>> = (0..n - 1).map(|_| (0..k).map(|_| fresh_literal(sink)).collect()).collect();
+    sink.add_clause(&[!x[0], s[0][0]]);
+    for l in s[0][1..].iter() {
+        sink.add_clause(&[!*l]);
+    }
+    for i in 1..n - 1 {
+        sink.add_clause(&[!x[i], s[i][0]]);
+        sink.add_clause(&[!s[i - 1][0], s[i][0]]);
+        for j in 1..k {
+            sink.add_clause(&[!x[i], !s[i - 1][j - 1], s[i][j]]);
+            sink.add_clause(&[!s[i - 1][j], s[i][j]]);
+        }
+        sink.add_clause(&[!x[i], !s[i - 1][k - 1]]);
+    }
+    sink.add_clause(&[!x[n - 1], !s[n - 2][k - 1]]);
+}
+
+// The root of the tree counts up to k+1, which it must not reach; 0 < k < n.
+fn totalizer(sink: &mut impl ClauseSink, x: &[Literal], k: usize) {
+    let root = totalizer_node(sink, x, k + 1);
+    sink.add_clause(&[!root[k]]);
+}
+
+// The unary count of `x` up to `limit`: output j is true if at least j+1
+// literals are.
+fn totalizer_node(sink: &mut impl ClauseSink, x: &[Literal], limit: usize) -> Vec<Literal> {
+    if x.len() == 1 {
+        return x.to_vec();
+    }
+    let (left, right) = x.split_at(x.len() / 2);
+    let a = totalizer_node(sink, left, limit);
+    let b = totalizer_node(sink, right, limit);
+    let out: Vec<Literal> = (0..(a.len() + b.len()).min(limit)).map(|_| fresh_literal(sink)).collect();
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            if i + j == 0 || i + j > out.len() {
+                continue;
+            }
+            let mut clause = Vec::new();
+            if i > 0 {
+                clause.push(!a[i - 1]);
+            }
+            if j > 0 {
+                clause.push(!b[j - 1]);
+            }
+            clause.push(out[i + j - 1]);
+            sink.add_clause(&clause);
+        }
+    }
+    return out;
+}
+
+// a wire of the sorting network
+#[derive(Clone, Copy)]
+enum Wire {
+    False,
+    Input(Literal),
+    // the larger or the smaller output of a comparator
+    Max(usize),
+    Min(usize),
+}
+
+// The network sorts the literals in descending order, padded with false to a
+// power of two; its output k must be false. 0 < k < n.
+fn sorting_network(sink: &mut impl ClauseSink, x: &[Literal], k: usize) {
+    let n = x.len().next_power_of_two();
+    let mut wires: Vec<Wire> = x.iter().map(|l| Wire::Input(*l)).collect();
+    wires.resize(n, Wire::False);
+    let mut comparators: Vec<(Wire, Wire)> = Vec::new();
+    let mut compare = |wires: &mut Vec<Wire>, i: usize, j: usize| match (wires[i], wires[j]) {
+        (_, Wire::False) => {}
+        (Wire::False, w) => {
+            wires[i] = w;
+            wires[j] = Wire::False;
+        }
+        (a, b) => {
+            wires[i] = Wire::Max(comparators.len());
+            wires[j] = Wire::Min(comparators.len());
+            comparators.push((a, b));
+        }
+    };
+    let mut p = 1;
+    while p < n {
+        let mut step = p;
+        while step > 0 {
+            let mut j = step % p;
+            while j + step < n {
+                for i in 0..step.min(n - j - step) {
+                    if (i + j) / (2 * p) == (i + j + step) / (2 * p) {
+                        compare(&mut wires, i + j, i + j + step);
+                    }
+                }
+                j += 2 * step;
+            }
+            step /= 2;
+        }
+        p *= 2;
+    }
+
+    // the outputs output k depends on, from the last comparator back
+    let mut needed_max = vec![false; comparators.len()];
+    let mut needed_min = vec![false; comparators.len()];
+    let need = |w: Wire, needed_max: &mut Vec<bool>, needed_min: &mut Vec<bool>| match w {
+        Wire::Max(c) => needed_max[c] = true,
+        Wire::Min(c) => needed_min[c] = true,
+        _ => {}
+    };
+    need(wires[k], &mut needed_max, &mut needed_min);
+    for c in (0..comparators.len()).rev() {
+        if needed_max[c] || needed_min[c] {
+            let (a, b) = comparators[c];
+            need(a, &mut needed_max, &mut needed_min);
+            need(b, &mut needed_max, &mut needed_min);
+        }
+    }
+
+    let mut outputs = BTreeMap::<(usize, bool), Literal>::new();
+    let literal = |w: Wire, outputs: &BTreeMap<(usize, bool), Literal>| match w {
+        Wire::Input(l) => Some(l),
+        Wire::Max(c) => Some(outputs[&(c, true)]),
+        Wire::Min(c) => Some(outputs[&(c, false)]),
+        Wire::False => None,
+    };
+    for (c, (a, b)) in comparators.iter().enumerate() {
+        if !needed_max[c] && !needed_min[c] {
+            continue;
+        }
+        let (a, b) = (literal(*a, &outputs).unwrap(), literal(*b, &outputs).unwrap());
+        if needed_max[c] {
+            let max = fresh_literal(sink);
+            sink.add_clause(&[!a, max]);
+            sink.add_clause(&[!b, max]);
+            outputs.insert((c, true), max);
+        }
+        if needed_min[c] {
+            let min = fresh_literal(sink);
+            sink.add_clause(&[!a, !b, min]);
+            outputs.insert((c, false), min);
+        }
+    }
+    if let Some(l) = literal(wires[k], &outputs) {
+        sink.add_clause(&[!l]);
+    }
+}
+
+/// The sum of the coefficients of the true literals of `terms` is at least
+/// `bound`.
+pub fn pb_at_least(
+    sink: &mut impl ClauseSink,
+    terms: &[(u64, Literal)],
+    bound: u64,
+    encoding: PbEncoding,
+) -> Vec<Variable> {
+    return record(sink, |sink| pb_clauses(sink, terms, bound, encoding));
+}
+
+fn pb_clauses(sink: &mut impl ClauseSink, terms: &[(u64, Literal)], bound: u64, encoding: PbEncoding) {
+    let terms: Vec<(u64, Literal)> = terms.iter().copied().filter(|(a, _)| *a > 0).collect();
+    let total: u128 = terms.iter().map(|(a, _)| *a as u128).sum();
+    if bound == 0 {
+        return;
+    }
+    if total < bound as u128 {
+        add_contradiction(sink);
+        return;
+    }
+    match encoding {
+        PbEncoding::Bdd => bdd(sink, terms, bound),
+        PbEncoding::Adder => adder(sink, &terms, bound),
+    }
+}
+
+// a node of the decision diagram
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Node {
+    True,
+    False,
+    // true if the rest of the constraint holds
+    Variable(Literal),
+}
+
+fn bdd(sink: &mut impl ClauseSink, mut terms: Vec<(u64, Literal)>, bound: u64) {
+    terms.sort_by_key(|(a, _)| std::cmp::Reverse(*a));
+    // what the terms from i on can add up to
+    let mut suffix = vec![0u128; terms.len() + 1];
+    for i in (0..terms.len()).rev() {
+        suffix[i] = suffix[i + 1] + terms[i].0 as u128;
+    }
+    let mut memo = BTreeMap::new();
+    match bdd_node(sink, &terms, &suffix, 0, bound, &mut memo) {
+        Node::True => {}
+        Node::False => add_contradiction(sink),
+        Node::Variable(v) => sink.add_clause(&[v]),
+    }
+}
+
+// The node for "the terms from i on add up to at least `bound`". A node is
+// true only if its high child is (when the literal of the term is true) or
+// its low child is.
+fn bdd_node(
+    sink: &mut impl ClauseSink,
+    terms: &[(u64, Literal)],
+    suffix: &[u128],
+    i: usize,
+    bound: u64,
+    memo: &mut BTreeMap<(usize, u64), Node>,
+) -> Node {
+    if bound == 0 {
+        return Node::True;
+    }
+    if suffix[i] < bound as u128 {
+        return Node::False;
+    }
+    if let Some(node) = memo.get(&(i, bound)) {
+        return *node;
+    }
+    let (a, l) = terms[i];
+    let high = bdd_node(sink, terms, suffix, i + 1, bound.saturating_sub(a), memo);
+    let low = bdd_node(sink, terms, suffix, i + 1, bound, memo);
+    let v = fresh_literal(sink);
+    match high {
+        Node::True => {}
+        Node::False => sink.add_clause(&[!v]),
+        Node::Variable(h) => sink.add_clause(&[!v, h]),
+    }
+    match low {
+        Node::True => {}
+        Node::False => sink.add_clause(&[!v, l]),
+        Node::Variable(w) => sink.add_clause(&[!v, l, w]),
+    }
+    let node = Node::Variable(v);
+    memo.insert((i, bound), node);
+    return node;
+}
+
+// Sums the terms bit by bit with full and half adders, then compares the sum
+// with the bound.
+fn adder(sink: &mut impl ClauseSink, terms: &[(u64, Literal)], bound: u64) {
+    let mut buckets: Vec<Vec<Literal>> = vec![Vec::new(); 64];
+    for (a, l) in terms.iter() {
+        for (j, bucket) in buckets.iter_mut().enumerate() {
+            if a >> j & 1 == 1 {
+                bucket.push(*l);
+            }
+        }
+    }
+    // the bits of the sum, None where it is always 0
+    let mut sum = Vec::<Option<Literal>>::new();
+    let mut j = 0;
+    while j < buckets.len() {
+        while buckets[j].len() >= 2 {
+            let x = buckets[j].remove(0);
+            let y = buckets[j].remove(0);
+            let (s, carry) = if buckets[j].is_empty() {
+                half_adder(sink, x, y)
+            } else {
+                let z = buckets[j].remove(0);
+                full_adder(sink, x, y, z)
+            };
+            buckets[j].push(s);
+            if j + 1 == buckets.len() {
+                buckets.push(Vec::new());
+            }
+            buckets[j + 1].push(carry);
+        }
+        sum.push(buckets[j].pop());
+        j += 1;
+    }
+
+    // sum >= bound: for every 1 bit j of the bound, either the sum has it
+    // too, or it differs from the bound at a higher bit (where it must then
+    // be greater)
+    let bit = |i: usize| i < 64 && bound >> i & 1 == 1;
+    for j in (0..sum.len()).filter(|j| bit(*j)) {
+        let mut clause: Vec<Literal> = sum[j].into_iter().collect();
+        let mut satisfied = false;
+        for (i, s) in sum.iter().enumerate().skip(j + 1) {
+            match (bit(i), s) {
+                (true, Some(s)) => clause.push(!*s),
+                (true, None) => satisfied = true,
+                (false, Some(s)) => clause.push(*s),
+                (false, None) => {}
+            }
+        }
+        if satisfied {
+            continue;
+        }
+        if clause.is_empty() {
+            add_contradiction(sink);
+            return;
+        }
+        sink.add_clause(&clause);
+    }
+}
+
+// s = x xor y and c = x and y
+fn half_adder(sink: &mut impl ClauseSink, x: Literal, y: Literal) -> (Literal, Literal) {
+    let s = fresh_literal(sink);
+    let c = fresh_literal(sink);
+    sink.add_clause(&[!x, !y, !s]);
+    sink.add_clause(&[x, y, !s]);
+    sink.add_clause(&[!x, y, s]);
+    sink.add_clause(&[x, !y, s]);
+    sink.add_clause(&[!x, !y, c]);
+    sink.add_clause(&[x, !c]);
+    sink.add_clause(&[y, !c]);
+    return (s, c);
+}
+
+// s = x xor y xor z and c = at least two of x, y, z
+fn full_adder(sink: &mut impl ClauseSink, x: Literal, y: Literal, z: Literal) -> (Literal, Literal) {
+    let s = fresh_literal(sink);
+    let c = fresh_literal(sink);
+    for mask in 0..8u32 {
+        // the clause ruling out x, y, z having these values with the wrong s
+        let values = [mask & 1 == 1, mask & 2 == 2, mask & 4 == 4];
+        let mut clause: Vec<Literal> =
+            [x, y, z].iter().zip(values.iter()).map(|(l, v)| if *v { !*l } else { *l }).collect();
+        clause.push(if mask.count_ones() % 2 == 1 { s } else { !s });
+        sink.add_clause(&clause);
+    }
+    sink.add_clause(&[!x, !y, c]);
+    sink.add_clause(&[!x, !z, c]);
+    sink.add_clause(&[!y, !z, c]);
+    sink.add_clause(&[x, y, !c]);
+    sink.add_clause(&[x, z, !c]);
+    sink.add_clause(&[y, z, !c]);
+    return (s, c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::vsids::VSIDS;
+    use std::sync::atomic::AtomicBool;
+
+    // The number of assignments to n variables, over mixed polarities, that
+    // extend to a model of the encoding, checked one by one against
+    // `holds(number of true literals)`.
+    fn count_models(
+        n: u32,
+        encode: impl FnOnce(&mut Solver<VSIDS>, &[Literal]) -> Vec<Variable>,
+        holds: impl Fn(usize) -> bool,
+    ) -> u64 {
+        let mut solver = Solver::new(Problem::new(), VSIDS::with_options(Default::default()));
+        let variables: Vec<Variable> = (0..n).map(|_| solver.add_variable()).collect();
+        // every third literal negated, so that the encodings see both
+        let literals: Vec<Literal> = variables
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Literal {
+                variable: *v,
+                polarity: if i % 3 == 2 { Polarity::Off } else { Polarity::On },
+            })
+            .collect();
+        encode(&mut solver, &literals);
+        let interrupt = AtomicBool::new(false);
+        let mut count = 0;
+        for mask in 0..1u32 << n {
+            let assumptions: Vec<Literal> = literals
+                .iter()
+                .enumerate()
+                .map(|(i, l)| if mask >> i & 1 == 1 { *l } else { !*l })
+                .collect();
+            let model = solver.solve(&assumptions, &interrupt).is_some();
+            assert_eq!(model, holds(mask.count_ones() as usize), "n = {}, assignment {:b}", n, mask);
+            if model {
+                count += 1;
+            }
+        }
+        return count;
+    }
+
+    // the number of ways to pick k of n
+    fn binomial(n: u32, k: usize) -> u64 {
+        return (0..k as u64).fold(1, |b, i| b * (n as u64 - i) / (i + 1));
+    }
+
+    const AMO: [AmoEncoding; 4] =
+        [AmoEncoding::Pairwise, AmoEncoding::Ladder, AmoEncoding::Commander, AmoEncoding::Product];
+    const CARDINALITY: [CardinalityEncoding; 3] =
+        [CardinalityEncoding::Sequential, CardinalityEncoding::Totalizer, CardinalityEncoding::Network];
+
+    #[test]
+    fn at_most_one_encodings_count_models() {
+        for encoding in AMO {
+            for n in 1..=9 {
+                let count = count_models(n, |s, x| at_most_one(s, x, encoding), |t| t <= 1);
+                assert_eq!(count, 1 + n as u64, "{:?}", encoding);
+                let count = count_models(n, |s, x| exactly_one(s, x, encoding), |t| t == 1);
+                assert_eq!(count, n as u64, "{:?}", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn cardinality_encodings_count_models() {
+        for encoding in CARDINALITY {
+            for n in 1..=6 {
+                for k in 0..=n as usize + 1 {
+                    let at_most: u64 = (0..=k).map(|j| binomial(n, j)).sum();
+                    let count = count_models(n, |s, x| at_most_k(s, x, k, encoding), |t| t <= k);
+                    assert_eq!(count, at_most, "{:?}, at most {} of {}", encoding, k, n);
+                    let count = count_models(n, |s, x| at_least_k(s, x, k, encoding), |t| t >= k);
+                    let at_least = (1 << n) - at_most + binomial(n, k);
+                    assert_eq!(count, at_least, "{:?}, at least {} of {}", encoding, k, n);
+                    let count = count_models(n, |s, x| exactly_k(s, x, k, encoding), |t| t == k);
+                    assert_eq!(count, binomial(n, k), "{:?}, exactly {} of {}", encoding, k, n);
+                }
+            }
+        }
+    }
+}
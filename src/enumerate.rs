@@ -0,0 +1,165 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::HeuristicsVisitor;
+use crate::input::Translated;
+use crate::parser;
+use crate::profiler::{Phase, SolverProfiler};
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use crate::verify::Model;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicBool;
+
+// All-solutions enumeration (--enumerate). Without a projection the search of
+// dpll::enumerate goes on past every model, backtracking chronologically as if
+// the model were a conflict, and adds no clause. With a projection (`c ind` or
+// `c p show` lines, or the variables of an OPB or formula input whose
+// translation adds auxiliary ones), models that differ only outside the
+// projection must be listed once: every projected model is blocked by a
+// clause and the search starts over.
+
+/// The model of a solution, by variable index.
+pub fn model(s: &SolutionStack) -> Model {
+    return s
+        .stack
+        .iter()
+        .map(|step| (step.assignment.variable.index, step.assignment.polarity == Polarity::On))
+        .collect();
+}
+
+pub struct Enumerate<'a, F> {
+    pub input: &'a String,
+    // read instead of the input file when it is not DIMACS CNF
+    pub translated: Option<&'a Translated>,
+    pub use_bcp: bool,
+    pub seed: u64,
+    // the variables 1 to this one take part in the models, also those that
+    // are in no clause
+    pub variables: u32,
+    // stop after this many models
+    pub limit: Option<u64>,
+    pub projection: Option<&'a [u32]>,
+    // called with the whole model of every model found
+    pub on_model: F,
+}
+
+impl<F: FnMut(&Model)> HeuristicsVisitor for Enumerate<'_, F> {
+    type Output = (u64, SolverProfiler);
+
+    fn visit<H: Heuristics>(mut self, options: H::Options) -> Self::Output {
+        let mut h = H::with_options(options);
+        h.set_use_bcp(self.use_bcp);
+        h.set_seed(self.seed);
+        let mut prof = SolverProfiler::new();
+        prof.begin_phase(Phase::Parse);
+        let mut problem = match self.translated {
+            Some(t) => t.load(&mut h),
+            None => parser::parse(self.input, &mut h),
+        };
+        let free: Vec<Variable> = (1..=self.variables)
+            .map(|index| Variable { index })
+            .filter(|v| !problem.list_of_variables.contains_key(v))
+            .collect();
+        if !free.is_empty() {
+            let variables = problem.add_free_variables(&free);
+            h.add_parsed_clause(&variables);
+        }
+        prof.end_phase(Phase::Parse);
+        prof.reset_start_time();
+        let interrupt = AtomicBool::new(false);
+
+        // a projection on every variable is none
+        let projection = self.projection.filter(|vars| {
+            let vars: BTreeSet<u32> = vars.iter().copied().collect();
+            problem.list_of_variables.keys().any(|v| !vars.contains(&v.index))
+        });
+        let models = match projection {
+            None => {
+                let mut models = 0;
+                dpll::enumerate(&mut problem, &mut h, &mut prof, &interrupt, |s| {
+                    (self.on_model)(&model(s));
+                    models += 1;
+                    self.limit.is_none_or(|n| models < n)
+                })
+            }
+            Some(vars) => {
+                let mut solver = Solver::new(problem, h);
+                solver.profiler = prof;
+                let models = enumerate_projected(&mut solver, vars, self.limit, &interrupt, &mut self.on_model);
+                prof = solver.profiler;
+                models
+            }
+        };
+        prof.calc_duration_till_now();
+        return (models, prof);
+    }
+}
+
+// Blocks each projected model with a clause and solves again, until the
+// problem is UNSAT or `limit` models are found.
+fn enumerate_projected<H: Heuristics>(
+    solver: &mut Solver<H>,
+    vars: &[u32],
+    limit: Option<u64>,
+    interrupt: &AtomicBool,
+    on_model: &mut impl FnMut(&Model),
+) -> u64 {
+    let vars: BTreeSet<u32> = vars.iter().copied().collect();
+    let mut models = 0;
+    while limit.is_none_or(|n| models < n) {
+        let Some(s) = solver.solve(&[], interrupt) else {
+            break;
+        };
+        let m = model(&s);
+        on_model(&m);
+        models += 1;
+        let blocking: Vec<Literal> = m
+            .iter()
+            .filter(|(var, _)| vars.contains(var))
+            .map(|(var, value)| Literal::from_dimacs(if *value { -(*var as i32) } else { *var as i32 }))
+            .collect();
+        if blocking.is_empty() {
+            // the projection is empty: there is a single projected model
+            break;
+        }
+        solver.add_clause(&blocking);
+    }
+    return models;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::registry::{self, HeuristicsConfig};
+
+    // the number of models of a DIMACS file with `variables` variables
+    fn count_models(text: &str, variables: u32, projection: Option<&[u32]>) -> u64 {
+        let path = std::env::temp_dir().join(format!("enumerate_test_{}_{}.cnf", std::process::id(), variables));
+        std::fs::write(&path, text).unwrap();
+        let input = path.to_string_lossy().to_string();
+        let mut models = Vec::new();
+        let visitor = Enumerate {
+            input: &input,
+            translated: None,
+            use_bcp: true,
+            seed: 0,
+            variables,
+            limit: None,
+            projection,
+            on_model: |m: &Model| models.push(m.clone()),
+        };
+        let (count, _) = registry::dispatch("vsids", &HeuristicsConfig::default(), visitor).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(count as usize, models.len());
+        return count;
+    }
+
+    #[test]
+    fn variables_in_no_clause_are_enumerated() {
+        assert_eq!(count_models("p cnf 1 0\n", 1, Some(&[1])), 2);
+        assert_eq!(count_models("p cnf 2 0\n", 2, None), 4);
+        // 3 models of the clause, 2 of each of 3, 4 and 5, 3 of the other
+        let all = [1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(count_models("p cnf 7 2\n1 2 0\n-6 7 0\n", 7, Some(&all[..6])), 3 * 8 * 2);
+        assert_eq!(count_models("p cnf 7 2\n1 2 0\n-6 7 0\n", 7, None), 3 * 8 * 3);
+    }
+}
@@ -0,0 +1,737 @@
+use crate::encodings::{ClauseSink, ProblemSink};
+use crate::heuristics::heuristics::Heuristics;
+use crate::sat_solver::*;
+use crate::verify::Model;
+use std::collections::{BTreeMap, BTreeSet};
+
+// Propositional formulas over named variables, and their translation into
+// clauses. The text syntax, one formula per `;`, all of which must hold:
+//
+//   # a comment
+//   (a | b) & !c;
+//   a -> (b <-> c);
+//   ite(s, x ^ y, true)
+//
+// From the loosest binding: `<->`, `->` (to the right), `|`, `^`, `&`, `!`.
+//
+// The formulas become a circuit of AND, XOR and if-then-else gates over the
+// variables, built with structural hashing: a gate is made once, however often
+// it occurs, and gates with constant, equal or complementary inputs are
+// simplified away. Each gate gets an auxiliary variable, defined by clauses
+// in one of two ways. Tseitin's encoding makes the variable equivalent to its
+// gate. Plaisted and Greenbaum's writes only the implications the gate's
+// occurrences need: a gate that only occurs positively needs its variable to
+// imply the gate, not the other way round, which saves about half the clauses
+// and keeps the models of the formula (projected on its variables).
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Formula {
+    Constant(bool),
+    Variable(String),
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+    Xor(Box<Formula>, Box<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    Iff(Box<Formula>, Box<Formula>),
+    // if the first then the second else the third
+    Ite(Box<Formula>, Box<Formula>, Box<Formula>),
+}
+
+impl Formula {
+    pub fn eval(&self, value: &impl Fn(&str) -> bool) -> bool {
+        return match self {
+            Formula::Constant(b) => *b,
+            Formula::Variable(name) => value(name),
+            Formula::Not(f) => !f.eval(value),
+            Formula::And(fs) => fs.iter().all(|f| f.eval(value)),
+            Formula::Or(fs) => fs.iter().any(|f| f.eval(value)),
+            Formula::Xor(a, b) => a.eval(value) != b.eval(value),
+            Formula::Implies(a, b) => !a.eval(value) || b.eval(value),
+            Formula::Iff(a, b) => a.eval(value) == b.eval(value),
+            Formula::Ite(c, t, e) => {
+                if c.eval(value) {
+                    t.eval(value)
+                } else {
+                    e.eval(value)
+                }
+            }
+        };
+    }
+
+    /// Appends the names of the variables, in the order they occur, as often
+    /// as they occur.
+    pub fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Formula::Constant(_) => {}
+            Formula::Variable(name) => names.push(name),
+            Formula::Not(f) => f.collect_variables(names),
+            Formula::And(fs) | Formula::Or(fs) => fs.iter().for_each(|f| f.collect_variables(names)),
+            Formula::Xor(a, b) | Formula::Implies(a, b) | Formula::Iff(a, b) => {
+                a.collect_variables(names);
+                b.collect_variables(names);
+            }
+            Formula::Ite(c, t, e) => {
+                c.collect_variables(names);
+                t.collect_variables(names);
+                e.collect_variables(names);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Open,
+    Close,
+    Comma,
+    Not,
+    And,
+    Or,
+    Xor,
+    Implies,
+    Iff,
+    Semicolon,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+            Token::Not => write!(f, "'!'"),
+            Token::And => write!(f, "'&'"),
+            Token::Or => write!(f, "'|'"),
+            Token::Xor => write!(f, "'^'"),
+            Token::Implies => write!(f, "'->'"),
+            Token::Iff => write!(f, "'<->'"),
+            Token::Semicolon => write!(f, "';'"),
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+// the tokens of the text, each with its line
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    for (n, line) in text.lines().enumerate().map(|(n, l)| (n + 1, l)) {
+        let line = line.split('#').next().unwrap();
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let token = match c {
+                _ if c.is_whitespace() => continue,
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                '!' => Token::Not,
+                '&' => Token::And,
+                '|' => Token::Or,
+                '^' => Token::Xor,
+                ';' => Token::Semicolon,
+                '-' if line[i..].starts_with("->") => {
+                    chars.next();
+                    Token::Implies
+                }
+                '<' if line[i..].starts_with("<->") => {
+                    chars.nth(1);
+                    Token::Iff
+                }
+                _ if is_name_char(c) => {
+                    let mut end = i + c.len_utf8();
+                    while let Some((j, d)) = chars.next_if(|(_, d)| is_name_char(*d)) {
+                        end = j + d.len_utf8();
+                    }
+                    Token::Name(line[i..end].to_string())
+                }
+                _ => return Err(format!("line {}: unexpected '{}'", n, c)),
+            };
+            tokens.push((token, n));
+        }
+    }
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(t, _)| t)
+    }
+
+    fn accept(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.next += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn error(&self, expected: &str) -> String {
+        match self.tokens.get(self.next) {
+            Some((token, line)) => format!("line {}: expected {}, found {}", line, expected, token),
+            None => format!("expected {} at the end of the input", expected),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        if !self.accept(&token) {
+            return Err(self.error(&token.to_string()));
+        }
+        return Ok(());
+    }
+
+    fn iff(&mut self) -> Result<Formula, String> {
+        let mut f = self.implies()?;
+        while self.accept(&Token::Iff) {
+            f = Formula::Iff(Box::new(f), Box::new(self.implies()?));
+        }
+        return Ok(f);
+    }
+
+    fn implies(&mut self) -> Result<Formula, String> {
+        let f = self.or()?;
+        if self.accept(&Token::Implies) {
+            return Ok(Formula::Implies(Box::new(f), Box::new(self.implies()?)));
+        }
+        return Ok(f);
+    }
+
+    fn or(&mut self) -> Result<Formula, String> {
+        let mut fs = vec![self.xor()?];
+        while self.accept(&Token::Or) {
+            fs.push(self.xor()?);
+        }
+        return Ok(if fs.len() == 1 { fs.pop().unwrap() } else { Formula::Or(fs) });
+    }
+
+    fn xor(&mut self) -> Result<Formula, String> {
+        let mut f = self.and()?;
+        while self.accept(&Token::Xor) {
+            f = Formula::Xor(Box::new(f), Box::new(self.and()?));
+        }
+        return Ok(f);
+    }
+
+    fn and(&mut self) -> Result<Formula, String> {
+        let mut fs = vec![self.unary()?];
+        while self.accept(&Token::And) {
+            fs.push(self.unary()?);
+        }
+        return Ok(if fs.len() == 1 { fs.pop().unwrap() } else { Formula::And(fs) });
+    }
+
+    fn unary(&mut self) -> Result<Formula, String> {
+        if self.accept(&Token::Not) {
+            return Ok(Formula::Not(Box::new(self.unary()?)));
+        }
+        if self.accept(&Token::Open) {
+            let f = self.iff()?;
+            self.expect(Token::Close)?;
+            return Ok(f);
+        }
+        let Some(Token::Name(name)) = self.peek().cloned() else {
+            return Err(self.error("a formula"));
+        };
+        self.next += 1;
+        return Ok(match name.as_str() {
+            "true" => Formula::Constant(true),
+            "false" => Formula::Constant(false),
+            "ite" => {
+                self.expect(Token::Open)?;
+                let c = self.iff()?;
+                self.expect(Token::Comma)?;
+                let t = self.iff()?;
+                self.expect(Token::Comma)?;
+                let e = self.iff()?;
+                self.expect(Token::Close)?;
+                Formula::Ite(Box::new(c), Box::new(t), Box::new(e))
+            }
+            _ => Formula::Variable(name),
+        });
+    }
+}
+
+/// Parses formulas separated by `;`; the last one may end without.
+pub fn parse(text: &str) -> Result<Vec<Formula>, String> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        next: 0,
+    };
+    let mut formulas = Vec::new();
+    while parser.peek().is_some() {
+        if parser.accept(&Token::Semicolon) {
+            continue;
+        }
+        formulas.push(parser.iff()?);
+        if parser.peek().is_some() {
+            parser.expect(Token::Semicolon)?;
+        }
+    }
+    return Ok(formulas);
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormulaEncoding {
+    /// Every gate variable is equivalent to its gate
+    Tseitin,
+    /// Only the implications the polarity of the gate's occurrences needs
+    #[value(alias = "pg")]
+    PlaistedGreenbaum,
+}
+
+// An input of a gate: the index of a node, shifted left, and whether it is
+// negated in the lowest bit. Node 0 is the constant false.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge(u32);
+
+const FALSE: Edge = Edge(0);
+const TRUE: Edge = Edge(1);
+
+impl Edge {
+    fn node(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    fn negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+}
+
+impl std::ops::Not for Edge {
+    type Output = Edge;
+    fn not(self) -> Edge {
+        Edge(self.0 ^ 1)
+    }
+}
+
+// the inputs of a gate are never constant, nor the same variable twice
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    False,
+    Input(Variable),
+    // sorted
+    And(Vec<Edge>),
+    // neither input negated, sorted
+    Xor(Edge, Edge),
+    // the condition not negated, nor the then-branch
+    Ite(Edge, Edge, Edge),
+}
+
+struct Circuit<'a, S> {
+    sink: &'a mut S,
+    encoding: FormulaEncoding,
+    nodes: Vec<Node>,
+    table: BTreeMap<Node, usize>,
+    // the literal of every node that has been written, and whether the
+    // implications from its literal to the gate and back are
+    literals: Vec<Option<Literal>>,
+    written: Vec<[bool; 2]>,
+    auxiliary: Vec<Variable>,
+}
+
+impl<S: ClauseSink> Circuit<'_, S> {
+    fn node(&mut self, node: Node) -> Edge {
+        let index = match self.table.get(&node) {
+            Some(i) => *i,
+            None => {
+                self.nodes.push(node.clone());
+                self.literals.push(None);
+                self.written.push([false, false]);
+                self.table.insert(node, self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        return Edge((index as u32) << 1);
+    }
+
+    fn and(&mut self, mut inputs: Vec<Edge>) -> Edge {
+        if inputs.contains(&FALSE) {
+            return FALSE;
+        }
+        inputs.retain(|e| *e != TRUE);
+        inputs.sort();
+        inputs.dedup();
+        // an edge and its negation end up next to each other
+        if inputs.windows(2).any(|w| w[0] == !w[1]) {
+            return FALSE;
+        }
+        return match inputs.len() {
+            0 => TRUE,
+            1 => inputs[0],
+            _ => self.node(Node::And(inputs)),
+        };
+    }
+
+    fn or(&mut self, inputs: Vec<Edge>) -> Edge {
+        let negated = inputs.into_iter().map(|e| !e).collect();
+        return !self.and(negated);
+    }
+
+    fn xor(&mut self, a: Edge, b: Edge) -> Edge {
+        let negated = Edge((a.negated() != b.negated()) as u32);
+        let (a, b) = (Edge(a.0 & !1), Edge(b.0 & !1));
+        let x = if a == b {
+            FALSE
+        } else if a == FALSE {
+            b
+        } else if b == FALSE {
+            a
+        } else {
+            self.node(Node::Xor(a.min(b), a.max(b)))
+        };
+        return Edge(x.0 ^ negated.0);
+    }
+
+    fn ite(&mut self, c: Edge, t: Edge, e: Edge) -> Edge {
+        if c.negated() {
+            return self.ite(!c, e, t);
+        }
+        if c == FALSE {
+            return e;
+        }
+        if t == e {
+            return t;
+        }
+        if t == !e {
+            return !self.xor(c, t);
+        }
+        if t == TRUE || t == c {
+            return self.or(vec![c, e]);
+        }
+        if t == FALSE || t == !c {
+            return self.and(vec![!c, e]);
+        }
+        if e == TRUE || e == !c {
+            return self.or(vec![!c, t]);
+        }
+        if e == FALSE || e == c {
+            return self.and(vec![c, t]);
+        }
+        if t.negated() {
+            return !self.ite(c, !t, !e);
+        }
+        return self.node(Node::Ite(c, t, e));
+    }
+
+    fn build(&mut self, f: &Formula, variables: &mut BTreeMap<String, Variable>) -> Edge {
+        return match f {
+            Formula::Constant(b) => Edge(*b as u32),
+            Formula::Variable(name) => {
+                let v = match variables.get(name) {
+                    Some(v) => *v,
+                    None => {
+                        let v = self.sink.fresh_variable();
+                        variables.insert(name.clone(), v);
+                        v
+                    }
+                };
+                self.node(Node::Input(v))
+            }
+            Formula::Not(f) => !self.build(f, variables),
+            Formula::And(fs) => {
+                let inputs = fs.iter().map(|f| self.build(f, variables)).collect();
+                self.and(inputs)
+            }
+            Formula::Or(fs) => {
+                let inputs = fs.iter().map(|f| self.build(f, variables)).collect();
+                self.or(inputs)
+            }
+            Formula::Xor(a, b) => {
+                let (a, b) = (self.build(a, variables), self.build(b, variables));
+                self.xor(a, b)
+            }
+            Formula::Implies(a, b) => {
+                let (a, b) = (self.build(a, variables), self.build(b, variables));
+                self.or(vec![!a, b])
+            }
+            Formula::Iff(a, b) => {
+                let (a, b) = (self.build(a, variables), self.build(b, variables));
+                !self.xor(a, b)
+            }
+            Formula::Ite(c, t, e) => {
+                let (c, t, e) = (self.build(c, variables), self.build(t, variables), self.build(e, variables));
+                self.ite(c, t, e)
+            }
+        };
+    }
+
+    // The literal of `e`, with the clauses defining it that are needed where
+    // it occurs: positively in clauses, negatively, or both.
+    fn literal(&mut self, e: Edge, positive: bool, negative: bool) -> Literal {
+        let node = e.node();
+        let output = match self.literals[node] {
+            Some(l) => l,
+            None => {
+                let l = match self.nodes[node] {
+                    Node::Input(v) => Literal {
+                        variable: v,
+                        polarity: Polarity::On,
+                    },
+                    _ => {
+                        let v = self.sink.fresh_variable();
+                        self.auxiliary.push(v);
+                        Literal {
+                            variable: v,
+                            polarity: Polarity::On,
+                        }
+                    }
+                };
+                self.literals[node] = Some(l);
+                l
+            }
+        };
+        // a negated edge occurs with the opposite polarity of its node
+        let needed = match (self.encoding, e.negated()) {
+            (FormulaEncoding::Tseitin, _) => [true, true],
+            (FormulaEncoding::PlaistedGreenbaum, false) => [positive, negative],
+            (FormulaEncoding::PlaistedGreenbaum, true) => [negative, positive],
+        };
+        for (i, direction) in [Direction::Implies, Direction::ImpliedBy].into_iter().enumerate() {
+            if needed[i] && !self.written[node][i] {
+                self.written[node][i] = true;
+                self.define(node, output, direction);
+            }
+        }
+        return if e.negated() { !output } else { output };
+    }
+
+    fn define(&mut self, node: usize, o: Literal, direction: Direction) {
+        match (self.nodes[node].clone(), direction) {
+            (Node::False, _) | (Node::Input(_), _) => {}
+            (Node::And(inputs), Direction::Implies) => {
+                for e in inputs {
+                    let l = self.literal(e, true, false);
+                    self.sink.add_clause(&[!o, l]);
+                }
+            }
+            (Node::And(inputs), Direction::ImpliedBy) => {
+                let mut clause = vec![o];
+                for e in inputs {
+                    clause.push(!self.literal(e, false, true));
+                }
+                self.sink.add_clause(&clause);
+            }
+            (Node::Xor(a, b), direction) => {
+                let a = self.literal(a, true, true);
+                let b = self.literal(b, true, true);
+                if direction == Direction::Implies {
+                    self.sink.add_clause(&[!o, a, b]);
+                    self.sink.add_clause(&[!o, !a, !b]);
+                } else {
+                    self.sink.add_clause(&[o, !a, b]);
+                    self.sink.add_clause(&[o, a, !b]);
+                }
+            }
+            (Node::Ite(c, t, e), direction) => {
+                let c = self.literal(c, true, true);
+                if direction == Direction::Implies {
+                    let t = self.literal(t, true, false);
+                    let e = self.literal(e, true, false);
+                    self.sink.add_clause(&[!o, !c, t]);
+                    self.sink.add_clause(&[!o, c, e]);
+                } else {
+                    let t = self.literal(t, false, true);
+                    let e = self.literal(e, false, true);
+                    self.sink.add_clause(&[o, !c, !t]);
+                    self.sink.add_clause(&[o, c, !e]);
+                }
+            }
+        }
+    }
+
+    // Asserts `e`: the inputs of a top-level AND are asserted one by one, and
+    // a top-level OR is a single clause, without variables of their own.
+    fn assert(&mut self, e: Edge) {
+        if e == TRUE {
+            return;
+        }
+        if e == FALSE {
+            // the empty clause, which the solver does not take
+            let v = self.sink.fresh_variable();
+            self.auxiliary.push(v);
+            let x = Literal {
+                variable: v,
+                polarity: Polarity::On,
+            };
+            self.sink.add_clause(&[x]);
+            self.sink.add_clause(&[!x]);
+            return;
+        }
+        match (self.nodes[e.node()].clone(), e.negated()) {
+            (Node::And(inputs), false) => {
+                for input in inputs {
+                    self.assert(input);
+                }
+            }
+            (Node::And(inputs), true) => {
+                let clause: Vec<Literal> = inputs.into_iter().map(|i| !self.literal(i, false, true)).collect();
+                self.sink.add_clause(&clause);
+            }
+            _ => {
+                let l = self.literal(e, true, false);
+                self.sink.add_clause(&[l]);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    // the gate's literal implies the gate
+    Implies,
+    // the gate implies its literal
+    ImpliedBy,
+}
+
+/// Writes clauses for `formulas`, which must all hold. The variables of the
+/// formulas are looked up in `variables`; those missing get fresh variables
+/// and are added. Returns the auxiliary variables of the gates.
+pub fn add_formulas(
+    sink: &mut impl ClauseSink,
+    formulas: &[Formula],
+    variables: &mut BTreeMap<String, Variable>,
+    encoding: FormulaEncoding,
+) -> Vec<Variable> {
+    let mut circuit = Circuit {
+        sink,
+        encoding,
+        nodes: vec![Node::False],
+        table: BTreeMap::new(),
+        literals: vec![None],
+        written: vec![[false, false]],
+        auxiliary: Vec::new(),
+    };
+    circuit.table.insert(Node::False, 0);
+    for f in formulas {
+        let e = circuit.build(f, variables);
+        circuit.assert(e);
+    }
+    return circuit.auxiliary;
+}
+
+/// A file of formulas, with its variables numbered from 1 in the order they
+/// first occur.
+pub struct FormulaInput {
+    pub formulas: Vec<Formula>,
+    pub names: Vec<String>,
+    pub encoding: FormulaEncoding,
+}
+
+impl FormulaInput {
+    pub fn read(path: &str, encoding: FormulaEncoding) -> Result<FormulaInput, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let formulas = parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+        let mut occurrences = Vec::new();
+        for f in formulas.iter() {
+            f.collect_variables(&mut occurrences);
+        }
+        let mut seen = BTreeSet::new();
+        let names = occurrences.into_iter().filter(|name| seen.insert(*name)).map(String::from).collect();
+        return Ok(FormulaInput { formulas, names, encoding });
+    }
+
+    /// The problem of the clauses of the formulas.
+    pub fn load(&self, heuristics: &mut impl Heuristics) -> Problem {
+        let mut problem = Problem::new();
+        let mut variables: BTreeMap<String, Variable> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), Variable { index: i as u32 + 1 }))
+            .collect();
+        // the gate variables come after those of the formulas
+        let mut sink = ProblemSink {
+            problem: &mut problem,
+            heuristics,
+            next_variable: self.names.len() as u32,
+        };
+        add_formulas(&mut sink, &self.formulas, &mut variables, self.encoding);
+        return problem;
+    }
+
+    /// Checks a model against the formulas; a variable missing from the model
+    /// is false.
+    pub fn check(&self, model: &Model) -> Result<(), String> {
+        let indices: BTreeMap<&str, u32> =
+            self.names.iter().enumerate().map(|(i, name)| (name.as_str(), i as u32 + 1)).collect();
+        let value = |name: &str| model.get(&indices[name]).copied().unwrap_or(false);
+        match self.formulas.iter().position(|f| !f.eval(&value)) {
+            Some(i) => Err(format!("the solution falsifies formula {}", i + 1)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::check_translation;
+    use crate::input::Translated;
+    use crate::rng::Rng;
+    use crate::testing::temp_file;
+
+    // the number of models of `text`, the same under both encodings
+    fn count_models(text: &str) -> u64 {
+        let path = temp_file("formula.bool", text);
+        let mut counts = Vec::new();
+        for encoding in [FormulaEncoding::Tseitin, FormulaEncoding::PlaistedGreenbaum] {
+            let input = FormulaInput::read(&path, encoding).unwrap();
+            counts.push(check_translation(&Translated::Formula(input), "vsids"));
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(counts[0], counts[1], "{}", text);
+        return counts[0];
+    }
+
+    // a random formula over a to e, as text
+    fn random_formula(rng: &mut Rng, depth: u32) -> String {
+        if depth == 0 || rng.below(4) == 0 {
+            return match rng.below(12) {
+                0 => String::from("true"),
+                1 => String::from("false"),
+                v => String::from(["a", "b", "c", "d", "e"][v as usize % 5]),
+            };
+        }
+        let operator = rng.below(7);
+        let mut f = || random_formula(rng, depth - 1);
+        return match operator {
+            0 => format!("!{}", f()),
+            1 => format!("({} & {})", f(), f()),
+            2 => format!("({} | {})", f(), f()),
+            3 => format!("({} ^ {})", f(), f()),
+            4 => format!("({} -> {})", f(), f()),
+            5 => format!("({} <-> {})", f(), f()),
+            _ => format!("ite({}, {}, {})", f(), f(), f()),
+        };
+    }
+
+    #[test]
+    fn small_formulas_have_their_models() {
+        // a, b and c in 2 ways, s, x and y in 6
+        assert_eq!(count_models("# a comment\n(a | b) & !c;\na -> (b <-> c);\nite(s, x ^ y, true)"), 2 * 6);
+        assert_eq!(count_models("a & !a"), 0);
+        assert_eq!(count_models("a | !a"), 2);
+        assert_eq!(count_models("true"), 1);
+        assert_eq!(count_models("false;"), 0);
+        // a subformula occurring with both polarities
+        assert_eq!(count_models("(a & b) <-> c; (a & b) | d"), 5);
+    }
+
+    #[test]
+    fn random_formulas_have_their_models() {
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let formulas: Vec<String> = (0..1 + rng.below(3)).map(|_| random_formula(&mut rng, 4)).collect();
+            count_models(&formulas.join(";\n"));
+        }
+    }
+}
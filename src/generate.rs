@@ -0,0 +1,445 @@
+use crate::encodings::{self, AmoEncoding, ClauseSink};
+use crate::rng::Rng;
+use crate::sat_solver::{Literal, Variable};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+// Instance generators, so that benchmark sets can be built without get.sh and
+// network access. Every family writes plain DIMACS with one clause per line.
+
+#[derive(clap::Args, Debug)]
+pub struct GenerateArgs {
+    #[command(subcommand)]
+    pub family: Family,
+
+    #[arg(long, default_value_t = 0, global = true)]
+    pub seed: u64,
+
+    /// Number of instances; with more than one, --output is a directory
+    #[arg(long, default_value_t = 1, global = true)]
+    pub count: usize,
+
+    /// Output file ("-" for stdout), or directory with --count
+    #[arg(long, short, default_value_t = String::from("-"), global = true)]
+    pub output: String,
+
+    /// Encoding of the at-most-one constraints of php, colouring and queens
+    #[arg(long, value_enum, default_value_t = AmoEncoding::Pairwise, global = true)]
+    pub amo_encoding: AmoEncoding,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Family {
+    /// Uniform random k-SAT: m clauses over k distinct variables out of n
+    Ksat {
+        #[arg(long, short)]
+        n: u32,
+        #[arg(long, short)]
+        m: usize,
+        #[arg(long, short, default_value_t = 3)]
+        k: u32,
+    },
+    /// Random k-SAT with a hidden solution: only clauses it satisfies are kept
+    Planted {
+        #[arg(long, short)]
+        n: u32,
+        #[arg(long, short)]
+        m: usize,
+        #[arg(long, short, default_value_t = 3)]
+        k: u32,
+    },
+    /// Pigeonhole principle: one pigeon more than holes (unsatisfiable)
+    Php {
+        #[arg(long)]
+        holes: u32,
+    },
+    /// Tseitin parity on a random regular graph with an odd total charge
+    /// (Urquhart-style, unsatisfiable)
+    Parity {
+        #[arg(long)]
+        vertices: u32,
+        #[arg(long, default_value_t = 3)]
+        degree: u32,
+    },
+    /// k-colouring of a random graph with the given number of edges
+    Colouring {
+        #[arg(long)]
+        vertices: u32,
+        #[arg(long)]
+        edges: usize,
+        #[arg(long, default_value_t = 3)]
+        colours: u32,
+    },
+    /// n queens on an n x n board
+    Queens {
+        #[arg(long, short)]
+        n: u32,
+    },
+}
+
+// a random graph gives up after this many attempts
+const GRAPH_ATTEMPTS: usize = 1000;
+
+pub struct Formula {
+    pub comments: Vec<String>,
+    pub num_vars: u32,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+impl Formula {
+    fn new(num_vars: u32) -> Self {
+        Formula {
+            comments: Vec::new(),
+            num_vars,
+            clauses: Vec::new(),
+        }
+    }
+
+    pub fn write(&self, w: &mut dyn Write) -> io::Result<()> {
+        for c in self.comments.iter() {
+            writeln!(w, "c {}", c)?;
+        }
+        writeln!(w, "p cnf {} {}", self.num_vars, self.clauses.len())?;
+        for clause in self.clauses.iter() {
+            for l in clause.iter() {
+                write!(w, "{} ", l)?;
+            }
+            writeln!(w, "0")?;
+        }
+        return Ok(());
+    }
+
+    // the auxiliary variables of the encoding come after all others
+    fn at_most_one(&mut self, literals: &[i32], encoding: AmoEncoding) {
+        let literals: Vec<Literal> = literals.iter().map(|l| Literal::from_dimacs(*l)).collect();
+        encodings::at_most_one(self, &literals, encoding);
+    }
+
+    fn exactly_one(&mut self, literals: &[i32], encoding: AmoEncoding) {
+        let literals: Vec<Literal> = literals.iter().map(|l| Literal::from_dimacs(*l)).collect();
+        encodings::exactly_one(self, &literals, encoding);
+    }
+}
+
+impl ClauseSink for Formula {
+    fn fresh_variable(&mut self) -> Variable {
+        self.num_vars += 1;
+        return Variable { index: self.num_vars };
+    }
+
+    fn add_clause(&mut self, literals: &[Literal]) {
+        self.clauses.push(literals.iter().map(|l| l.to_dimacs()).collect());
+    }
+}
+
+// k distinct variables out of 1..=n with random signs
+fn random_clause(n: u32, k: u32, rng: &mut Rng) -> Vec<i32> {
+    let mut vars = BTreeSet::new();
+    let mut clause = Vec::new();
+    while clause.len() < k as usize {
+        let v = rng.below(n as u64) as i32 + 1;
+        if vars.insert(v) {
+            clause.push(if rng.below(2) == 0 { -v } else { v });
+        }
+    }
+    return clause;
+}
+
+fn ksat(n: u32, m: usize, k: u32, rng: &mut Rng) -> Result<Formula, String> {
+    if k == 0 || k > n {
+        return Err(format!("k must be between 1 and n ({})", n));
+    }
+    let mut f = Formula::new(n);
+    f.comments.push(format!("uniform random {}-SAT, n={} m={}", k, n, m));
+    f.clauses = (0..m).map(|_| random_clause(n, k, rng)).collect();
+    return Ok(f);
+}
+
+fn planted(n: u32, m: usize, k: u32, rng: &mut Rng) -> Result<Formula, String> {
+    if k == 0 || k > n {
+        return Err(format!("k must be between 1 and n ({})", n));
+    }
+    let solution: Vec<bool> = (0..n).map(|_| rng.below(2) == 1).collect();
+    let satisfied = |l: &i32| solution[l.unsigned_abs() as usize - 1] == (*l > 0);
+    let mut f = Formula::new(n);
+    f.comments.push(format!("random {}-SAT with a planted solution, n={} m={}", k, n, m));
+    let values: Vec<String> = (1..=n as i32).map(|v| if satisfied(&v) { v } else { -v }.to_string()).collect();
+    f.comments.push(format!("planted {}", values.join(" ")));
+    while f.clauses.len() < m {
+        let clause = random_clause(n, k, rng);
+        if clause.iter().any(satisfied) {
+            f.clauses.push(clause);
+        }
+    }
+    return Ok(f);
+}
+
+fn php(holes: u32, amo: AmoEncoding) -> Result<Formula, String> {
+    if holes == 0 {
+        return Err(String::from("need at least one hole"));
+    }
+    let pigeons = holes + 1;
+    // pigeon p sits in hole h
+    let var = |p: u32, h: u32| (p * holes + h + 1) as i32;
+    let mut f = Formula::new(pigeons * holes);
+    f.comments.push(format!("pigeonhole, {} pigeons in {} holes", pigeons, holes));
+    for p in 0..pigeons {
+        f.clauses.push((0..holes).map(|h| var(p, h)).collect());
+    }
+    for h in 0..holes {
+        let column: Vec<i32> = (0..pigeons).map(|p| var(p, h)).collect();
+        f.at_most_one(&column, amo);
+    }
+    return Ok(f);
+}
+
+// A random simple d-regular graph by the pairing model: the d copies of every
+// vertex are matched at random, and the matching is redrawn until it has no
+// loops or repeated edges.
+fn regular_graph(vertices: u32, degree: u32, rng: &mut Rng) -> Result<Vec<(u32, u32)>, String> {
+    if degree >= vertices || (vertices * degree) % 2 == 1 {
+        return Err(format!("no {}-regular graph on {} vertices", degree, vertices));
+    }
+    for _ in 0..GRAPH_ATTEMPTS {
+        let mut points: Vec<u32> = (0..vertices).flat_map(|v| (0..degree).map(move |_| v)).collect();
+        rng.shuffle(&mut points);
+        let mut edges = BTreeSet::new();
+        let simple = points.chunks(2).all(|pair| {
+            let (a, b) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+            a != b && edges.insert((a, b))
+        });
+        if simple {
+            return Ok(edges.into_iter().collect());
+        }
+    }
+    return Err(format!("no simple {}-regular graph on {} vertices found, try a smaller degree", degree, vertices));
+}
+
+fn parity(vertices: u32, degree: u32, rng: &mut Rng) -> Result<Formula, String> {
+    let edges = regular_graph(vertices, degree, rng)?;
+    // one variable per edge; the charges have an odd sum
+    let mut charge: Vec<bool> = (0..vertices).map(|_| rng.below(2) == 1).collect();
+    if charge.iter().filter(|c| **c).count() % 2 == 0 {
+        charge[0] = !charge[0];
+    }
+    let mut f = Formula::new(edges.len() as u32);
+    f.comments.push(format!("Tseitin parity on a random {}-regular graph with {} vertices", degree, vertices));
+    for v in 0..vertices {
+        let incident: Vec<i32> = (0..edges.len())
+            .filter(|e| edges[*e].0 == v || edges[*e].1 == v)
+            .map(|e| e as i32 + 1)
+            .collect();
+        // XOR of the incident edges equals the charge: forbid every sign
+        // pattern of the wrong parity
+        for pattern in 0u32..(1 << incident.len()) {
+            let negated = pattern.count_ones() % 2 == 1;
+            // the clause is falsified exactly by the assignment that sets the
+            // negated edges and clears the others, whose parity is `negated`
+            if negated == charge[v as usize] {
+                continue;
+            }
+            f.clauses.push(
+                incident
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| if pattern & (1 << i) != 0 { -e } else { *e })
+                    .collect(),
+            );
+        }
+    }
+    return Ok(f);
+}
+
+fn colouring(vertices: u32, edges: usize, colours: u32, amo: AmoEncoding, rng: &mut Rng) -> Result<Formula, String> {
+    let possible = vertices as usize * (vertices as usize).saturating_sub(1) / 2;
+    if edges > possible || colours == 0 {
+        return Err(format!("at most {} edges on {} vertices, and at least one colour", possible, vertices));
+    }
+    let mut graph = BTreeSet::new();
+    while graph.len() < edges {
+        let a = rng.below(vertices as u64) as u32;
+        let b = rng.below(vertices as u64) as u32;
+        if a != b {
+            graph.insert((a.min(b), a.max(b)));
+        }
+    }
+    // vertex v has colour c
+    let var = |v: u32, c: u32| (v * colours + c + 1) as i32;
+    let mut f = Formula::new(vertices * colours);
+    f.comments.push(format!("{}-colouring of a random graph, {} vertices and {} edges", colours, vertices, edges));
+    for v in 0..vertices {
+        let options: Vec<i32> = (0..colours).map(|c| var(v, c)).collect();
+        f.exactly_one(&options, amo);
+    }
+    for (a, b) in graph {
+        for c in 0..colours {
+            f.clauses.push(vec![-var(a, c), -var(b, c)]);
+        }
+    }
+    return Ok(f);
+}
+
+fn queens(n: u32, amo: AmoEncoding) -> Result<Formula, String> {
+    if n == 0 {
+        return Err(String::from("the board needs at least one square"));
+    }
+    let n = n as i32;
+    // a queen on row r, column c
+    let var = |r: i32, c: i32| r * n + c + 1;
+    let mut f = Formula::new((n * n) as u32);
+    f.comments.push(format!("{} queens", n));
+    for r in 0..n {
+        let row: Vec<i32> = (0..n).map(|c| var(r, c)).collect();
+        f.exactly_one(&row, amo);
+    }
+    for c in 0..n {
+        let column: Vec<i32> = (0..n).map(|r| var(r, c)).collect();
+        f.at_most_one(&column, amo);
+    }
+    // diagonals have a constant r - c, anti-diagonals a constant r + c
+    for d in -(n - 1)..n {
+        let diagonal: Vec<i32> = (0..n).filter(|r| (0..n).contains(&(r - d))).map(|r| var(r, r - d)).collect();
+        f.at_most_one(&diagonal, amo);
+    }
+    for s in 0..2 * n - 1 {
+        let anti: Vec<i32> = (0..n).filter(|r| (0..n).contains(&(s - r))).map(|r| var(r, s - r)).collect();
+        f.at_most_one(&anti, amo);
+    }
+    return Ok(f);
+}
+
+fn generate_one(family: &Family, amo: AmoEncoding, rng: &mut Rng) -> Result<Formula, String> {
+    match *family {
+        Family::Ksat { n, m, k } => ksat(n, m, k, rng),
+        Family::Planted { n, m, k } => planted(n, m, k, rng),
+        Family::Php { holes } => php(holes, amo),
+        Family::Parity { vertices, degree } => parity(vertices, degree, rng),
+        Family::Colouring { vertices, edges, colours } => colouring(vertices, edges, colours, amo, rng),
+        Family::Queens { n } => queens(n, amo),
+    }
+}
+
+fn family_name(family: &Family) -> &'static str {
+    match family {
+        Family::Ksat { .. } => "ksat",
+        Family::Planted { .. } => "planted",
+        Family::Php { .. } => "php",
+        Family::Parity { .. } => "parity",
+        Family::Colouring { .. } => "colouring",
+        Family::Queens { .. } => "queens",
+    }
+}
+
+pub fn generate(args: &GenerateArgs) -> Result<(), String> {
+    let mut rng = Rng::new(args.seed);
+    if args.count <= 1 {
+        let mut f = generate_one(&args.family, args.amo_encoding, &mut rng)?;
+        f.comments.push(format!("seed {}", args.seed));
+        let result = if args.output == "-" {
+            f.write(&mut io::stdout().lock())
+        } else {
+            fs::File::create(&args.output).and_then(|mut file| f.write(&mut file))
+        };
+        return result.map_err(|e| format!("{}: {}", args.output, e));
+    }
+
+    if args.output == "-" {
+        return Err(String::from("--count needs --output DIR"));
+    }
+    fs::create_dir_all(&args.output).map_err(|e| format!("{}: {}", args.output, e))?;
+    for i in 0..args.count {
+        let mut f = generate_one(&args.family, args.amo_encoding, &mut rng)?;
+        f.comments.push(format!("seed {}, instance {}", args.seed, i));
+        let path = Path::new(&args.output).join(format!("{}_{:04}.cnf", family_name(&args.family), i));
+        fs::File::create(&path)
+            .and_then(|mut file| f.write(&mut file))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    println!("GENERATED: {} instances in {}", args.count, args.output);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::heuristics::Heuristics;
+    use crate::heuristics::vsids::VSIDS;
+    use crate::sat_solver::incremental::Solver;
+    use crate::sat_solver::Problem;
+    use crate::verify::{self, Cnf, Model};
+    use clap::ValueEnum;
+    use std::sync::atomic::AtomicBool;
+
+    // a model of the formula, checked against its clauses
+    fn solve(f: &Formula) -> Option<Model> {
+        let mut solver = Solver::new(Problem::new(), VSIDS::with_options(Default::default()));
+        for _ in 0..f.num_vars {
+            solver.add_variable();
+        }
+        for clause in f.clauses.iter() {
+            let literals: Vec<Literal> = clause.iter().map(|l| Literal::from_dimacs(*l)).collect();
+            solver.add_clause(&literals);
+        }
+        let solution = solver.solve(&[], &AtomicBool::new(false))?;
+        let model: Model = crate::enumerate::model(&solution).into_iter().collect();
+        let cnf = Cnf {
+            declared_variables: Some(f.num_vars),
+            clauses: f.clauses.clone(),
+            xors: Vec::new(),
+            projection: None,
+            prefix: Vec::new(),
+        };
+        assert!(verify::check(&cnf, &model).unwrap().ok());
+        return Some(model);
+    }
+
+    fn text(f: &Formula) -> String {
+        let mut buffer = Vec::new();
+        f.write(&mut buffer).unwrap();
+        return String::from_utf8(buffer).unwrap();
+    }
+
+    #[test]
+    fn pigeons_do_not_fit() {
+        for amo in AmoEncoding::value_variants() {
+            for holes in 1..=4 {
+                assert!(solve(&php(holes, *amo).unwrap()).is_none(), "{:?}, {} holes", amo, holes);
+            }
+        }
+    }
+
+    #[test]
+    fn planted_solutions_satisfy_every_clause() {
+        let mut rng = Rng::new(1);
+        for _ in 0..20 {
+            // well above the threshold, where uniform random 3-SAT is UNSAT
+            let f = planted(20, 200, 3, &mut rng).unwrap();
+            let planted: Model = f.comments[1]
+                .strip_prefix("planted ")
+                .unwrap()
+                .split_whitespace()
+                .map(|l| l.parse::<i32>().unwrap())
+                .map(|l| (l.unsigned_abs(), l > 0))
+                .collect();
+            assert!(f.clauses.iter().all(|c| c.iter().any(|l| planted[&l.unsigned_abs()] == (*l > 0))));
+            assert!(solve(&f).is_some());
+        }
+    }
+
+    #[test]
+    fn the_seed_fixes_the_instance() {
+        let families = [
+            Family::Ksat { n: 30, m: 120, k: 3 },
+            Family::Planted { n: 30, m: 120, k: 3 },
+            Family::Parity { vertices: 10, degree: 3 },
+            Family::Colouring { vertices: 10, edges: 20, colours: 3 },
+        ];
+        for family in families.iter() {
+            let instance = |seed| text(&generate_one(family, AmoEncoding::Pairwise, &mut Rng::new(seed)).unwrap());
+            assert_eq!(instance(5), instance(5), "{:?}", family);
+            assert_ne!(instance(5), instance(6), "{:?}", family);
+        }
+    }
+}
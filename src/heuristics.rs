@@ -2,3 +2,10 @@ pub mod heuristics;
 pub mod ascending;
 pub mod vsids;
 pub mod dlis;
+pub mod jeroslow_wang;
+pub mod moms;
+pub mod chb;
+pub mod lrb;
+pub mod lookahead;
+pub mod polarity;
+pub mod registry;
@@ -21,12 +21,14 @@ impl Debug for Ascending {
         for v in self.variable_unassigned.iter() {
             _ = write!(f, "{}", v.index);
         }
-        writeln!(f, "")
+        writeln!(f)
     }
 }
 
 impl Heuristics for Ascending {
-    fn new() -> Self {
+    type Options = NoOptions;
+
+    fn with_options(_options: NoOptions) -> Self {
         Ascending {
             variable_unassigned: BTreeSet::<Variable>::new(),
             variable_assigned: BTreeSet::<Variable>::new(),
@@ -0,0 +1,136 @@
+use crate::heuristics::heuristics::*;
+use crate::sat_solver::*;
+use core::fmt;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CHBOptions {
+    // step size of the exponential recency weighted average, decaying from
+    // alpha_start to alpha_min by alpha_decay per conflict
+    pub alpha_start: f64,
+    pub alpha_min: f64,
+    pub alpha_decay: f64,
+}
+
+// defaults from Liang et al., 2016
+impl Default for CHBOptions {
+    fn default() -> Self {
+        CHBOptions {
+            alpha_start: 0.4,
+            alpha_min: 0.06,
+            alpha_decay: 1e-6,
+        }
+    }
+}
+
+/// Conflict History-Based branching. Every time a variable is assigned, its
+/// score Q moves towards a reward that is higher the more recently the
+/// variable took part in a conflict.
+pub struct CHB {
+    pub score: BTreeMap<Variable, f64>,
+    pub last_conflict: BTreeMap<Variable, u64>,
+    pub variable_unassigned: BTreeSet<Variable>,
+    // used to pick a polarity: the more frequent literal of the input wins
+    pub literal_frequency: BTreeMap<Literal, u64>,
+    pub conflicts: u64,
+    pub alpha: f64,
+    pub options: CHBOptions,
+    use_bcp: bool,
+}
+
+impl Debug for CHB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        _ = writeln!(f, "CHB: print (alpha {}, conflicts {})", self.alpha, self.conflicts);
+        for (v, q) in self.score.iter() {
+            _ = write!(f, "{v:?} {q} ");
+        }
+        writeln!(f)
+    }
+}
+
+impl Heuristics for CHB {
+    type Options = CHBOptions;
+
+    fn with_options(options: CHBOptions) -> Self {
+        CHB {
+            score: BTreeMap::new(),
+            last_conflict: BTreeMap::new(),
+            variable_unassigned: BTreeSet::new(),
+            literal_frequency: BTreeMap::new(),
+            conflicts: 0,
+            alpha: options.alpha_start,
+            options,
+            use_bcp: false,
+        }
+    }
+
+    fn add_parsed_clause(&mut self, c: &Clause) {
+        for l in c.list_of_literals.iter() {
+            self.score.entry(l.variable).or_insert(0.0);
+            self.variable_unassigned.insert(l.variable);
+            *self.literal_frequency.entry(*l).or_insert(0) += 1;
+        }
+        trace!(target: "chb", "CHB: add clause {c:?}");
+    }
+
+    fn add_conflict_clause(&mut self, c: &Clause) {
+        self.conflicts += 1;
+        self.alpha = (self.alpha - self.options.alpha_decay).max(self.options.alpha_min);
+        for l in c.list_of_literals.iter() {
+            self.last_conflict.insert(l.variable, self.conflicts);
+        }
+        trace!(target: "chb", "CHB: conflict #{} on clause {c:?}", self.conflicts);
+    }
+
+    fn decide(&mut self) -> Option<Literal> {
+        let (v, q) = self
+            .variable_unassigned
+            .iter()
+            .map(|v| (*v, self.score[v]))
+            .fold(None, |best: Option<(Variable, f64)>, (v, q)| match best {
+                Some((_, bq)) if bq >= q => best,
+                _ => Some((v, q)),
+            })?;
+        let on = Literal { variable: v, polarity: Polarity::On };
+        let count = |l: &Literal| self.literal_frequency.get(l).copied().unwrap_or(0);
+        let l = if count(&on) >= count(&!on) { on } else { !on };
+        trace!(target: "chb", "CHB: decide {l:?} with score {q}");
+        Some(l)
+    }
+
+    fn assign_variable(&mut self, var: Variable) {
+        assert!(self.variable_unassigned.remove(&var));
+        // a variable that was part of the latest conflict gets the full reward
+        let last = self.last_conflict.get(&var).copied().unwrap_or(0);
+        let multiplier = if last == self.conflicts && last > 0 { 1.0 } else { 0.9 };
+        let reward = multiplier / ((self.conflicts - last + 1) as f64);
+        let q = self.score.get_mut(&var).unwrap();
+        *q = (1.0 - self.alpha) * *q + self.alpha * reward;
+        trace!(target: "chb", "CHB: assign variable {var:?}");
+    }
+
+    fn unassign_variable(&mut self, var: Variable) {
+        assert!(self.variable_unassigned.insert(var));
+        trace!(target: "chb", "CHB: unassign variable {var:?}");
+    }
+
+    fn satisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn unsatisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn set_use_bcp(&mut self, _use_bcp: bool) {
+        self.use_bcp = _use_bcp;
+    }
+
+    fn use_bcp(&self) -> bool {
+        self.use_bcp
+    }
+}
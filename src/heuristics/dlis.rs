@@ -24,8 +24,10 @@ impl Debug for DLIS {
 }
 
 impl Heuristics for DLIS {
+    type Options = NoOptions;
+
     // creates a new heuristics struct
-    fn new() -> Self {
+    fn with_options(_options: NoOptions) -> Self {
         DLIS {
             literal_frequency: HashMap::<Literal, u64>::new(),
             frequency_literal_assigned: HashSet::<(u64, Literal)>::new(),
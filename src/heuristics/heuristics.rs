@@ -1,10 +1,41 @@
 use crate::sat_solver::*;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fmt::Debug;
+
+// Options of heuristics that take no parameters
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoOptions {}
 
 pub trait Heuristics {
-    fn new() -> Self;
+    // Typed parameters, filled in by the registry from --heuristic-opt and the
+    // config file. Fields missing there keep their Default value.
+    type Options: Debug + Clone + Default + DeserializeOwned + Send;
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self::with_options(Self::Options::default())
+    }
+    fn with_options(options: Self::Options) -> Self;
+    // Rejects option values out of range, which `with_options` may assume
+    // never to get.
+    fn check_options(_options: &Self::Options) -> Result<(), String>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
     fn add_parsed_clause(&mut self, c: &Clause);
     fn add_conflict_clause(&mut self, c: &Clause);
     fn decide(&mut self) -> Option<Literal>;
+    // Heuristics that need to inspect (or tentatively propagate on) the
+    // current state of the problem override this; any change they make to the
+    // problem must be undone before returning.
+    fn decide_with_problem(&mut self, _p: &mut Problem) -> Option<Literal> {
+        self.decide()
+    }
     fn assign_variable(&mut self, var: Variable);
     fn unassign_variable(&mut self, var: Variable);
     fn satisfy_clause(&mut self, c: &Clause);
@@ -14,4 +45,9 @@ pub trait Heuristics {
         /* default impl */
         false
     }
+    // Heuristics making random choices draw them from an Rng seeded here
+    // (once, before solving), so that runs with the same --seed agree.
+    fn set_seed(&mut self, _seed: u64) {
+        /* default impl: nothing random */
+    }
 }
@@ -0,0 +1,149 @@
+use crate::heuristics::heuristics::*;
+use crate::sat_solver::*;
+use core::fmt;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JeroslowWangOptions {
+    // score a variable by J(x) + J(x') instead of its heavier literal alone
+    pub two_sided: bool,
+}
+
+impl Default for JeroslowWangOptions {
+    fn default() -> Self {
+        JeroslowWangOptions { two_sided: true }
+    }
+}
+
+pub struct JeroslowWang {
+    // J(l) = sum of 2^-|c| over the input clauses c containing l
+    pub literal_weight: BTreeMap<Literal, f64>,
+    pub variable_assigned: BTreeSet<Variable>,
+    pub options: JeroslowWangOptions,
+    use_bcp: bool,
+}
+
+impl Debug for JeroslowWang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        _ = writeln!(f, "JeroslowWang: print");
+        for (l, w) in self.literal_weight.iter() {
+            _ = write!(f, "{l:?} {w} ");
+        }
+        writeln!(f)
+    }
+}
+
+// Two-sided rule: pick the variable with the largest J(x) + J(x'), and assign
+// it the polarity with the larger J. One-sided: pick the literal with the
+// largest J.
+fn pick(weights: &BTreeMap<Literal, f64>, two_sided: bool) -> Option<Literal> {
+    let mut best: Option<(f64, Literal)> = None;
+    for (l, w) in weights.iter() {
+        let w_other = weights.get(&!*l).copied().unwrap_or(0.0);
+        // every variable is visited twice, keep the heavier literal
+        if *w < w_other {
+            continue;
+        }
+        let score = if two_sided { w + w_other } else { *w };
+        if best.is_none_or(|(s, _)| score > s) {
+            best = Some((score, *l));
+        }
+    }
+    best.map(|(_, l)| l)
+}
+
+impl Heuristics for JeroslowWang {
+    type Options = JeroslowWangOptions;
+
+    fn with_options(options: JeroslowWangOptions) -> Self {
+        JeroslowWang {
+            literal_weight: BTreeMap::new(),
+            variable_assigned: BTreeSet::new(),
+            options,
+            use_bcp: false,
+        }
+    }
+
+    fn add_parsed_clause(&mut self, c: &Clause) {
+        let weight = 0.5f64.powi(c.list_of_literals.len() as i32);
+        for l in c.list_of_literals.iter() {
+            *self.literal_weight.entry(*l).or_insert(0.0) += weight;
+        }
+        trace!(target: "jw", "JeroslowWang: add clause {c:?}");
+    }
+
+    fn add_conflict_clause(&mut self, _c: &Clause) {
+
+    }
+
+    // static weights of the input clauses, restricted to unassigned variables
+    fn decide(&mut self) -> Option<Literal> {
+        let unassigned: BTreeMap<Literal, f64> = self
+            .literal_weight
+            .iter()
+            .filter(|(l, _)| !self.variable_assigned.contains(&l.variable))
+            .map(|(l, w)| (*l, *w))
+            .collect();
+        let l = pick(&unassigned, self.options.two_sided)?;
+        trace!(target: "jw", "JeroslowWang: decide {l:?}");
+        Some(l)
+    }
+
+    // weights recomputed over the unresolved clauses, counting only their
+    // unassigned literals
+    fn decide_with_problem(&mut self, p: &mut Problem) -> Option<Literal> {
+        let mut weights = BTreeMap::<Literal, f64>::new();
+        for rc in p.list_of_clauses.iter() {
+            let c = rc.borrow();
+            let states: Vec<LiteralState> =
+                c.list_of_literal_infos.iter().map(|li| li.borrow().status).collect();
+            if states.contains(&LiteralState::Sat) {
+                continue;
+            }
+            let free = states.iter().filter(|s| **s == LiteralState::Unknown).count();
+            let weight = 0.5f64.powi(free as i32);
+            for (l, s) in c.list_of_literals.iter().zip(states.iter()) {
+                if *s == LiteralState::Unknown {
+                    *weights.entry(*l).or_insert(0.0) += weight;
+                }
+            }
+        }
+        if weights.is_empty() {
+            // every clause is satisfied, the remaining variables are free
+            return self.decide();
+        }
+        let l = pick(&weights, self.options.two_sided)?;
+        trace!(target: "jw", "JeroslowWang: decide {l:?}");
+        Some(l)
+    }
+
+    fn assign_variable(&mut self, var: Variable) {
+        self.variable_assigned.insert(var);
+        trace!(target: "jw", "JeroslowWang: assign variable {var:?}");
+    }
+
+    fn unassign_variable(&mut self, var: Variable) {
+        self.variable_assigned.remove(&var);
+        trace!(target: "jw", "JeroslowWang: unassign variable {var:?}");
+    }
+
+    fn satisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn unsatisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn set_use_bcp(&mut self, _use_bcp: bool) {
+        self.use_bcp = _use_bcp;
+    }
+
+    fn use_bcp(&self) -> bool {
+        self.use_bcp
+    }
+}
@@ -0,0 +1,231 @@
+use crate::heuristics::heuristics::*;
+use crate::profiler::SolverProfiler;
+use crate::sat_solver::dpll::*;
+use crate::sat_solver::*;
+use core::fmt;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LookaheadOptions {
+    // number of free variables (the most frequent ones in the input) that are
+    // looked ahead on at every decision
+    pub preselect: usize,
+}
+
+impl Default for LookaheadOptions {
+    fn default() -> Self {
+        LookaheadOptions { preselect: 16 }
+    }
+}
+
+pub struct Lookahead {
+    pub variable_occurrences: BTreeMap<Variable, u64>,
+    pub variable_assigned: BTreeSet<Variable>,
+    pub options: LookaheadOptions,
+    // counts the tentative propagations, kept apart from the real profiler
+    probe_profiler: SolverProfiler,
+    use_bcp: bool,
+}
+
+impl Debug for Lookahead {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Lookahead: {}", self.probe_profiler)
+    }
+}
+
+// Heuristics handed to the existing BCP while probing. The lookahead reads all
+// the state it needs from the Problem, so nothing has to be tracked here.
+struct Probe;
+
+impl Heuristics for Probe {
+    type Options = NoOptions;
+
+    fn with_options(_options: NoOptions) -> Self {
+        Probe
+    }
+    fn add_parsed_clause(&mut self, _c: &Clause) {}
+    fn add_conflict_clause(&mut self, _c: &Clause) {}
+    fn decide(&mut self) -> Option<Literal> {
+        None
+    }
+    fn assign_variable(&mut self, _var: Variable) {}
+    fn unassign_variable(&mut self, _var: Variable) {}
+    fn satisfy_clause(&mut self, _c: &Clause) {}
+    fn unsatisfy_clause(&mut self, _c: &Clause) {}
+    fn set_use_bcp(&mut self, _use_bcp: bool) {}
+    fn use_bcp(&self) -> bool {
+        true
+    }
+}
+
+enum ProbeResult {
+    // propagating the literal runs into a conflict
+    Failed,
+    // number of clauses that lost a literal but are not satisfied
+    Reduced(u64),
+}
+
+impl Lookahead {
+    /// Tentatively assigns `l`, propagates with the existing BCP, measures the
+    /// result and undoes everything again. The XOR and cardinality
+    /// constraints are left out: their explanations would be added to the
+    /// clauses.
+    fn probe(&mut self, p: &mut Problem, l: Literal) -> ProbeResult {
+        let mut trail = SolutionStack { stack: vec![] };
+        let mut probe = Probe;
+        let xor_constraints = std::mem::take(&mut p.xor_constraints);
+        let cardinality_constraints = std::mem::take(&mut p.cardinality_constraints);
+
+        trail.push_free_choice_first_try(l.variable, l.polarity);
+        mark_variable_assigned(p, l.variable);
+        update_literal_info(p, l.variable, l.polarity, UpdateLiteralInfoCause::FreeAssignment, &mut probe);
+        let no_conflict = boolean_constraint_propagation(p, &mut trail, &mut probe, &mut self.probe_profiler);
+
+        let result = if no_conflict {
+            let mut reduced = BTreeSet::<u32>::new();
+            for step in trail.stack.iter() {
+                let falsified = Literal {
+                    variable: step.assignment.variable,
+                    polarity: !step.assignment.polarity,
+                };
+                if let Some(li) = p.list_of_literal_infos.get(&falsified) {
+                    for rc in li.borrow().list_of_clauses.iter() {
+                        let c = rc.borrow();
+                        if c.recalculate_clause_state(p) != ClauseState::Satisfied {
+                            reduced.insert(c.id);
+                        }
+                    }
+                }
+            }
+            ProbeResult::Reduced(reduced.len() as u64)
+        } else {
+            ProbeResult::Failed
+        };
+
+        for step in trail.stack.iter() {
+            undo_assignment(p, step.assignment.variable);
+        }
+        p.list_of_clauses_to_check.clear();
+        p.xor_constraints = xor_constraints;
+        p.cardinality_constraints = cardinality_constraints;
+        result
+    }
+}
+
+impl Heuristics for Lookahead {
+    type Options = LookaheadOptions;
+
+    fn with_options(options: LookaheadOptions) -> Self {
+        Lookahead {
+            variable_occurrences: BTreeMap::new(),
+            variable_assigned: BTreeSet::new(),
+            options,
+            probe_profiler: SolverProfiler::new(),
+            use_bcp: false,
+        }
+    }
+
+    fn add_parsed_clause(&mut self, c: &Clause) {
+        for l in c.list_of_literals.iter() {
+            *self.variable_occurrences.entry(l.variable).or_insert(0) += 1;
+        }
+        trace!(target: "lookahead", "Lookahead: add clause {c:?}");
+    }
+
+    fn add_conflict_clause(&mut self, _c: &Clause) {
+
+    }
+
+    // Without access to the problem there is nothing to look ahead on, so
+    // fall back to the most frequent unassigned variable.
+    fn decide(&mut self) -> Option<Literal> {
+        self.variable_occurrences
+            .iter()
+            .filter(|(v, _)| !self.variable_assigned.contains(v))
+            .max_by_key(|(_, n)| **n)
+            .map(|(v, _)| Literal {
+                variable: *v,
+                polarity: Polarity::On,
+            })
+    }
+
+    // march_cu-style: score every preselected variable by the mixed diff of
+    // the clauses reduced by either polarity, and branch on the polarity that
+    // reduces fewer clauses first. A failed literal is answered with its
+    // complement right away.
+    // Probing relies on the watch literals, which the search only maintains
+    // when BCP is enabled; without BCP we fall back to decide().
+    fn decide_with_problem(&mut self, p: &mut Problem) -> Option<Literal> {
+        if !self.use_bcp {
+            return self.decide();
+        }
+        debug_assert!(p.list_of_clauses_to_check.is_empty());
+
+        let mut candidates: Vec<(u64, Variable)> = self
+            .variable_occurrences
+            .iter()
+            .filter(|(v, _)| p.list_of_variables.get(v) == Some(&VariableState::Unassigned))
+            .map(|(v, n)| (*n, *v))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.sort_by(|x, y| y.cmp(x));
+        candidates.truncate(self.options.preselect.max(1));
+
+        let mut best: Option<(u64, Literal)> = None;
+        for (_, v) in candidates {
+            let pos = Literal { variable: v, polarity: Polarity::On };
+            let neg = Literal { variable: v, polarity: Polarity::Off };
+            match (self.probe(p, pos), self.probe(p, neg)) {
+                (ProbeResult::Failed, _) => {
+                    trace!(target: "lookahead", "Lookahead: failed literal {pos:?}");
+                    return Some(neg);
+                }
+                (_, ProbeResult::Failed) => {
+                    trace!(target: "lookahead", "Lookahead: failed literal {neg:?}");
+                    return Some(pos);
+                }
+                (ProbeResult::Reduced(dp), ProbeResult::Reduced(dn)) => {
+                    let mixed = 1024 * dp * dn + dp + dn;
+                    let l = if dp <= dn { pos } else { neg };
+                    if best.is_none_or(|(s, _)| mixed > s) {
+                        best = Some((mixed, l));
+                    }
+                }
+            }
+        }
+
+        let (score, l) = best.unwrap();
+        trace!(target: "lookahead", "Lookahead: decide {l:?} with score {score}");
+        Some(l)
+    }
+
+    fn assign_variable(&mut self, var: Variable) {
+        self.variable_assigned.insert(var);
+    }
+
+    fn unassign_variable(&mut self, var: Variable) {
+        self.variable_assigned.remove(&var);
+    }
+
+    fn satisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn unsatisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn set_use_bcp(&mut self, _use_bcp: bool) {
+        self.use_bcp = _use_bcp;
+    }
+
+    fn use_bcp(&self) -> bool {
+        self.use_bcp
+    }
+}
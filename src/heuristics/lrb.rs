@@ -0,0 +1,145 @@
+use crate::heuristics::heuristics::*;
+use crate::sat_solver::*;
+use core::fmt;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LRBOptions {
+    // step size of the exponential recency weighted average, decaying from
+    // alpha_start to alpha_min by alpha_decay per conflict
+    pub alpha_start: f64,
+    pub alpha_min: f64,
+    pub alpha_decay: f64,
+}
+
+// defaults from Liang et al., 2016
+impl Default for LRBOptions {
+    fn default() -> Self {
+        LRBOptions {
+            alpha_start: 0.4,
+            alpha_min: 0.06,
+            alpha_decay: 1e-6,
+        }
+    }
+}
+
+/// Learning-Rate Branching. When a variable is unassigned, its score Q moves
+/// towards its learning rate: the share of the conflicts that happened while
+/// it was assigned in which it took part.
+pub struct LRB {
+    pub score: BTreeMap<Variable, f64>,
+    // conflict count at the time each assigned variable was assigned
+    pub assigned_at: BTreeMap<Variable, u64>,
+    // conflicts each assigned variable took part in since its assignment
+    pub participated: BTreeMap<Variable, u64>,
+    pub variable_unassigned: BTreeSet<Variable>,
+    // used to pick a polarity: the more frequent literal of the input wins
+    pub literal_frequency: BTreeMap<Literal, u64>,
+    pub conflicts: u64,
+    pub alpha: f64,
+    pub options: LRBOptions,
+    use_bcp: bool,
+}
+
+impl Debug for LRB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        _ = writeln!(f, "LRB: print (alpha {}, conflicts {})", self.alpha, self.conflicts);
+        for (v, q) in self.score.iter() {
+            _ = write!(f, "{v:?} {q} ");
+        }
+        writeln!(f)
+    }
+}
+
+impl Heuristics for LRB {
+    type Options = LRBOptions;
+
+    fn with_options(options: LRBOptions) -> Self {
+        LRB {
+            score: BTreeMap::new(),
+            assigned_at: BTreeMap::new(),
+            participated: BTreeMap::new(),
+            variable_unassigned: BTreeSet::new(),
+            literal_frequency: BTreeMap::new(),
+            conflicts: 0,
+            alpha: options.alpha_start,
+            options,
+            use_bcp: false,
+        }
+    }
+
+    fn add_parsed_clause(&mut self, c: &Clause) {
+        for l in c.list_of_literals.iter() {
+            self.score.entry(l.variable).or_insert(0.0);
+            self.variable_unassigned.insert(l.variable);
+            *self.literal_frequency.entry(*l).or_insert(0) += 1;
+        }
+        trace!(target: "lrb", "LRB: add clause {c:?}");
+    }
+
+    fn add_conflict_clause(&mut self, c: &Clause) {
+        self.conflicts += 1;
+        self.alpha = (self.alpha - self.options.alpha_decay).max(self.options.alpha_min);
+        for l in c.list_of_literals.iter() {
+            if let Some(n) = self.participated.get_mut(&l.variable) {
+                *n += 1;
+            }
+        }
+        trace!(target: "lrb", "LRB: conflict #{} on clause {c:?}", self.conflicts);
+    }
+
+    fn decide(&mut self) -> Option<Literal> {
+        let (v, q) = self
+            .variable_unassigned
+            .iter()
+            .map(|v| (*v, self.score[v]))
+            .fold(None, |best: Option<(Variable, f64)>, (v, q)| match best {
+                Some((_, bq)) if bq >= q => best,
+                _ => Some((v, q)),
+            })?;
+        let on = Literal { variable: v, polarity: Polarity::On };
+        let count = |l: &Literal| self.literal_frequency.get(l).copied().unwrap_or(0);
+        let l = if count(&on) >= count(&!on) { on } else { !on };
+        trace!(target: "lrb", "LRB: decide {l:?} with score {q}");
+        Some(l)
+    }
+
+    fn assign_variable(&mut self, var: Variable) {
+        assert!(self.variable_unassigned.remove(&var));
+        self.assigned_at.insert(var, self.conflicts);
+        self.participated.insert(var, 0);
+        trace!(target: "lrb", "LRB: assign variable {var:?}");
+    }
+
+    fn unassign_variable(&mut self, var: Variable) {
+        assert!(self.variable_unassigned.insert(var));
+        let interval = self.conflicts - self.assigned_at.remove(&var).unwrap_or(self.conflicts);
+        let participated = self.participated.remove(&var).unwrap_or(0);
+        if interval > 0 {
+            let reward = participated as f64 / interval as f64;
+            let q = self.score.get_mut(&var).unwrap();
+            *q = (1.0 - self.alpha) * *q + self.alpha * reward;
+        }
+        trace!(target: "lrb", "LRB: unassign variable {var:?}");
+    }
+
+    fn satisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn unsatisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn set_use_bcp(&mut self, _use_bcp: bool) {
+        self.use_bcp = _use_bcp;
+    }
+
+    fn use_bcp(&self) -> bool {
+        self.use_bcp
+    }
+}
@@ -0,0 +1,191 @@
+use crate::heuristics::heuristics::*;
+use crate::sat_solver::*;
+use core::fmt;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MOMSOptions {
+    // weight of the sum of both occurrence counts relative to their product,
+    // as in Freeman's (f(x) + f(x')) * 2^k + f(x) * f(x')
+    pub k: u32,
+}
+
+impl Default for MOMSOptions {
+    fn default() -> Self {
+        MOMSOptions { k: 4 }
+    }
+}
+
+/// Maximum Occurrences in clauses of Minimum Size.
+pub struct MOMS {
+    // occurrences in the shortest input clauses, used without a Problem
+    pub literal_frequency: BTreeMap<Literal, u64>,
+    pub shortest_clause: usize,
+    pub variables: BTreeSet<Variable>,
+    pub variable_assigned: BTreeSet<Variable>,
+    pub options: MOMSOptions,
+    use_bcp: bool,
+}
+
+impl Debug for MOMS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        _ = writeln!(f, "MOMS: print (clause size {})", self.shortest_clause);
+        for (l, n) in self.literal_frequency.iter() {
+            _ = write!(f, "{l:?} {n} ");
+        }
+        writeln!(f)
+    }
+}
+
+const MAX_K: u32 = 32;
+
+fn pick(frequency: &BTreeMap<Literal, u64>, k: u32) -> Option<Literal> {
+    let mut best: Option<(u64, Literal)> = None;
+    for (l, n) in frequency.iter() {
+        let n_other = frequency.get(&!*l).copied().unwrap_or(0);
+        if *n < n_other {
+            continue;
+        }
+        let score = (n + n_other) * 2u64.pow(k) + n * n_other;
+        if best.is_none_or(|(s, _)| score > s) {
+            best = Some((score, *l));
+        }
+    }
+    best.map(|(_, l)| l)
+}
+
+impl Heuristics for MOMS {
+    type Options = MOMSOptions;
+
+    // 2^k multiplies occurrence counts in a u64
+    fn check_options(options: &MOMSOptions) -> Result<(), String> {
+        if options.k > MAX_K {
+            return Err(format!("k must be at most {}, got {}", MAX_K, options.k));
+        }
+        return Ok(());
+    }
+
+    fn with_options(options: MOMSOptions) -> Self {
+        MOMS {
+            literal_frequency: BTreeMap::new(),
+            shortest_clause: usize::MAX,
+            variables: BTreeSet::new(),
+            variable_assigned: BTreeSet::new(),
+            options,
+            use_bcp: false,
+        }
+    }
+
+    fn add_parsed_clause(&mut self, c: &Clause) {
+        self.variables.extend(c.list_of_literals.iter().map(|l| l.variable));
+        let size = c.list_of_literals.len();
+        if size < self.shortest_clause {
+            self.shortest_clause = size;
+            self.literal_frequency.clear();
+        }
+        if size == self.shortest_clause {
+            for l in c.list_of_literals.iter() {
+                *self.literal_frequency.entry(*l).or_insert(0) += 1;
+            }
+        }
+        trace!(target: "moms", "MOMS: add clause {c:?}");
+    }
+
+    fn add_conflict_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn decide(&mut self) -> Option<Literal> {
+        let unassigned: BTreeMap<Literal, u64> = self
+            .literal_frequency
+            .iter()
+            .filter(|(l, _)| !self.variable_assigned.contains(&l.variable))
+            .map(|(l, n)| (*l, *n))
+            .collect();
+        // variables outside the shortest clauses still need a value
+        let l = pick(&unassigned, self.options.k).or_else(|| {
+            self.variables
+                .difference(&self.variable_assigned)
+                .next()
+                .map(|v| Literal {
+                    variable: *v,
+                    polarity: Polarity::On,
+                })
+        })?;
+        trace!(target: "moms", "MOMS: decide {l:?}");
+        Some(l)
+    }
+
+    // occurrences among the unresolved clauses with the fewest unassigned
+    // literals
+    fn decide_with_problem(&mut self, p: &mut Problem) -> Option<Literal> {
+        let mut shortest = usize::MAX;
+        let mut frequency = BTreeMap::<Literal, u64>::new();
+        for rc in p.list_of_clauses.iter() {
+            let c = rc.borrow();
+            let states: Vec<LiteralState> =
+                c.list_of_literal_infos.iter().map(|li| li.borrow().status).collect();
+            if states.contains(&LiteralState::Sat) {
+                continue;
+            }
+            let free = states.iter().filter(|s| **s == LiteralState::Unknown).count();
+            if free == 0 || free > shortest {
+                continue;
+            }
+            if free < shortest {
+                shortest = free;
+                frequency.clear();
+            }
+            for (l, s) in c.list_of_literals.iter().zip(states.iter()) {
+                if *s == LiteralState::Unknown {
+                    *frequency.entry(*l).or_insert(0) += 1;
+                }
+            }
+        }
+        if frequency.is_empty() {
+            // every clause is satisfied: pick any unassigned variable
+            return p
+                .list_of_variables
+                .iter()
+                .filter(|(_, vs)| **vs == VariableState::Unassigned)
+                .map(|(v, _)| Literal {
+                    variable: *v,
+                    polarity: Polarity::On,
+                })
+                .min();
+        }
+        let l = pick(&frequency, self.options.k)?;
+        trace!(target: "moms", "MOMS: decide {l:?}");
+        Some(l)
+    }
+
+    fn assign_variable(&mut self, var: Variable) {
+        self.variable_assigned.insert(var);
+        trace!(target: "moms", "MOMS: assign variable {var:?}");
+    }
+
+    fn unassign_variable(&mut self, var: Variable) {
+        self.variable_assigned.remove(&var);
+        trace!(target: "moms", "MOMS: unassign variable {var:?}");
+    }
+
+    fn satisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn unsatisfy_clause(&mut self, _c: &Clause) {
+
+    }
+
+    fn set_use_bcp(&mut self, _use_bcp: bool) {
+        self.use_bcp = _use_bcp;
+    }
+
+    fn use_bcp(&self) -> bool {
+        self.use_bcp
+    }
+}
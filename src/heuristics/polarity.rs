@@ -0,0 +1,112 @@
+use crate::heuristics::heuristics::*;
+use crate::rng::Rng;
+use crate::sat_solver::*;
+use core::fmt;
+use log::trace;
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolarityMode {
+    // keep whatever polarity the wrapped heuristics recommends
+    Heuristic,
+    // flip the polarity the wrapped heuristics recommends
+    Inverted,
+    // always assign On
+    Positive,
+    // always assign Off
+    Negative,
+    // a coin flip per decision, see Heuristics::set_seed
+    Random,
+}
+
+/// Wraps another `Heuristics` and only overrides the polarity of its
+/// decisions; the choice of variable is left untouched.
+pub struct PolarityOverride<H: Heuristics> {
+    pub inner: H,
+    pub mode: PolarityMode,
+    rng: Rng,
+}
+
+impl<H: Heuristics> PolarityOverride<H> {
+    pub fn wrap(inner: H, mode: PolarityMode) -> Self {
+        PolarityOverride { inner, mode, rng: Rng::new(0) }
+    }
+
+    fn apply_mode(&mut self, l: Literal) -> Literal {
+        let polarity = match self.mode {
+            PolarityMode::Heuristic => l.polarity,
+            PolarityMode::Inverted => !l.polarity,
+            PolarityMode::Positive => Polarity::On,
+            PolarityMode::Negative => Polarity::Off,
+            PolarityMode::Random if self.rng.below(2) == 0 => Polarity::Off,
+            PolarityMode::Random => Polarity::On,
+        };
+        trace!(target: "heuristics", "PolarityOverride: decide {:?} as {:?}", l.variable, polarity);
+        Literal {
+            variable: l.variable,
+            polarity,
+        }
+    }
+}
+
+impl<H: Heuristics> Debug for PolarityOverride<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "PolarityOverride: {:?}", self.mode)
+    }
+}
+
+impl<H: Heuristics> Heuristics for PolarityOverride<H> {
+    type Options = H::Options;
+
+    fn with_options(options: H::Options) -> Self {
+        Self::wrap(H::with_options(options), PolarityMode::Heuristic)
+    }
+
+    fn add_parsed_clause(&mut self, c: &Clause) {
+        self.inner.add_parsed_clause(c);
+    }
+
+    fn add_conflict_clause(&mut self, c: &Clause) {
+        self.inner.add_conflict_clause(c);
+    }
+
+    fn decide(&mut self) -> Option<Literal> {
+        let l = self.inner.decide()?;
+        Some(self.apply_mode(l))
+    }
+
+    fn decide_with_problem(&mut self, p: &mut Problem) -> Option<Literal> {
+        let l = self.inner.decide_with_problem(p)?;
+        Some(self.apply_mode(l))
+    }
+
+    fn assign_variable(&mut self, var: Variable) {
+        self.inner.assign_variable(var);
+    }
+
+    fn unassign_variable(&mut self, var: Variable) {
+        self.inner.unassign_variable(var);
+    }
+
+    fn satisfy_clause(&mut self, c: &Clause) {
+        self.inner.satisfy_clause(c);
+    }
+
+    fn unsatisfy_clause(&mut self, c: &Clause) {
+        self.inner.unsatisfy_clause(c);
+    }
+
+    fn set_use_bcp(&mut self, _use_bcp: bool) {
+        self.inner.set_use_bcp(_use_bcp);
+    }
+
+    fn use_bcp(&self) -> bool {
+        self.inner.use_bcp()
+    }
+
+    // the wrapped heuristics gets a seed of its own from our stream
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.inner.set_seed(self.rng.next_u64());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::{ascending::Ascending, dlis::DLIS, lookahead::Lookahead, vsids::VSIDS};
+use crate::heuristics::{chb::CHB, jeroslow_wang::JeroslowWang, lrb::LRB, moms::MOMS};
+use serde::Deserialize;
+use std::fs;
+
+// The Heuristics trait is generic (and thus not object safe), so the registry
+// cannot hand out a Box<dyn Heuristics>. Instead, callers pass a visitor that
+// is instantiated with the concrete heuristics type picked by name.
+
+pub struct HeuristicsEntry {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub const HEURISTICS: [HeuristicsEntry; 8] = [
+    HeuristicsEntry { name: "ascending", description: "lowest unassigned variable first" },
+    HeuristicsEntry { name: "dlis", description: "dynamic largest individual sum" },
+    HeuristicsEntry { name: "vsids", description: "variable state independent decaying sum" },
+    HeuristicsEntry { name: "lookahead", description: "march-style lookahead on preselected variables" },
+    HeuristicsEntry { name: "jw", description: "Jeroslow-Wang" },
+    HeuristicsEntry { name: "moms", description: "maximum occurrences in clauses of minimum size" },
+    HeuristicsEntry { name: "chb", description: "conflict history-based branching" },
+    HeuristicsEntry { name: "lrb", description: "learning-rate branching" },
+];
+
+pub trait HeuristicsVisitor {
+    type Output;
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output;
+}
+
+/// Per-heuristics options, one TOML table per heuristics name, e.g.
+///
+/// ```toml
+/// [vsids]
+/// decay = 0.95
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicsConfig {
+    table: toml::Table,
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if HEURISTICS.iter().any(|e| e.name == name) {
+        return Ok(());
+    }
+    return Err(format!("unknown heuristics '{}', see --list-heuristics", name));
+}
+
+impl HeuristicsConfig {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let table: toml::Table = contents.parse().map_err(|e| format!("{}: {}", path, e))?;
+        for (name, value) in table.iter() {
+            check_name(name)?;
+            if !value.is_table() {
+                return Err(format!("{}: '{}' must be a table of options", path, name));
+            }
+        }
+        return Ok(HeuristicsConfig { table });
+    }
+
+    /// Sets one option given as `name.key=value`, overriding the config file.
+    /// The value is read as a TOML value, falling back to a plain string.
+    pub fn set_option(&mut self, option: &str) -> Result<(), String> {
+        let bad = || format!("malformed heuristics option '{}', expected name.key=value", option);
+        let (path, value) = option.split_once('=').ok_or_else(bad)?;
+        let (name, key) = path.trim().split_once('.').ok_or_else(bad)?;
+        let value = value.trim();
+        let value = match format!("v = {}", value).parse::<toml::Table>() {
+            Ok(mut t) => t.remove("v").unwrap(),
+            Err(_) => toml::Value::String(String::from(value)),
+        };
+        return self.set(name, key.trim(), value);
+    }
+
+    pub fn set(&mut self, name: &str, key: &str, value: toml::Value) -> Result<(), String> {
+        check_name(name)?;
+        let section = self
+            .table
+            .entry(name)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        section.as_table_mut().unwrap().insert(String::from(key), value);
+        return Ok(());
+    }
+
+    /// The config in the format read by `from_file`.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(&self.table).unwrap()
+    }
+
+    /// Typed options for the heuristics `name`; missing keys keep their
+    /// defaults, unknown keys and values out of range are an error.
+    pub fn options<H: Heuristics>(&self, name: &str) -> Result<H::Options, String> {
+        let section = match self.table.get(name) {
+            Some(section) => section.clone(),
+            None => return Ok(H::Options::default()),
+        };
+        let options = H::Options::deserialize(section)
+            .map_err(|e| format!("options of {}: {}", name, e.to_string().trim_end()))?;
+        H::check_options(&options).map_err(|e| format!("options of {}: {}", name, e))?;
+        return Ok(options);
+    }
+}
+
+/// Calls `visitor` with the heuristics type registered as `name` and its
+/// options taken from `config`.
+pub fn dispatch<V: HeuristicsVisitor>(
+    name: &str,
+    config: &HeuristicsConfig,
+    visitor: V,
+) -> Result<V::Output, String> {
+    let output = match name {
+        "ascending" => visitor.visit::<Ascending>(config.options::<Ascending>(name)?),
+        "dlis"      => visitor.visit::<DLIS>(config.options::<DLIS>(name)?),
+        "vsids"     => visitor.visit::<VSIDS>(config.options::<VSIDS>(name)?),
+        "lookahead" => visitor.visit::<Lookahead>(config.options::<Lookahead>(name)?),
+        "jw"        => visitor.visit::<JeroslowWang>(config.options::<JeroslowWang>(name)?),
+        "moms"      => visitor.visit::<MOMS>(config.options::<MOMS>(name)?),
+        "chb"       => visitor.visit::<CHB>(config.options::<CHB>(name)?),
+        "lrb"       => visitor.visit::<LRB>(config.options::<LRB>(name)?),
+        _           => return Err(check_name(name).unwrap_err()),
+    };
+    return Ok(output);
+}
+
+struct Describe;
+
+impl HeuristicsVisitor for Describe {
+    type Output = String;
+    fn visit<H: Heuristics>(self, options: H::Options) -> String {
+        format!("{:?}", options)
+    }
+}
+
+/// Checks the options of every heuristics, not only the one in use, so that
+/// a typo in a config file is reported right away.
+pub fn validate(config: &HeuristicsConfig) -> Result<(), String> {
+    for entry in HEURISTICS.iter() {
+        dispatch(entry.name, config, Describe)?;
+    }
+    return Ok(());
+}
+
+/// One line per registered heuristics with its effective options.
+pub fn list(config: &HeuristicsConfig) -> Result<String, String> {
+    let mut out = String::new();
+    for entry in HEURISTICS.iter() {
+        let options = dispatch(entry.name, config, Describe)?;
+        out += &format!("{:<10} {:<48} {}\n", entry.name, entry.description, options);
+    }
+    return Ok(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_out_of_range_are_errors() {
+        for option in ["vsids.decay=2", "vsids.decay=0", "vsids.decay=nan", "moms.k=64"] {
+            let mut config = HeuristicsConfig::default();
+            config.set_option(option).unwrap();
+            assert!(validate(&config).is_err(), "{}", option);
+        }
+        let mut config = HeuristicsConfig::default();
+        config.set_option("vsids.decay=0.95").unwrap();
+        assert!(validate(&config).is_ok());
+    }
+}
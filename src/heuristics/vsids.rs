@@ -2,14 +2,29 @@ use crate::heuristics::heuristics::*;
 use crate::sat_solver::*;
 use core::fmt;
 use log::trace;
+use serde::Deserialize;
 use std::collections::{HashMap, HashSet, BTreeSet};
 use std::fmt::Debug;
 
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VSIDSOptions {
+    // Without a decay the bump grows by one per decision. With a decay d in
+    // (0, 1) it grows by a factor 1/d instead, which is the same as
+    // multiplying every score by d, so older conflicts fade exponentially.
+    pub decay: Option<f64>,
+}
+
+// once the bump exceeds this, every score is shifted right by RESCALE_SHIFT
+const RESCALE_LIMIT: u64 = 1 << 40;
+const RESCALE_SHIFT: u32 = 20;
+
 pub struct VSIDS {
     pub literal_counter: HashMap<Literal, u64>,
     pub counter_literal_assigned: HashSet<(u64, Literal)>,
     pub counter_literal_unassigned: BTreeSet<(u64, Literal)>,
     pub iteration: u64,
+    pub options: VSIDSOptions,
     use_bcp: bool,
 }
 
@@ -24,14 +39,49 @@ impl Debug for VSIDS {
     }
 }
 
+impl VSIDS {
+    // scale the bump and every score down by the same factor, which keeps the
+    // ranking but makes room for the exponentially growing bump
+    fn rescale(&mut self) {
+        self.iteration >>= RESCALE_SHIFT;
+        for counter in self.literal_counter.values_mut() {
+            *counter >>= RESCALE_SHIFT;
+        }
+        self.counter_literal_assigned = self
+            .counter_literal_assigned
+            .iter()
+            .map(|(c, l)| (c >> RESCALE_SHIFT, *l))
+            .collect();
+        self.counter_literal_unassigned = self
+            .counter_literal_unassigned
+            .iter()
+            .map(|(c, l)| (c >> RESCALE_SHIFT, *l))
+            .collect();
+        trace!(target: "vsids", "VSIDS: rescale scores");
+    }
+}
+
 impl Heuristics for VSIDS {
+    type Options = VSIDSOptions;
+
+    fn check_options(options: &VSIDSOptions) -> Result<(), String> {
+        match options.decay {
+            Some(decay) if !(decay > 0.0 && decay <= 1.0) => Err(format!("decay must be in (0, 1], got {}", decay)),
+            _ => Ok(()),
+        }
+    }
+
     // creates a new heuristics struct
-    fn new() -> Self {
+    fn with_options(options: VSIDSOptions) -> Self {
+        if let Some(decay) = options.decay {
+            assert!(decay > 0.0 && decay <= 1.0, "vsids.decay must be in (0, 1]");
+        }
         VSIDS {
             literal_counter: HashMap::<Literal, u64>::new(),
             counter_literal_assigned: HashSet::<(u64, Literal)>::new(),
             counter_literal_unassigned: BTreeSet::<(u64, Literal)>::new(),
             iteration: 1,
+            options,
             use_bcp: false,
         }
     }
@@ -40,11 +90,16 @@ impl Heuristics for VSIDS {
     // literal assignment status will remain unchanged
     fn add_parsed_clause(&mut self, c: &Clause) {
         for l in c.list_of_literals.iter() {
-            let counter_old = self.literal_counter.entry(*l).or_insert(0).clone();
+            let counter_old = *self.literal_counter.entry(*l).or_insert(0);
             let counter_new = counter_old + self.iteration;
             *self.literal_counter.get_mut(l).unwrap() = counter_new;
-            self.counter_literal_unassigned.remove(&(counter_old, *l));
-            assert!(self.counter_literal_unassigned.insert((counter_new, *l)));
+            // literals of a conflict clause are typically assigned
+            if self.counter_literal_assigned.remove(&(counter_old, *l)) {
+                assert!(self.counter_literal_assigned.insert((counter_new, *l)));
+            } else {
+                self.counter_literal_unassigned.remove(&(counter_old, *l));
+                assert!(self.counter_literal_unassigned.insert((counter_new, *l)));
+            }
         }
 
         let lits = &c.list_of_literals;
@@ -57,7 +112,13 @@ impl Heuristics for VSIDS {
 
     // recommend highest ranked literal but with inverted polarity
     fn decide(&mut self) -> Option<Literal> {
-        self.iteration += 1;
+        self.iteration = match self.options.decay {
+            Some(decay) => (self.iteration + 1).max((self.iteration as f64 / decay) as u64),
+            None => self.iteration + 1,
+        };
+        if self.iteration > RESCALE_LIMIT {
+            self.rescale();
+        }
         if let Some(score_literal) = self.counter_literal_unassigned.last() {
             let score_literal = score_literal.1;
             trace!(target: "vsids", "VSIDS: decide {score_literal:?}");
@@ -0,0 +1,170 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::HeuristicsVisitor;
+use crate::parser::Incremental;
+use crate::profiler::SolverProfiler;
+use crate::progress::Progress;
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use crate::verify::{self, Cnf};
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+// Incremental CNF: clauses interleaved with `a <lits> 0` queries, each of which
+// asks whether the clauses read so far are satisfiable under the assumptions.
+//
+// A single incremental solver answers all queries, so the state of the
+// heuristics (e.g. VSIDS scores) carries over from one query to the next, as
+// would learned clauses if `dpll` learned any. Assumptions only hold for their
+// own query.
+
+pub struct SolveQueries<'a> {
+    pub lines: &'a [Incremental],
+    pub use_bcp: bool,
+    pub seed: u64,
+    pub progress: Option<Progress>,
+}
+
+impl HeuristicsVisitor for SolveQueries<'_> {
+    // whether each query is satisfiable
+    type Output = Result<(Vec<bool>, SolverProfiler), String>;
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+        let mut h = H::with_options(options);
+        h.set_use_bcp(self.use_bcp);
+        h.set_seed(self.seed);
+        // the clauses register their variables, and each solve those of its
+        // assumptions that are in no clause yet
+        let mut solver = Solver::new(Problem::new(), h);
+        if let Some(progress) = self.progress {
+            solver.profiler.set_progress(progress);
+        }
+        // the clauses so far, for checking models
+        let mut cnf = Cnf {
+            declared_variables: None,
+            clauses: Vec::new(),
+            xors: Vec::new(),
+            projection: None,
+            prefix: Vec::new(),
+        };
+        let interrupt = AtomicBool::new(false);
+        let mut answers = Vec::new();
+        // after the empty clause, every query is UNSAT
+        let mut refuted = false;
+        for line in self.lines.iter() {
+            match line {
+                Incremental::Clause(literals) => {
+                    cnf.clauses.push(literals.iter().map(|l| l.to_dimacs()).collect());
+                    if literals.is_empty() {
+                        refuted = true;
+                    } else {
+                        solver.add_clause(literals);
+                    }
+                }
+                Incremental::Query(assumptions) => {
+                    let start = Instant::now();
+                    let solution = if refuted {
+                        None
+                    } else {
+                        solver.solve(assumptions, &interrupt)
+                    };
+                    if let Some(s) = &solution {
+                        check(&cnf, assumptions, &crate::enumerate::model(s))
+                            .map_err(|e| format!("query {}: {}", answers.len() + 1, e))?;
+                    }
+                    answers.push(solution.is_some());
+                    println!(
+                        "QUERY {}: {} ({:.3}s)",
+                        answers.len(),
+                        if solution.is_some() { "SAT" } else { "UNSAT" },
+                        start.elapsed().as_secs_f64()
+                    );
+                }
+            }
+        }
+        return Ok((answers, solver.profiler));
+    }
+}
+
+/// Checks a model against the clauses so far and the assumptions of its
+/// query.
+pub fn check(cnf: &Cnf, assumptions: &[Literal], model: &verify::Model) -> Result<(), String> {
+    let report = verify::check(cnf, model)?;
+    if !report.ok() {
+        eprint!("{}", verify::Details(cnf, &report, model));
+        return Err(String::from("the solution does not satisfy the clauses"));
+    }
+    for l in assumptions.iter() {
+        if model.get(&l.variable.index).copied() != Some(l.polarity == Polarity::On) {
+            return Err(format!("the solution does not satisfy the assumption {}", l.to_dimacs()));
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::registry::{self, HeuristicsConfig, HEURISTICS};
+    use crate::parser;
+    use crate::rng::Rng;
+    use crate::testing::temp_file;
+
+    // the answers to the queries of `text` under every heuristics, which
+    // must agree
+    fn answers(text: &str) -> Vec<bool> {
+        let path = temp_file("icnf.icnf", text);
+        let lines = parser::parse_icnf(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut all = Vec::new();
+        for entry in HEURISTICS.iter() {
+            let visitor = SolveQueries { lines: &lines, use_bcp: true, seed: 0, progress: None };
+            let (answers, _) = registry::dispatch(entry.name, &HeuristicsConfig::default(), visitor).unwrap().unwrap();
+            all.push(answers);
+        }
+        assert!(all.iter().all(|a| *a == all[0]), "{:?} for\n{}", all, text);
+        return all.swap_remove(0);
+    }
+
+    #[test]
+    fn assumptions_on_variables_in_no_clause() {
+        assert_eq!(answers("p inccnf\n1 0\na 2 0\na -1 2 0\na 2147483647 0\n"), vec![true, false, true]);
+        assert_eq!(answers("p inccnf\na 1 0\n-1 0\na 1 0\n0\na 2 0\n"), vec![true, false, false]);
+    }
+
+    #[test]
+    fn literals_have_a_negation() {
+        let path = temp_file("icnf.icnf", "p inccnf\na -2147483648 0\n");
+        assert!(parser::parse_icnf(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn random_queries_match_brute_force() {
+        let mut rng = Rng::new(0);
+        for _ in 0..30 {
+            let n = 1 + rng.below(5) as u32;
+            let mut text = String::from("p inccnf\n");
+            let mut clauses: Vec<Vec<i32>> = Vec::new();
+            let mut expected = Vec::new();
+            for _ in 0..2 + rng.below(8) {
+                let mut variables: Vec<i32> = (1..=n as i32).collect();
+                rng.shuffle(&mut variables);
+                let size = 1 + rng.below(3.min(n as u64)) as usize;
+                let literals: Vec<i32> =
+                    variables[..size].iter().map(|v| if rng.below(2) == 0 { *v } else { -*v }).collect();
+                let line: Vec<String> = literals.iter().map(|l| l.to_string()).collect();
+                if rng.below(2) == 0 {
+                    text += &format!("{} 0\n", line.join(" "));
+                    clauses.push(literals);
+                    continue;
+                }
+                text += &format!("a {} 0\n", line.join(" "));
+                let holds = |mask: u32, l: &i32| (mask >> (l.unsigned_abs() - 1) & 1 == 1) == (*l > 0);
+                expected.push((0..1u32 << n).any(|mask| {
+                    literals.iter().all(|l| holds(mask, l)) && clauses.iter().all(|c| c.iter().any(|l| holds(mask, l)))
+                }));
+            }
+            assert_eq!(answers(&text), expected, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::aiger::AigerInput;
+use crate::formula::FormulaInput;
+use crate::heuristics::heuristics::Heuristics;
+use crate::pb::PbInput;
+use crate::sat_solver::*;
+use crate::verify::Model;
+
+// Input formats other than DIMACS CNF. They are translated into a Problem
+// whose variables 1 to n are those of the input and the ones after them
+// auxiliary variables of the translation. Models are checked against the input
+// as written and show only its own variables, under their names.
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    /// By the file name: OPB for .opb, formulas for .bool, AIGER for .aag
+    /// and .aig, QDIMACS for .qdimacs, iCNF for .icnf, DIMACS CNF otherwise
+    Auto,
+    Cnf,
+    Opb,
+    /// Propositional formulas over named variables
+    Formula,
+    /// And-Inverter Graphs, ASCII or binary
+    Aiger,
+    /// Quantified Boolean formulas, solved by the 2QBF solver
+    Qdimacs,
+    /// Clauses interleaved with `a <lits> 0` queries, solved incrementally
+    Icnf,
+}
+
+impl InputFormat {
+    /// The format of the file at `path`.
+    pub fn of(self, path: &str) -> InputFormat {
+        match self {
+            InputFormat::Auto if path.ends_with(".opb") => InputFormat::Opb,
+            InputFormat::Auto if path.ends_with(".bool") => InputFormat::Formula,
+            InputFormat::Auto if path.ends_with(".aag") || path.ends_with(".aig") => InputFormat::Aiger,
+            InputFormat::Auto if path.ends_with(".qdimacs") => InputFormat::Qdimacs,
+            InputFormat::Auto if path.ends_with(".icnf") => InputFormat::Icnf,
+            InputFormat::Auto => InputFormat::Cnf,
+            format => format,
+        }
+    }
+}
+
+pub enum Translated {
+    Pb(PbInput),
+    Formula(FormulaInput),
+    Aiger(AigerInput),
+}
+
+impl Translated {
+    pub fn load(&self, heuristics: &mut impl Heuristics) -> Problem {
+        match self {
+            Translated::Pb(pb) => pb.load(heuristics),
+            Translated::Formula(f) => f.load(heuristics),
+            Translated::Aiger(a) => a.load(heuristics),
+        }
+    }
+
+    pub fn check(&self, model: &Model) -> Result<(), String> {
+        match self {
+            Translated::Pb(pb) => crate::pb::check(&pb.opb, model),
+            Translated::Formula(f) => f.check(model),
+            Translated::Aiger(a) => a.check(model),
+        }
+    }
+
+    /// The number of variables of the input.
+    pub fn variables(&self) -> u32 {
+        match self {
+            Translated::Pb(pb) => pb.opb.variables,
+            Translated::Formula(f) => f.names.len() as u32,
+            Translated::Aiger(a) => a.variables(),
+        }
+    }
+
+    /// The name of variable `v` of the input.
+    pub fn name(&self, v: u32) -> String {
+        match self {
+            Translated::Pb(_) => v.to_string(),
+            Translated::Formula(f) => f.names[v as usize - 1].clone(),
+            Translated::Aiger(a) => a.name(v),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::enumerate::Enumerate;
+    use crate::heuristics::registry::{self, HeuristicsConfig};
+
+    /// Checks a translation by brute force: the models the solver enumerates,
+    /// projected on the variables of the input, all satisfy the input, and
+    /// there are as many as there are assignments to those variables that do.
+    /// Returns their number.
+    pub fn check_translation(translated: &Translated, heuristics: &str) -> u64 {
+        let n = translated.variables();
+        let projection: Vec<u32> = (1..=n).collect();
+        let mut models = Vec::new();
+        let visitor = Enumerate {
+            input: &String::new(),
+            translated: Some(translated),
+            use_bcp: true,
+            seed: 0,
+            variables: n,
+            limit: None,
+            projection: Some(&projection),
+            on_model: |m: &Model| models.push(m.clone()),
+        };
+        let (count, _) = registry::dispatch(heuristics, &HeuristicsConfig::default(), visitor).unwrap();
+        for m in models.iter() {
+            translated.check(m).unwrap();
+        }
+        let satisfying = (0..1u64 << n)
+            .filter(|mask| {
+                let model: Model = (1..=n).map(|v| (v, mask >> (v - 1) & 1 == 1)).collect();
+                translated.check(&model).is_ok()
+            })
+            .count() as u64;
+        assert_eq!(count, satisfying);
+        return count;
+    }
+}
@@ -0,0 +1,31 @@
+// The solver as a library: the CNF encodings and model checking used by the
+// command line are usable from other programs, e.g. `encodings` and `verify`.
+pub mod aiger;
+pub mod bench;
+pub mod bmc;
+pub mod bignum;
+pub mod count;
+pub mod cube;
+pub mod encodings;
+pub mod enumerate;
+pub mod formula;
+pub mod generate;
+pub mod heuristics;
+pub mod icnf;
+pub mod input;
+pub mod parser;
+pub mod pb;
+pub mod portfolio;
+pub mod profiler;
+pub mod progress;
+pub mod qbf;
+pub mod rng;
+pub mod runner;
+pub mod sat_solver;
+pub mod serve;
+pub mod trace;
+pub mod tune;
+pub mod verify;
+
+#[cfg(test)]
+mod testing;
@@ -1,25 +1,72 @@
-mod heuristics;
-mod parser;
-mod profiler;
-mod sat_solver;
-use core::panic;
+use ::sat_solver::{
+    aiger, bench, bmc, count, cube, encodings, enumerate, formula, generate, heuristics, icnf, input,
+    parser, pb, portfolio, profiler, progress, qbf, sat_solver, serve, trace, tune, verify,
+};
 use std::collections::BTreeSet;
+use std::process;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::{trace,info};
 use sat_solver::*;
-use crate::heuristics::{ascending::Ascending, heuristics::Heuristics, dlis::DLIS, vsids::VSIDS};
-use crate::profiler::SolverProfiler;
+use crate::heuristics::{ascending::Ascending, heuristics::Heuristics};
+use crate::heuristics::registry::{self, HeuristicsConfig, HeuristicsVisitor};
+use crate::input::{InputFormat, Translated};
+use crate::profiler::{Phase, SolverProfiler};
+use crate::progress::Progress;
+use crate::trace::TraceWriter;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(index=1)]
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(index=1, required_unless_present = "list_heuristics")]
+    input: Option<String>,
+
+    /// Format of the input file
+    #[arg(long, value_enum, default_value_t = input::InputFormat::Auto)]
+    format: input::InputFormat,
+
+    /// OPB input: how to handle constraints with equal coefficients
+    #[arg(long, value_enum, default_value_t = pb::CardinalityMode::Native)]
+    cardinality: pb::CardinalityMode,
+
+    /// OPB input: the encoding of the other constraints
+    #[arg(long, value_enum, default_value_t = encodings::PbEncoding::Bdd)]
+    pb_encoding: encodings::PbEncoding,
+
+    /// Formula input: how gates are turned into clauses
+    #[arg(long, value_enum, default_value_t = formula::FormulaEncoding::PlaistedGreenbaum)]
+    formula_encoding: formula::FormulaEncoding,
+
+    /// AIGER input: the bad-state property to check, or the output if there
+    /// are none
+    #[arg(long, default_value_t = 0)]
+    property: usize,
+
+    /// AIGER input: write the result in the AIGER witness format to FILE
+    #[arg(long, value_name = "FILE")]
+    witness: Option<String>,
 
     #[arg(long, default_value_t = String::from("vsids"))]
     heuristics: String,
 
+    /// List the available heuristics with their options and exit
+    #[arg(long)]
+    list_heuristics: bool,
+
+    /// Set a heuristics option, e.g. vsids.decay=0.95 (repeatable)
+    #[arg(long, value_name = "NAME.KEY=VALUE")]
+    heuristic_opt: Vec<String>,
+
+    /// TOML file with one table of options per heuristics
+    #[arg(long, value_name = "FILE")]
+    heuristics_config: Option<String>,
+
     #[arg(long)]
     no_bcp: bool,
 
@@ -28,74 +75,400 @@ struct Args {
 
     #[arg(long)]
     satisfiable: bool,
+
+    /// Seed for every random choice of the solver; runs with the same seed
+    /// and input make the same decisions
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Race N differently configured solvers in parallel threads
+    #[arg(long, value_name = "N")]
+    portfolio: Option<usize>,
+
+    /// Split the problem into cubes, write them to FILE (iCNF) and exit
+    #[arg(long, value_name = "FILE")]
+    cube: Option<String>,
+
+    /// Maximum number of literals per cube
+    #[arg(long, default_value_t = 8)]
+    cube_depth: usize,
+
+    /// Stop splitting once this many cubes exist
+    #[arg(long)]
+    cube_count: Option<usize>,
+
+    /// Splitting variable score
+    #[arg(long, value_enum, default_value_t = cube::CubeScore::March)]
+    cube_score: cube::CubeScore,
+
+    /// Solve the input under each cube of FILE (iCNF)
+    #[arg(long, value_name = "FILE")]
+    conquer: Option<String>,
+
+    /// With --conquer, write one DIMACS job per cube into DIR instead of solving
+    #[arg(long, value_name = "DIR")]
+    conquer_jobs: Option<String>,
+
+    /// Worker threads for --conquer
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+
+    /// Print a progress row to stderr every N conflicts
+    #[arg(long, value_name = "N")]
+    progress_conflicts: Option<u64>,
+
+    /// Print a progress row to stderr every SECS seconds
+    #[arg(long, value_name = "SECS")]
+    progress_interval: Option<f64>,
+
+    /// Comma-separated progress columns: time, conflicts, decisions, props,
+    /// props/s, restarts, learned, trail, remaining, maxlevel
+    #[arg(long, value_name = "LIST", default_value_t = String::from(progress::DEFAULT_COLUMNS))]
+    progress_columns: String,
+
+    /// Write solver statistics as JSON to FILE ("-" for stdout)
+    #[arg(long, value_name = "FILE")]
+    stats_json: Option<String>,
+
+    /// Record every decision, implication, conflict and backtrack to FILE in
+    /// the binary trace format, for the replay subcommand
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["portfolio", "conquer"])]
+    trace: Option<String>,
+
+    /// List all models, or the first N (--enumerate=N); with `c ind` /
+    /// `c p show` lines, only the distinct assignments of those variables
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "0",
+          conflicts_with_all = ["portfolio", "conquer", "cube", "trace"])]
+    enumerate: Option<u64>,
 }
 
-fn test(input : &String, mut h: impl Heuristics, use_bcp: bool) -> (Problem, Option<SolutionStack>) {
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search the options of a heuristics over a directory of instances
+    Tune(tune::TuneArgs),
+    /// Run configurations on labelled instances and report PAR-2 and solved counts
+    Bench(bench::BenchArgs),
+    /// Re-apply a recorded trace and dump the solver state at a given event
+    Replay(trace::ReplayArgs),
+    /// Check a model (v lines) against a CNF file
+    VerifyModel(verify::VerifyModelArgs),
+    /// Write random and combinatorial benchmark instances in DIMACS
+    Generate(generate::GenerateArgs),
+    /// Count the models of a CNF file (#SAT), exactly or approximately
+    Count(count::CountArgs),
+    /// Bounded model checking of an AIGER circuit
+    Bmc(bmc::BmcArgs),
+    /// Drive an incremental solver with a line protocol on stdin/stdout
+    Serve(serve::ServeArgs),
+}
+
+fn test(input : &String, translated: Option<&Translated>, mut h: impl Heuristics, use_bcp: bool, seed: u64, progress: Option<Progress>, trace: Option<TraceWriter>) -> (Option<SolutionStack>, SolverProfiler) {
     let mut prof = SolverProfiler::new();
+    if let Some(progress) = progress {
+        prof.set_progress(progress);
+    }
+    if let Some(trace) = trace {
+        prof.set_trace(trace);
+    }
     h.set_use_bcp(use_bcp);
-    let mut problem = parser::parse(input, &mut h);
+    h.set_seed(seed);
+    prof.begin_phase(Phase::Parse);
+    let mut problem = match translated {
+        Some(t) => t.load(&mut h),
+        None => parser::parse(input, &mut h),
+    };
+    prof.end_phase(Phase::Parse);
     trace!(target: "solver", "problem is: {:#?}", problem);
     prof.reset_start_time();
-    let solution = sat_solver::dpll::dpll(&mut problem, &mut h, &mut prof);
+    let solution = sat_solver::dpll::dpll(&mut problem, &mut h, &mut prof, &AtomicBool::new(false));
     prof.calc_duration_till_now();
     info!(target: "solver", "solution is {:?}", solution);
     info!(target: "profiler", "Profiling results: {}", prof);
-    (problem, solution)
+    (solution, prof)
+}
+
+struct Run<'a> {
+    input: &'a String,
+    translated: Option<&'a Translated>,
+    use_bcp: bool,
+    seed: u64,
+    progress: Option<Progress>,
+    trace: Option<TraceWriter>,
 }
 
-fn verify(p : &Problem, s: &SolutionStack) -> bool {
-    let mut clauses_unsatisfied = BTreeSet::<u32>::new();
-    for c in p.list_of_clauses.iter() {
-        clauses_unsatisfied.insert((**c).borrow().id);
+impl HeuristicsVisitor for Run<'_> {
+    type Output = (Option<SolutionStack>, SolverProfiler);
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+        test(self.input, self.translated, H::with_options(options), self.use_bcp, self.seed, self.progress, self.trace)
     }
-    for step in s.stack.iter() {
-        let literal = Literal{
-            variable: step.assignment.variable,
-            polarity: step.assignment.polarity,
+}
+
+fn heuristics_config(args: &Args) -> Result<HeuristicsConfig, String> {
+    let mut config = match &args.heuristics_config {
+        Some(path) => HeuristicsConfig::from_file(path)?,
+        None => HeuristicsConfig::default(),
+    };
+    // command line options override the config file
+    for option in args.heuristic_opt.iter() {
+        config.set_option(option)?;
+    }
+    registry::validate(&config)?;
+    return Ok(config);
+}
+
+// progress reporting is on if either interval is given
+fn progress(args: &Args) -> Result<Option<Progress>, String> {
+    if args.progress_conflicts.is_none() && args.progress_interval.is_none() {
+        return Ok(None);
+    }
+    let columns = progress::parse_columns(&args.progress_columns)?;
+    let every = match args.progress_interval {
+        Some(secs) if !(secs.is_finite() && secs > 0.0) => {
+            return Err(format!("--progress-interval must be a positive number of seconds, got {}", secs));
+        }
+        secs => secs.map(Duration::from_secs_f64),
+    };
+    return Ok(Some(Progress::new(args.progress_conflicts, every, columns)));
+}
+
+fn exit_with_error<T>(message: String) -> T {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+// checks a model against the input formula with the independent checker
+fn verify(cnf: &verify::Cnf, model: &verify::Model) -> Result<(), String> {
+    let report = verify::check(cnf, model)?;
+    if !report.ok() {
+        eprint!("{}", verify::Details(cnf, &report, model));
+        return Err(String::from("the solution does not satisfy the input"));
+    }
+    return Ok(());
+}
+
+// prints the ASSIGNMENT line of a model: the variables of a translated input
+// under their names, or those of the projection, if any
+fn print_assignment(model: &verify::Model, translated: Option<&Translated>, projection: Option<&BTreeSet<u32>>) {
+    print!("ASSIGNMENT:");
+    for (var, value) in model.iter() {
+        let name = match translated {
+            Some(t) => (*var <= t.variables()).then(|| t.name(*var)),
+            None => projection.is_none_or(|p| p.contains(var)).then(|| var.to_string()),
         };
-        if let Some(clauses_appeared) = p.list_of_literal_infos.get(&literal) {
-            let cs = &(**clauses_appeared).borrow_mut().list_of_clauses;
-            for clause in cs.iter() {
-                clauses_unsatisfied.remove(&(**clause).borrow().id);
-            }
+        if let Some(name) = name {
+            print!(" {}={}", name, if *value {1} else {0});
         }
     }
+    println!();
+}
+
+// prints every model (over the projection, if any) as it is found, then the
+// number of models
+fn enumerate(args: &Args, input: &String, translated: Option<&Translated>, config: &HeuristicsConfig, limit: u64) -> Result<(), String> {
+    let cnf = match translated {
+        Some(_) => None,
+        None => Some(verify::read_cnf(input)?),
+    };
+    // the auxiliary variables of a translation are not shown
+    let projection_vars: Option<Vec<u32>> = match (translated, &cnf) {
+        (Some(t), _) => Some((1..=t.variables()).collect()),
+        (None, Some(cnf)) => cnf.projection.clone(),
+        (None, None) => None,
+    };
+    let projection: Option<BTreeSet<u32>> = projection_vars.as_ref().map(|vars| vars.iter().copied().collect());
+    let mut first = true;
+    let on_model = |model: &verify::Model| {
+        match (translated, &cnf) {
+            (Some(t), _) => t.check(model),
+            (None, Some(cnf)) => verify(cnf, model),
+            (None, None) => Ok(()),
+        }.unwrap_or_else(exit_with_error);
+        if first {
+            println!("RESULT: SAT");
+            first = false;
+        }
+        print_assignment(model, translated, projection.as_ref());
+    };
+    // every declared, projected or input variable, also one in no clause
+    let variables = match (translated, &cnf) {
+        (Some(t), _) => t.variables(),
+        (None, Some(cnf)) => {
+            let projected = projection_vars.iter().flatten().copied().max().unwrap_or(0);
+            cnf.declared_variables.unwrap_or(0).max(cnf.max_variable()).max(projected)
+        }
+        (None, None) => 0,
+    };
+    let visitor = enumerate::Enumerate {
+        input,
+        translated,
+        use_bcp: !args.no_bcp,
+        seed: args.seed,
+        variables,
+        limit: if limit == 0 { None } else { Some(limit) },
+        projection: projection_vars.as_deref(),
+        on_model,
+    };
+    let (models, prof) = registry::dispatch(&args.heuristics, config, visitor)?;
+    if models == 0 {
+        println!("RESULT: UNSAT");
+    }
+    println!("MODELS: {}", models);
+    if let Some(path) = &args.stats_json {
+        prof.write_json(path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    return Ok(());
+}
+
+// solves a 2QBF and prints whether it is true, with the values of the outer
+// block that make it true (existential) or false (universal)
+fn qbf(args: &Args, input: &String, config: &HeuristicsConfig) -> Result<(), String> {
+    let qbf = qbf::Qbf::from_cnf(&verify::read_cnf(input)?).map_err(|e| format!("{}: {}", input, e))?;
+    let visitor = qbf::SolveQbf { qbf: &qbf, use_bcp: !args.no_bcp, seed: args.seed };
+    let result = registry::dispatch(&args.heuristics, config, visitor)?;
+    info!(target: "qbf", "{} iterations", result.iterations);
+    println!("RESULT: {}", if result.truth { "SAT" } else { "UNSAT" });
+    if let Some(certificate) = &result.certificate {
+        print!("{}", if qbf.universal { "COUNTEREXAMPLE:" } else { "ASSIGNMENT:" });
+        for (var, value) in certificate.iter() {
+            print!(" {}={}", var, if *value {1} else {0});
+        }
+        println!();
+    }
+    if let Some(path) = &args.stats_json {
+        result.profiler.write_json(path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    return Ok(());
+}
 
-    info!(target: "verify", "leftover clauses not satisfied: {:#?}", clauses_unsatisfied);
-    clauses_unsatisfied.is_empty()
+// answers the queries of an iCNF file in order, one line each
+fn icnf(args: &Args, input: &String, config: &HeuristicsConfig) -> Result<(), String> {
+    let lines = parser::parse_icnf(input)?;
+    let visitor = icnf::SolveQueries { lines: &lines, use_bcp: !args.no_bcp, seed: args.seed, progress: progress(args)? };
+    let (_, prof) = registry::dispatch(&args.heuristics, config, visitor)??;
+    if let Some(path) = &args.stats_json {
+        prof.write_json(path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    return Ok(());
 }
 
 fn main() {
     env_logger::init();
     let args = Args::parse();
     info!(target: "solver", "{:?}", args);
+    if let Some(command) = &args.command {
+        match command {
+            Command::Tune(t) => tune::tune(t).unwrap_or_else(exit_with_error),
+            Command::Bench(b) => bench::bench(b).unwrap_or_else(exit_with_error),
+            Command::Replay(r) => trace::replay(r).unwrap_or_else(exit_with_error),
+            Command::VerifyModel(v) => verify::verify_model(v).unwrap_or_else(exit_with_error),
+            Command::Generate(g) => generate::generate(g).unwrap_or_else(exit_with_error),
+            Command::Count(c) => count::count(c).unwrap_or_else(exit_with_error),
+            Command::Bmc(b) => bmc::bmc(b).unwrap_or_else(exit_with_error),
+            Command::Serve(s) => serve::serve(s).unwrap_or_else(exit_with_error),
+        }
+        return;
+    }
     let use_bcp = !args.no_bcp;
+    let config = heuristics_config(&args).unwrap_or_else(exit_with_error);
+
+    if args.list_heuristics {
+        print!("{}", registry::list(&config).unwrap_or_else(exit_with_error));
+        return;
+    }
+    let input = args.input.as_ref().unwrap();
+    if args.format.of(input) == InputFormat::Qdimacs {
+        if args.enumerate.is_some() || args.portfolio.is_some() || args.cube.is_some() || args.conquer.is_some() || args.trace.is_some() {
+            exit_with_error::<()>(String::from("QDIMACS input does not work with --enumerate, --portfolio, --cube, --conquer or --trace"));
+        }
+        qbf(&args, input, &config).unwrap_or_else(exit_with_error);
+        return;
+    }
+    if args.format.of(input) == InputFormat::Icnf {
+        if args.enumerate.is_some() || args.portfolio.is_some() || args.cube.is_some() || args.conquer.is_some() || args.trace.is_some() {
+            exit_with_error::<()>(String::from("iCNF input does not work with --enumerate, --portfolio, --cube, --conquer or --trace"));
+        }
+        icnf(&args, input, &config).unwrap_or_else(exit_with_error);
+        return;
+    }
+    let translated = match args.format.of(input) {
+        InputFormat::Auto | InputFormat::Cnf | InputFormat::Qdimacs | InputFormat::Icnf => None,
+        InputFormat::Opb => Some(pb::PbInput::read(input, args.cardinality, args.pb_encoding).map(Translated::Pb)),
+        InputFormat::Formula => Some(formula::FormulaInput::read(input, args.formula_encoding).map(Translated::Formula)),
+        InputFormat::Aiger => Some(aiger::AigerInput::read(input, args.property).map(Translated::Aiger)),
+    }.map(|t| t.unwrap_or_else(exit_with_error));
+    if args.witness.is_some() && !matches!(translated, Some(Translated::Aiger(_))) {
+        exit_with_error::<()>(String::from("--witness needs AIGER input"));
+    }
+    if translated.is_some() && (args.portfolio.is_some() || args.cube.is_some() || args.conquer.is_some() || args.trace.is_some()) {
+        exit_with_error::<()>(String::from("only DIMACS CNF input works with --portfolio, --cube, --conquer or --trace"));
+    }
 
-    let (p, s) = match args.heuristics.as_str() {
-        "ascending" => test(&args.input, Ascending::new(), use_bcp),
-        "dlis"      => test(&args.input, DLIS::new(), use_bcp),
-        "vsids"     => test(&args.input, VSIDS::new(), use_bcp),
-        _           => panic!("Unrecognised heuristics specified"),
+    if let Some(limit) = args.enumerate {
+        enumerate(&args, input, translated.as_ref(), &config, limit).unwrap_or_else(exit_with_error);
+        return;
+    }
+
+    if let Some(cube_file) = &args.cube {
+        let p = parser::parse(input, &mut Ascending::new());
+        let options = cube::CubeOptions {
+            depth: args.cube_depth,
+            count: args.cube_count,
+            score: args.cube_score,
+        };
+        let cubes = cube::make_cubes(&p, &options);
+        cube::write_cubes(cube_file, &cubes).unwrap_or_else(exit_with_error);
+        println!("CUBES: {}", cubes.len());
+        return;
+    }
+    if let (Some(cube_file), Some(dir)) = (&args.conquer, &args.conquer_jobs) {
+        let p = parser::parse(input, &mut Ascending::new());
+        let cubes = cube::read_cubes(cube_file).unwrap_or_else(exit_with_error);
+        cube::write_jobs(&p, &cubes, dir).unwrap_or_else(exit_with_error);
+        return;
+    }
+
+    let (s, mut prof) = if let Some(cube_file) = &args.conquer {
+        let cubes = cube::read_cubes(cube_file).unwrap_or_else(exit_with_error);
+        cube::conquer(input, &args.heuristics, &config, use_bcp, args.seed, &cubes, args.threads)
+            .unwrap_or_else(exit_with_error)
+    } else if let Some(n) = args.portfolio {
+        let configs = portfolio::default_configs(n, use_bcp, args.seed);
+        let (_, s, prof) = portfolio::solve(input, &configs, &config);
+        (s, prof)
+    } else {
+        let progress = progress(&args).unwrap_or_else(exit_with_error);
+        let trace = args.trace.as_ref().map(|path| TraceWriter::create(path).unwrap_or_else(exit_with_error));
+        registry::dispatch(&args.heuristics, &config, Run { input, translated: translated.as_ref(), use_bcp, seed: args.seed, progress, trace })
+            .unwrap_or_else(exit_with_error)
     };
+    if let Some(trace) = prof.take_trace() {
+        let events = trace.finish().unwrap_or_else(exit_with_error);
+        info!(target: "trace", "{} events written", events);
+    }
 
     if args.check {
         assert!(args.satisfiable == s.is_some());
     }
+    prof.begin_phase(Phase::Verify);
+    let verified = s.as_ref().map_or(Ok(()), |sol| match &translated {
+        Some(t) => t.check(&enumerate::model(sol)),
+        None => verify::read_cnf(input).and_then(|cnf| verify(&cnf, &enumerate::model(sol))),
+    });
+    prof.end_phase(Phase::Verify);
+    verified.unwrap_or_else(exit_with_error);
+    if let (Some(path), Some(Translated::Aiger(aig))) = (&args.witness, &translated) {
+        aig.write_witness(path, s.as_ref().map(enumerate::model).as_ref()).unwrap_or_else(exit_with_error);
+    }
     if let Some(sol) = &s  {
         info!("solution is {:?}", sol);
-        assert!(verify(&p, &sol));
         println!("RESULT: SAT");
-        print!("ASSIGNMENT:");
-        let mut sorted = BTreeSet::new();
-        for s in sol.stack.iter() {
-            sorted.insert((s.assignment.variable.index, s.assignment.polarity));
-        }
-        for (var, pol) in sorted {
-            print!(" {}={}", var, if pol == Polarity::Off {0} else {1});
-        }
-        println!();
+        print_assignment(&enumerate::model(sol), translated.as_ref(), None);
     }
     else {
         println!("RESULT: UNSAT");
     }
+    if let Some(path) = &args.stats_json {
+        prof.write_json(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+    }
 }
@@ -1,19 +1,16 @@
 use crate::heuristics::heuristics::Heuristics;
 use log::info;
-use std::cell::RefCell;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
-use std::rc::Rc;
 
 use crate::sat_solver::*;
-use std::collections::{BTreeSet, HashMap};
 
 pub fn parse(filename: &String, heuristics: &mut impl Heuristics) -> Problem {
     let path = Path::new(filename);
     let display = path.display();
 
-    let mut file = match File::open(&path) {
+    let mut file = match File::open(path) {
         Err(why) => panic!("cannot open {}: {}", display, why),
         Ok(file) => file,
     };
@@ -24,105 +21,147 @@ pub fn parse(filename: &String, heuristics: &mut impl Heuristics) -> Problem {
         Ok(_) => info!(target: "parser", "successfully read {}", display),
     };
 
-    let mut circuit = Problem {
-        list_of_variables: HashMap::<Variable, VariableState>::new(),
-        list_of_literal_infos: HashMap::<Literal, Rc<RefCell<LiteralInfo>>>::new(),
-        list_of_clauses: Vec::<Rc<RefCell<Clause>>>::new(),
-        list_of_clauses_to_check: BTreeSet::new(),
-    };
+    let mut circuit = Problem::new();
 
     // CLAUSE LOOP
-    let mut clause_id: u32 = 0;
+    // A clause is the literals up to the next 0, over any number of lines.
+    let mut literals = Vec::<Literal>::new();
     for line in buffer.split("\n").map(|s| s.trim()) {
         if line.is_empty(){
             continue;
         }
-        if line.starts_with("0") { 
-            continue; 
-        }
+        // SATLIB benchmarks end with "%" and a stray "0", which is not an
+        // empty clause
         if line.starts_with("%") {
-            continue;
+            break;
         }
         if line.starts_with("c") || line.starts_with("p") {
             continue;
         }
-        if circuit.list_of_clauses.len() < (clause_id + 1) as usize {
-            circuit.list_of_clauses.push(Rc::new(RefCell::new(Clause {
-                id: clause_id as u32,
-                // status: ClauseState::Unresolved,
-                list_of_literals: Vec::<Literal>::new(),
-                list_of_literal_infos: vec![],
-                watch_literals: [NULL_LITERAL; 2],
-            })));
+        // the quantifier prefix of QDIMACS, read by the QBF solver
+        if matches!(line.split_whitespace().next(), Some("e") | Some("a")) {
+            continue;
+        }
+        // an XOR constraint, e.g. "x1 -2 3 0", on a single line
+        if let Some(xor) = line.strip_prefix("x") {
+            let xor_literals: Vec<Literal> = xor
+                .split_whitespace()
+                .map_while(|s| s.parse::<i32>().ok())
+                .take_while(|l| *l != 0)
+                .map(Literal::from_dimacs)
+                .collect();
+            let variables = circuit.add_xor(&xor_literals);
+            heuristics.add_parsed_clause(&variables);
+            continue;
         }
         // LITERAL LOOP
-        let mut clause_lit_count = 0;
         for literal_str in line.split_whitespace() {
             let literal_val: i32 = match literal_str.parse() {
                 Ok(val) => val,
                 Err(_) => break,
             };
-            if literal_val == 0 {
-                clause_id += 1;
+            if literal_val != 0 {
+                literals.push(Literal::from_dimacs(literal_val));
                 continue;
             }
-            clause_lit_count += 1;
+            // a 0 without literals before it is the empty clause
+            let current_clause = circuit.add_clause(&literals);
+            heuristics.add_parsed_clause(&current_clause.borrow());
+            literals.clear();
+        }
+    }
+    // the last clause may lack its terminating 0
+    if !literals.is_empty() {
+        let current_clause = circuit.add_clause(&literals);
+        heuristics.add_parsed_clause(&current_clause.borrow());
+    }
 
-            // register new variable
-            assert!(
-                literal_val != 0,
-                "Variable index must be non-zero, because zero is for NULL_VARIABLE, we got {}",
-                literal_val
-            );
-            let variable = Variable {
-                index: literal_val.abs() as u32,
-            };
-            circuit
-                .list_of_variables
-                .insert(variable, VariableState::Unassigned);
+    circuit
+}
 
-            // register new literal
-            let literal = Literal {
-                variable: variable,
-                polarity: if literal_val > 0 {
-                    Polarity::On
-                } else {
-                    Polarity::Off
-                },
-            };
-            let current_clause = circuit.list_of_clauses.last().unwrap();
-            circuit
-                .list_of_literal_infos
-                .entry(literal.clone())
-                .and_modify(|e| {
-                    (**e)
-                        .borrow_mut()
-                        .list_of_clauses
-                        .push(Rc::clone(current_clause))
-                })
-                .or_insert({
-                    let l = LiteralInfo {
-                        list_of_clauses: vec![Rc::clone(current_clause)],
-                        status: LiteralState::Unknown,
-                    };
-                    Rc::new(RefCell::new(l))
-                });
-            let mut clause = (**current_clause).borrow_mut();
-            clause.list_of_literals.push(literal);
-            clause.watch_literals[clause_lit_count % 2] = literal;
+/// A line of an iCNF file: a clause, or a query (`a <lits> 0`) that solves the
+/// clauses read so far under the given assumptions.
+pub enum Incremental {
+    Clause(Vec<Literal>),
+    Query(Vec<Literal>),
+}
+
+/// Reads an iCNF file (`p inccnf`), keeping the clauses and queries in order.
+/// A clause or query is on a single line; a line `0` is the empty clause.
+/// Tautological clauses are left out.
+pub fn parse_icnf(filename: &String) -> Result<Vec<Incremental>, String> {
+    let buffer = std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+    let mut lines = Vec::new();
+    for (n, line) in buffer.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if line.is_empty() || line.starts_with("c") || line.starts_with("p") {
+            continue;
+        }
+        if line.starts_with("x") {
+            return Err(format!("{}:{}: XOR constraints are not supported in iCNF", filename, n));
+        }
+        let query = line.strip_prefix("a");
+        let mut literals = Vec::new();
+        for token in query.unwrap_or(line).split_whitespace() {
+            // i32::MIN has no negation, so a model could not print it
+            let value: i32 = token
+                .parse()
+                .ok()
+                .filter(|l| *l != i32::MIN)
+                .ok_or_else(|| format!("{}:{}: bad literal '{}'", filename, n, token))?;
+            if value == 0 {
+                break;
+            }
+            literals.push(Literal::from_dimacs(value));
+        }
+        if query.is_some() {
+            lines.push(Incremental::Query(literals));
+            continue;
+        }
+        // the solver takes clauses without repeated literals; a tautology
+        // constrains nothing
+        literals.sort();
+        literals.dedup();
+        if literals.windows(2).all(|w| w[0].variable != w[1].variable) {
+            lines.push(Incremental::Clause(literals));
         }
-        let current_clause = circuit.list_of_clauses.last().unwrap();
-        heuristics.add_parsed_clause(&current_clause.borrow());
     }
+    return Ok(lines);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::ascending::Ascending;
+    use crate::heuristics::heuristics::Heuristics;
+    use crate::testing::temp_file;
 
-    circuit.list_of_clauses.iter().for_each(|rc| {
-        let mut clause_ref = (**rc).borrow_mut();
-        clause_ref.list_of_literal_infos = clause_ref
-            .list_of_literals
+    fn clauses(text: &str) -> Vec<Vec<i32>> {
+        let path = temp_file("parser.cnf", text);
+        let problem = parse(&path, &mut Ascending::with_options(Default::default()));
+        std::fs::remove_file(&path).unwrap();
+        return problem
+            .list_of_clauses
             .iter()
-            .map(|l| Rc::clone(&circuit.list_of_literal_infos[l]))
-            .collect()
-    });
+            .map(|c| c.borrow().list_of_literals.iter().map(|l| l.to_dimacs()).collect())
+            .collect();
+    }
 
-    circuit
+    #[test]
+    fn clauses_end_at_zero_not_at_line_ends() {
+        assert_eq!(clauses("p cnf 3 2\n-1 -2\n-3 0\n1 0\n2 0\n"), vec![vec![-1, -2, -3], vec![1], vec![2]]);
+        assert_eq!(clauses("p cnf 3 2\n-1 -2 0 -3 0\n1 2"), vec![vec![-1, -2], vec![-3], vec![1, 2]]);
+    }
+
+    #[test]
+    fn a_lone_zero_is_the_empty_clause() {
+        assert_eq!(clauses("p cnf 1 2\n1 0\n0\n"), vec![vec![1], vec![]]);
+        let path = temp_file("parser.cnf", "p cnf 1 2\n1 0\n0\n");
+        let mut h = Ascending::with_options(Default::default());
+        let mut problem = parse(&path, &mut h);
+        std::fs::remove_file(&path).unwrap();
+        let interrupt = std::sync::atomic::AtomicBool::new(false);
+        assert!(dpll::dpll(&mut problem, &mut h, &mut crate::profiler::SolverProfiler::new(), &interrupt).is_none());
+        // but not after the "%" that ends SATLIB benchmarks
+        assert_eq!(clauses("p cnf 2 2\n1 2 0\n-1 0\n%\n0\n"), vec![vec![1, 2], vec![-1]]);
+    }
 }
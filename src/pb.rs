@@ -0,0 +1,368 @@
+use crate::encodings::{self, CardinalityEncoding, PbEncoding, ProblemSink};
+use crate::heuristics::heuristics::Heuristics;
+use crate::sat_solver::*;
+use crate::verify::Model;
+use std::collections::BTreeMap;
+
+// Pseudo-Boolean input in the OPB format of the pseudo-Boolean competition:
+//
+//   * #variable= 3 #constraint= 2
+//   min: +1 x1 +2 x2 ;
+//   +1 x1 +2 x2 -3 ~x3 >= 2 ;
+//   +1 x1 +1 x2 +1 x3 = 1 ;
+//
+// Only linear constraints are read. An objective function is accepted but
+// not optimised: the solver decides whether the constraints can be satisfied.
+//
+// Every constraint is normalised to a sum of positive coefficients times
+// literals of distinct variables being at least a positive bound. One whose
+// coefficients are all equal is a cardinality constraint, "at least k of
+// these literals", and the others are general PB constraints. Cardinality
+// constraints are propagated natively or encoded into clauses, general ones
+// are always encoded.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    AtLeast,
+    Equal,
+    AtMost,
+}
+
+#[derive(Debug)]
+pub struct Constraint {
+    // coefficient and literal (negative for ~x) of every term
+    pub terms: Vec<(i64, i32)>,
+    pub relation: Relation,
+    pub rhs: i64,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub struct Opb {
+    // the largest variable index, in the header or the constraints
+    pub variables: u32,
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardinalityMode {
+    /// Propagate cardinality constraints with watched literals
+    Native,
+    Sequential,
+    Totalizer,
+    Network,
+}
+
+pub fn read_opb(path: &str) -> Result<Opb, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_opb(&text).map_err(|e| format!("{}: {}", path, e));
+}
+
+fn parse_opb(text: &str) -> Result<Opb, String> {
+    let mut opb = Opb { variables: 0, constraints: Vec::new() };
+    // the tokens of the statement read so far, and the line it starts on
+    let mut statement = Vec::<&str>::new();
+    let mut start = 0;
+    for (n, line) in text.lines().enumerate() {
+        if let Some(comment) = line.trim_start().strip_prefix('*') {
+            // the header: "* #variable= 3 #constraint= 2"
+            let mut words = comment.split_whitespace();
+            while let Some(word) = words.next() {
+                if word == "#variable=" {
+                    if let Some(v) = words.next().and_then(|v| v.parse().ok()) {
+                        opb.variables = opb.variables.max(v);
+                    }
+                }
+            }
+            continue;
+        }
+        for (i, part) in line.split(';').enumerate() {
+            if i > 0 {
+                // a ';' ends the statement
+                if let Some(c) = parse_statement(&statement, start + 1)? {
+                    opb.constraints.push(c);
+                }
+                statement.clear();
+            }
+            for token in part.split_whitespace() {
+                if statement.is_empty() {
+                    start = n;
+                }
+                statement.push(token);
+            }
+        }
+    }
+    if !statement.is_empty() {
+        return Err(format!("line {}: missing ';'", start + 1));
+    }
+    for c in opb.constraints.iter() {
+        for (_, l) in c.terms.iter() {
+            opb.variables = opb.variables.max(l.unsigned_abs());
+        }
+    }
+    return Ok(opb);
+}
+
+// A constraint, or None for the objective function or an empty statement.
+fn parse_statement(tokens: &[&str], line: usize) -> Result<Option<Constraint>, String> {
+    let Some(first) = tokens.first() else {
+        return Ok(None);
+    };
+    if first.starts_with("min:") || first.starts_with("max:") {
+        return Ok(None);
+    }
+    let mut terms = Vec::new();
+    let mut coefficient: Option<i64> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let relation = match *token {
+            ">=" => Some(Relation::AtLeast),
+            "=" => Some(Relation::Equal),
+            "<=" => Some(Relation::AtMost),
+            _ => None,
+        };
+        if let Some(relation) = relation {
+            if coefficient.is_some() {
+                return Err(format!("line {}: coefficient without a literal", line));
+            }
+            let [rhs] = &tokens[i + 1..] else {
+                return Err(format!("line {}: expected one number after {}", line, token));
+            };
+            let rhs = parse_integer(rhs).ok_or_else(|| format!("line {}: bad right-hand side {}", line, rhs))?;
+            return Ok(Some(Constraint { terms, relation, rhs, line }));
+        }
+        if let Some(literal) = parse_literal(token) {
+            let Some(a) = coefficient.take() else {
+                return Err(format!("line {}: only linear constraints are supported", line));
+            };
+            terms.push((a, literal));
+            continue;
+        }
+        if coefficient.is_some() {
+            return Err(format!("line {}: two coefficients in a row", line));
+        }
+        coefficient = Some(parse_integer(token).ok_or_else(|| format!("line {}: unexpected {}", line, token))?);
+    }
+    return Err(format!("line {}: missing relation", line));
+}
+
+fn parse_integer(token: &str) -> Option<i64> {
+    return token.strip_prefix('+').unwrap_or(token).parse().ok();
+}
+
+// "x3" or "~x3", as 3 or -3
+fn parse_literal(token: &str) -> Option<i32> {
+    let (negated, name) = match token.strip_prefix('~') {
+        Some(name) => (true, name),
+        None => (false, token),
+    };
+    let index: i32 = name.strip_prefix('x')?.parse().ok().filter(|i| *i > 0)?;
+    return Some(if negated { -index } else { index });
+}
+
+/// The coefficients of the true literals of `terms` add up to at least
+/// `bound`; the coefficients are positive and at most the bound, the
+/// variables distinct.
+#[derive(Debug)]
+pub struct Normalized {
+    pub terms: Vec<(u64, Literal)>,
+    pub bound: u64,
+}
+
+impl Constraint {
+    /// The normalised constraints equivalent to this one: none if it always
+    /// holds, two for an equation.
+    pub fn normalize(&self) -> Result<Vec<Normalized>, String> {
+        let terms: Vec<(i128, i32)> = self.terms.iter().map(|(a, l)| (*a as i128, *l)).collect();
+        let negated: Vec<(i128, i32)> = terms.iter().map(|(a, l)| (-a, *l)).collect();
+        let rhs = self.rhs as i128;
+        let parts = match self.relation {
+            Relation::AtLeast => vec![(terms, rhs)],
+            Relation::AtMost => vec![(negated, -rhs)],
+            Relation::Equal => vec![(terms, rhs), (negated, -rhs)],
+        };
+        let mut normalized = Vec::new();
+        for (terms, bound) in parts {
+            if let Some(n) = normalize(&terms, bound).map_err(|e| format!("line {}: {}", self.line, e))? {
+                normalized.push(n);
+            }
+        }
+        return Ok(normalized);
+    }
+
+    fn holds(&self, model: &Model) -> bool {
+        let sum: i128 = self
+            .terms
+            .iter()
+            .filter(|(_, l)| model.get(&l.unsigned_abs()).copied().unwrap_or(false) == (*l > 0))
+            .map(|(a, _)| *a as i128)
+            .sum();
+        return match self.relation {
+            Relation::AtLeast => sum >= self.rhs as i128,
+            Relation::Equal => sum == self.rhs as i128,
+            Relation::AtMost => sum <= self.rhs as i128,
+        };
+    }
+}
+
+// sum a_i l_i >= bound
+fn normalize(terms: &[(i128, i32)], mut bound: i128) -> Result<Option<Normalized>, String> {
+    // a ~x = a - a x: every variable gets one coefficient on its positive
+    // literal
+    let mut coefficients = BTreeMap::<u32, i128>::new();
+    for (a, l) in terms.iter() {
+        let c = coefficients.entry(l.unsigned_abs()).or_insert(0);
+        if *l > 0 {
+            *c += a;
+        } else {
+            *c -= a;
+            bound -= a;
+        }
+    }
+    // and a negative one goes to the negated literal: c x = c + (-c) ~x
+    let mut normalized = Vec::new();
+    for (v, c) in coefficients {
+        if c > 0 {
+            normalized.push((c, Literal::from_dimacs(v as i32)));
+        } else if c < 0 {
+            normalized.push((-c, Literal::from_dimacs(-(v as i32))));
+            bound -= c;
+        }
+    }
+    if bound <= 0 {
+        return Ok(None);
+    }
+    let bound = u64::try_from(bound).map_err(|_| String::from("coefficients too large"))?;
+    return Ok(Some(Normalized {
+        terms: normalized.into_iter().map(|(a, l)| (a.min(bound as i128) as u64, l)).collect(),
+        bound,
+    }));
+}
+
+/// Checks a model against the constraints as written in the input; a
+/// variable missing from the model is false.
+pub fn check(opb: &Opb, model: &Model) -> Result<(), String> {
+    match opb.constraints.iter().find(|c| !c.holds(model)) {
+        Some(c) => Err(format!("the solution falsifies the constraint on line {}", c.line)),
+        None => Ok(()),
+    }
+}
+
+/// An OPB formula, normalised, and how to solve it.
+pub struct PbInput {
+    pub opb: Opb,
+    pub normalized: Vec<Normalized>,
+    pub cardinality: CardinalityMode,
+    pub encoding: PbEncoding,
+}
+
+impl PbInput {
+    pub fn read(path: &str, cardinality: CardinalityMode, encoding: PbEncoding) -> Result<PbInput, String> {
+        let opb = read_opb(path)?;
+        let mut normalized = Vec::new();
+        for c in opb.constraints.iter() {
+            normalized.extend(c.normalize().map_err(|e| format!("{}: {}", path, e))?);
+        }
+        return Ok(PbInput { opb, normalized, cardinality, encoding });
+    }
+
+    /// The problem of the normalised constraints.
+    pub fn load(&self, heuristics: &mut impl Heuristics) -> Problem {
+        let mut problem = Problem::new();
+        // the auxiliary variables come after those of the formula
+        let mut sink = ProblemSink {
+            problem: &mut problem,
+            heuristics,
+            next_variable: self.opb.variables,
+        };
+        for c in self.normalized.iter() {
+            self.add(&mut sink, c);
+        }
+        return problem;
+    }
+
+    fn add(&self, sink: &mut ProblemSink<impl Heuristics>, c: &Normalized) {
+        let literals: Vec<Literal> = c.terms.iter().map(|(_, l)| *l).collect();
+        let a = match c.terms.first() {
+            Some((a, _)) if c.terms.iter().all(|(b, _)| b == a) => *a,
+            _ => {
+                encodings::pb_at_least(sink, &c.terms, c.bound, self.encoding);
+                return;
+            }
+        };
+        let k = c.bound.div_ceil(a) as usize;
+        let encoding = match self.cardinality {
+            CardinalityMode::Native if 1 < k && k <= literals.len() => {
+                let variables = sink.problem.add_cardinality(&literals, k);
+                sink.heuristics.add_parsed_clause(&variables);
+                return;
+            }
+            // a clause, or a constraint that cannot hold
+            CardinalityMode::Native => CardinalityEncoding::Sequential,
+            CardinalityMode::Sequential => CardinalityEncoding::Sequential,
+            CardinalityMode::Totalizer => CardinalityEncoding::Totalizer,
+            CardinalityMode::Network => CardinalityEncoding::Network,
+        };
+        encodings::at_least_k(sink, &literals, k, encoding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::tests::check_translation;
+    use crate::input::Translated;
+    use crate::rng::Rng;
+
+    const MODES: [CardinalityMode; 4] =
+        [CardinalityMode::Native, CardinalityMode::Sequential, CardinalityMode::Totalizer, CardinalityMode::Network];
+
+    // the number of models of `text`, the same under every way of solving it
+    fn count_models(text: &str) -> u64 {
+        let mut counts = Vec::new();
+        for cardinality in MODES {
+            for encoding in [PbEncoding::Bdd, PbEncoding::Adder] {
+                let opb = parse_opb(text).unwrap();
+                let normalized = opb.constraints.iter().flat_map(|c| c.normalize().unwrap()).collect();
+                let input = PbInput { opb, normalized, cardinality, encoding };
+                counts.push(check_translation(&Translated::Pb(input), "vsids"));
+            }
+        }
+        assert!(counts.iter().all(|c| *c == counts[0]), "{:?} for\n{}", counts, text);
+        return counts[0];
+    }
+
+    #[test]
+    fn small_formulas_have_their_models() {
+        // x1 + 2 x2 + 3 x3 >= 5, i.e. x2 and x3; x1 and x4 are free
+        let text = "* #variable= 4 #constraint= 1\nmin: +1 x1 ;\n+1 x1 +2 x2 -3 ~x3 >= 2 ;\n";
+        assert_eq!(count_models(text), 2 * 2);
+        assert_eq!(count_models("+1 x1 +1 x2 +1 x3 = 1 ;"), 3);
+        // at least two of four, and at most three
+        assert_eq!(count_models("+2 x1 +2 x2 +2 x3 +2 x4 >= 4 ;\n+1 x1 +1 x2 +1 x3 +1 x4 <= 3 ;"), 6 + 4);
+        // x1 and ~x1 cancel out to the constant 1
+        assert_eq!(count_models("+1 x1 +1 ~x1 +1 x2 >= 2 ;"), 2);
+        assert_eq!(count_models("+1 x1 +1 x2 >= 3 ;"), 0);
+        assert_eq!(count_models("+3 x1 +5 x2 +7 x3 = 8 ;"), 1);
+    }
+
+    #[test]
+    fn random_formulas_have_their_models() {
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let n = 1 + rng.below(5);
+            let mut text = String::new();
+            for _ in 0..1 + rng.below(3) {
+                // equal coefficients now and then, for cardinality constraints
+                let same = rng.below(2) == 0;
+                for _ in 0..1 + rng.below(n) {
+                    let a = if same { 2 } else { rng.below(7) as i64 - 3 };
+                    let sign = if rng.below(3) == 0 { "~" } else { "" };
+                    text += &format!("{:+} {}x{} ", a, sign, 1 + rng.below(n));
+                }
+                let relation = [">=", "<=", ">=", "<=", "="][rng.below(5) as usize];
+                text += &format!("{} {} ;\n", relation, rng.below(5) as i64 - 1);
+            }
+            count_models(&text);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::polarity::{PolarityMode, PolarityOverride};
+use crate::heuristics::registry::{self, HeuristicsConfig, HeuristicsVisitor};
+use crate::parser;
+use crate::profiler::{Phase, SolverProfiler};
+use crate::rng::Rng;
+use crate::sat_solver::*;
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+// Each worker parses the input on its own thread, so the Rc/RefCell-based
+// Problem never crosses a thread boundary. Only the SolutionStack (plain Copy
+// data) is sent back.
+//
+// Note: dpll does not learn clauses, so there is nothing for the workers to
+// share besides the cancellation flag.
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    pub heuristics: String,
+    pub polarity: PolarityMode,
+    pub use_bcp: bool,
+    pub seed: u64,
+}
+
+const HEURISTICS: [&str; 3] = ["vsids", "dlis", "ascending"];
+const POLARITY_MODES: [PolarityMode; 4] = [
+    PolarityMode::Heuristic,
+    PolarityMode::Inverted,
+    PolarityMode::Negative,
+    PolarityMode::Positive,
+];
+
+/// Returns `n` pairwise-different worker configurations. Every heuristics is
+/// deterministic, so once each has run with each fixed polarity mode, the
+/// remaining workers pick their polarities at random. Each worker gets its own
+/// seed, drawn from `seed`.
+pub fn default_configs(n: usize, use_bcp: bool, seed: u64) -> Vec<WorkerConfig> {
+    let mut rng = Rng::new(seed);
+    let mut configs = vec![];
+    for i in 0..n.max(1) {
+        let fixed = POLARITY_MODES.len() * HEURISTICS.len();
+        configs.push(WorkerConfig {
+            heuristics: String::from(HEURISTICS[i % HEURISTICS.len()]),
+            polarity: if i < fixed { POLARITY_MODES[i / HEURISTICS.len()] } else { PolarityMode::Random },
+            use_bcp,
+            seed: rng.next_u64(),
+        });
+    }
+    configs
+}
+
+struct Worker<'a> {
+    input: &'a String,
+    mode: PolarityMode,
+    use_bcp: bool,
+    seed: u64,
+    interrupt: &'a AtomicBool,
+}
+
+impl HeuristicsVisitor for Worker<'_> {
+    type Output = (Option<SolutionStack>, SolverProfiler);
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+        let mut prof = SolverProfiler::new();
+        let mut h = PolarityOverride::wrap(H::with_options(options), self.mode);
+        h.set_use_bcp(self.use_bcp);
+        h.set_seed(self.seed);
+        prof.begin_phase(Phase::Parse);
+        let mut problem = parser::parse(self.input, &mut h);
+        prof.end_phase(Phase::Parse);
+        prof.reset_start_time();
+        let solution = dpll::dpll(&mut problem, &mut h, &mut prof, self.interrupt);
+        prof.calc_duration_till_now();
+        info!(target: "profiler", "Profiling results: {}", prof);
+        (solution, prof)
+    }
+}
+
+fn dispatch_worker(
+    input: &String,
+    config: &WorkerConfig,
+    options: &HeuristicsConfig,
+    interrupt: &AtomicBool,
+) -> (Option<SolutionStack>, SolverProfiler) {
+    let worker = Worker {
+        input,
+        mode: config.polarity,
+        use_bcp: config.use_bcp,
+        seed: config.seed,
+        interrupt,
+    };
+    registry::dispatch(&config.heuristics, options, worker)
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Races one solver per configuration and returns the answer of whichever
+/// finishes first, together with the index of the winning configuration. The
+/// remaining workers are cancelled and joined before returning. The options
+/// of each heuristics are taken from `options`. The returned profiler is the
+/// winner's.
+pub fn solve(
+    input: &str,
+    configs: &[WorkerConfig],
+    options: &HeuristicsConfig,
+) -> (usize, Option<SolutionStack>, SolverProfiler) {
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+
+    let handles: Vec<_> = configs
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, config)| {
+            let input = input.to_string();
+            let interrupt = Arc::clone(&interrupt);
+            let options = options.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                info!(target: "portfolio", "worker {} starts with {:?}", i, config);
+                let (solution, prof) = dispatch_worker(&input, &config, &options, &interrupt);
+                // a cancelled worker has no definitive answer to report
+                if !interrupt.load(Ordering::Relaxed) {
+                    _ = tx.send((i, solution, prof));
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let (winner, solution, prof) = rx
+        .recv()
+        .expect("all portfolio workers exited without an answer");
+    interrupt.store(true, Ordering::Relaxed);
+    info!(target: "portfolio", "worker {} won with {:?}", winner, configs[winner]);
+
+    for handle in handles {
+        _ = handle.join();
+    }
+
+    (winner, solution, prof)
+}
@@ -1,6 +1,25 @@
+use crate::progress::{Progress, Snapshot};
+use crate::trace::{Event, TraceWriter};
 use core::fmt;
+use std::fs;
+use std::io::{self, Write};
 use std::time::{Duration, Instant};
 
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Parse,
+    Preprocess,
+    Search,
+    Verify,
+}
+
+const PHASES: [(Phase, &str); 4] = [
+    (Phase::Parse, "parse"),
+    (Phase::Preprocess, "preprocess"),
+    (Phase::Search, "search"),
+    (Phase::Verify, "verify"),
+];
+
 #[derive(Debug)]
 pub struct SolverProfiler {
     // counters
@@ -8,10 +27,31 @@ pub struct SolverProfiler {
     implied_decisions: u64,
     backtracked_decisions: u64,
     flipped_decisions: u64,
+    conflicts: u64,
+    // watched clauses examined by BCP
+    propagations: u64,
+    // dpll neither restarts nor learns clauses; these stay 0 for now but are
+    // part of the exported statistics so that consumers need not special-case
+    // this solver
+    restarts: u64,
+    learned_clauses: u64,
+    deleted_clauses: u64,
+    max_decision_level: u64,
 
     // timers
     duration: Duration,
     start_time: Instant,
+    phase_durations: [Duration; 4],
+    phase_start_times: [Instant; 4],
+
+    progress: Option<Progress>,
+    trace: Option<TraceWriter>,
+}
+
+impl Default for SolverProfiler {
+    fn default() -> Self {
+        return Self::new();
+    }
 }
 
 impl SolverProfiler {
@@ -21,8 +61,53 @@ impl SolverProfiler {
             implied_decisions: 0,
             backtracked_decisions: 0,
             flipped_decisions: 0,
+            conflicts: 0,
+            propagations: 0,
+            restarts: 0,
+            learned_clauses: 0,
+            deleted_clauses: 0,
+            max_decision_level: 0,
             start_time: Instant::now(),
             duration: Duration::new(0, 0),
+            phase_durations: [Duration::new(0, 0); 4],
+            phase_start_times: [Instant::now(); 4],
+            progress: None,
+            trace: None,
+        }
+    }
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.progress = Some(progress);
+    }
+    /// Called by the search once per decision; prints a progress row when
+    /// one is due.
+    pub fn tick(&mut self, trail: usize, variables: usize) {
+        let Some(mut progress) = self.progress.take() else {
+            return;
+        };
+        if progress.due(self.conflicts) {
+            progress.print(&Snapshot {
+                elapsed: Instant::now().duration_since(self.start_time),
+                conflicts: self.conflicts,
+                decisions: self.free_decisions,
+                propagations: self.propagations,
+                restarts: self.restarts,
+                learned: self.learned_clauses,
+                trail: trail as u64,
+                remaining: (variables - trail) as u64,
+                max_level: self.max_decision_level,
+            });
+        }
+        self.progress = Some(progress);
+    }
+    pub fn set_trace(&mut self, trace: TraceWriter) {
+        self.trace = Some(trace);
+    }
+    pub fn take_trace(&mut self) -> Option<TraceWriter> {
+        self.trace.take()
+    }
+    pub fn record(&mut self, event: Event) {
+        if let Some(trace) = &mut self.trace {
+            trace.record(&event);
         }
     }
     pub fn reset_start_time(&mut self) {
@@ -31,6 +116,18 @@ impl SolverProfiler {
     pub fn calc_duration_till_now(&mut self) {
         self.duration = Instant::now().duration_since(self.start_time);
     }
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    pub fn begin_phase(&mut self, phase: Phase) {
+        self.phase_start_times[phase as usize] = Instant::now();
+    }
+    // phases may be entered repeatedly (e.g. incremental solving), their
+    // durations add up
+    pub fn end_phase(&mut self, phase: Phase) {
+        let elapsed = Instant::now().duration_since(self.phase_start_times[phase as usize]);
+        self.phase_durations[phase as usize] += elapsed;
+    }
     pub fn bump_free_decisions(&mut self) {
         self.free_decisions += 1;
     }
@@ -43,11 +140,81 @@ impl SolverProfiler {
     pub fn bump_flipped_decisions(&mut self) {
         self.flipped_decisions += 1;
     }
+    pub fn bump_conflicts(&mut self) {
+        self.conflicts += 1;
+    }
+    pub fn bump_propagations(&mut self) {
+        self.propagations += 1;
+    }
+    pub fn note_decision_level(&mut self, level: u64) {
+        self.max_decision_level = self.max_decision_level.max(level);
+    }
+
+    /// Adds the counters and phase timers of another profiler, e.g. of another
+    /// worker thread. The maximum decision level is the larger of both.
+    pub fn merge(&mut self, other: &SolverProfiler) {
+        self.free_decisions += other.free_decisions;
+        self.implied_decisions += other.implied_decisions;
+        self.backtracked_decisions += other.backtracked_decisions;
+        self.flipped_decisions += other.flipped_decisions;
+        self.conflicts += other.conflicts;
+        self.propagations += other.propagations;
+        self.restarts += other.restarts;
+        self.learned_clauses += other.learned_clauses;
+        self.deleted_clauses += other.deleted_clauses;
+        self.max_decision_level = self.max_decision_level.max(other.max_decision_level);
+        for (mine, theirs) in self.phase_durations.iter_mut().zip(other.phase_durations.iter()) {
+            *mine += *theirs;
+        }
+    }
+
+    // every counter by name, for the machine-readable outputs
+    pub fn counters(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("free_decisions", self.free_decisions),
+            ("implied_decisions", self.implied_decisions),
+            ("backtracked_decisions", self.backtracked_decisions),
+            ("flipped_decisions", self.flipped_decisions),
+            ("conflicts", self.conflicts),
+            ("propagations", self.propagations),
+            ("restarts", self.restarts),
+            ("learned_clauses", self.learned_clauses),
+            ("deleted_clauses", self.deleted_clauses),
+            ("max_decision_level", self.max_decision_level),
+        ]
+    }
+
+    // every timer by name, in seconds
+    pub fn timers(&self) -> Vec<(&'static str, f64)> {
+        let mut timers = vec![("duration", self.duration.as_secs_f64())];
+        for (phase, name) in PHASES {
+            timers.push((name, self.phase_durations[phase as usize].as_secs_f64()));
+        }
+        timers
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let counters: serde_json::Map<String, serde_json::Value> =
+            self.counters().into_iter().map(|(k, v)| (k.to_string(), v.into())).collect();
+        let timers: serde_json::Map<String, serde_json::Value> =
+            self.timers().into_iter().map(|(k, v)| (k.to_string(), v.into())).collect();
+        serde_json::json!({ "counters": counters, "timers": timers })
+    }
+
+    /// Writes the statistics as JSON to `path`, or to stdout for "-".
+    pub fn write_json(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        if path == "-" {
+            return writeln!(io::stdout(), "{}", json);
+        }
+        fs::write(path, json + "\n")
+    }
 }
 
 impl fmt::Display for SolverProfiler {
+    // plotter.py reads the duration as the last field
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "free_decisions: {}, implied_decisions: {}, backtracked_decisions: {}, flipped_decisions: {}, duration: {}us", 
-        self.free_decisions, self.implied_decisions, self.backtracked_decisions, self.flipped_decisions, self.duration.as_micros())
+        write!(f, "free_decisions: {}, implied_decisions: {}, backtracked_decisions: {}, flipped_decisions: {}, conflicts: {}, propagations: {}, max_decision_level: {}, duration: {}us",
+        self.free_decisions, self.implied_decisions, self.backtracked_decisions, self.flipped_decisions, self.conflicts, self.propagations, self.max_decision_level, self.duration.as_micros())
     }
 }
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// MiniSat/CaDiCaL-style progress table on stderr, one row every N conflicts
+// and/or every few seconds. Rows start with "c " so they pass as DIMACS
+// comments if stderr is captured together with the solver output.
+
+// the header is repeated after this many rows
+const HEADER_EVERY: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Time,
+    Conflicts,
+    Decisions,
+    Propagations,
+    PropagationsPerSec,
+    Restarts,
+    Learned,
+    Trail,
+    Remaining,
+    MaxLevel,
+}
+
+const COLUMNS: [(Column, &str); 10] = [
+    (Column::Time, "time"),
+    (Column::Conflicts, "conflicts"),
+    (Column::Decisions, "decisions"),
+    (Column::Propagations, "props"),
+    (Column::PropagationsPerSec, "props/s"),
+    (Column::Restarts, "restarts"),
+    (Column::Learned, "learned"),
+    (Column::Trail, "trail"),
+    (Column::Remaining, "remaining"),
+    (Column::MaxLevel, "maxlevel"),
+];
+
+pub const DEFAULT_COLUMNS: &str = "time,conflicts,decisions,props/s,restarts,learned,trail,remaining";
+
+/// Parses a comma-separated list of column names.
+pub fn parse_columns(list: &str) -> Result<Vec<Column>, String> {
+    list.split(',')
+        .map(|name| {
+            COLUMNS
+                .iter()
+                .find(|(_, n)| *n == name.trim())
+                .map(|(c, _)| *c)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = COLUMNS.iter().map(|(_, n)| *n).collect();
+                    format!("unknown progress column '{}', expected one of {}", name, known.join(","))
+                })
+        })
+        .collect()
+}
+
+fn column_name(column: Column) -> &'static str {
+    COLUMNS.iter().find(|(c, _)| *c == column).unwrap().1
+}
+
+// the values a row is made of, taken from SolverProfiler and the search state
+pub struct Snapshot {
+    pub elapsed: Duration,
+    pub conflicts: u64,
+    pub decisions: u64,
+    pub propagations: u64,
+    pub restarts: u64,
+    pub learned: u64,
+    pub trail: u64,
+    pub remaining: u64,
+    pub max_level: u64,
+}
+
+#[derive(Debug)]
+pub struct Progress {
+    every_conflicts: Option<u64>,
+    every: Option<Duration>,
+    columns: Vec<Column>,
+    next_conflicts: u64,
+    next_time: Option<Instant>,
+    rows: u64,
+}
+
+impl Progress {
+    pub fn new(every_conflicts: Option<u64>, every: Option<Duration>, columns: Vec<Column>) -> Self {
+        Progress {
+            every_conflicts,
+            every,
+            columns,
+            next_conflicts: every_conflicts.unwrap_or(u64::MAX),
+            next_time: every.map(|e| Instant::now() + e),
+            rows: 0,
+        }
+    }
+
+    /// True if a row is due; also schedules the next one.
+    pub fn due(&mut self, conflicts: u64) -> bool {
+        let by_conflicts = conflicts >= self.next_conflicts;
+        let by_time = self.next_time.is_some_and(|t| Instant::now() >= t);
+        if !by_conflicts && !by_time {
+            return false;
+        }
+        if let Some(n) = self.every_conflicts {
+            self.next_conflicts = conflicts + n;
+        }
+        self.next_time = self.every.map(|e| Instant::now() + e);
+        return true;
+    }
+
+    pub fn print(&mut self, s: &Snapshot) {
+        let mut err = io::stderr().lock();
+        if self.rows.is_multiple_of(HEADER_EVERY) {
+            let names: Vec<String> = self.columns.iter().map(|c| format!("{:>10}", column_name(*c))).collect();
+            _ = writeln!(err, "c {}", names.join(" "));
+        }
+        self.rows += 1;
+
+        let secs = s.elapsed.as_secs_f64();
+        let values: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| match c {
+                Column::Time => format!("{:>10.2}", secs),
+                Column::Conflicts => format!("{:>10}", s.conflicts),
+                Column::Decisions => format!("{:>10}", s.decisions),
+                Column::Propagations => format!("{:>10}", s.propagations),
+                Column::PropagationsPerSec => format!("{:>10.0}", s.propagations as f64 / secs.max(1e-9)),
+                Column::Restarts => format!("{:>10}", s.restarts),
+                Column::Learned => format!("{:>10}", s.learned),
+                Column::Trail => format!("{:>10}", s.trail),
+                Column::Remaining => format!("{:>10}", s.remaining),
+                Column::MaxLevel => format!("{:>10}", s.max_level),
+            })
+            .collect();
+        _ = writeln!(err, "c {}", values.join(" "));
+    }
+}
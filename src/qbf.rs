@@ -0,0 +1,365 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::HeuristicsVisitor;
+use crate::profiler::SolverProfiler;
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use crate::verify::{Cnf, Model};
+use log::info;
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicBool;
+
+// 2QBF: formulas ∃X ∀Y φ and ∀X ∃Y φ with a CNF matrix φ, read from QDIMACS.
+// Variables in no block of the prefix are existential and outermost, adjacent
+// blocks of the same quantifier are merged, and a prefix of one block (or
+// none) is a 2QBF with an empty block.
+//
+// Both are solved by counterexample-guided abstraction refinement with two
+// incremental SAT solvers. For ∃X ∀Y φ, the abstraction proposes an x; the
+// other solver, holding the negation of φ, looks for a y with φ(x, y) false.
+// If there is none, x is the certificate; otherwise the abstraction gets
+// φ(X, y) (the clauses y does not satisfy, cut down to their literals over X),
+// which x falsifies. ∀X ∃Y φ is the dual: the abstraction proposes an x that
+// falsifies φ(X, y) for every y found so far, and the other solver, holding φ,
+// looks for a y with φ(x, y) true. If there is none, x is a counterexample.
+
+pub struct Qbf {
+    // whether the outer block is universal
+    pub universal: bool,
+    pub outer: Vec<u32>,
+    pub clauses: Vec<Vec<i32>>,
+    pub variables: u32,
+}
+
+impl Qbf {
+    pub fn from_cnf(cnf: &Cnf) -> Result<Qbf, String> {
+        if !cnf.xors.is_empty() {
+            return Err(String::from("XOR constraints are not supported in QBF"));
+        }
+        let prefix_max = cnf.prefix.iter().flat_map(|(_, block)| block.iter()).copied().max().unwrap_or(0);
+        let variables = cnf.max_variable().max(cnf.declared_variables.unwrap_or(0)).max(prefix_max);
+        let mut blocks: Vec<(bool, Vec<u32>)> = Vec::new();
+        let mut quantified = BTreeSet::new();
+        for (universal, block) in cnf.prefix.iter() {
+            for v in block.iter() {
+                if !quantified.insert(*v) {
+                    return Err(format!("variable {} is quantified twice", v));
+                }
+            }
+            match blocks.last_mut() {
+                Some((u, vars)) if u == universal => vars.extend(block.iter()),
+                _ => blocks.push((*universal, block.clone())),
+            }
+        }
+        let free: Vec<u32> = (1..=variables).filter(|v| !quantified.contains(v)).collect();
+        if !free.is_empty() {
+            match blocks.first_mut() {
+                Some((false, vars)) => vars.extend(free),
+                _ => blocks.insert(0, (false, free)),
+            }
+        }
+        if blocks.len() > 2 {
+            return Err(format!(
+                "the prefix has {} quantifier blocks (free variables count as an outer existential one); only 2QBF is supported",
+                blocks.len()
+            ));
+        }
+        let mut blocks = blocks.into_iter();
+        // the inner block is all the variables not in the outer one
+        let (universal, outer) = blocks.next().unwrap_or((false, Vec::new()));
+        // without repeated literals and tautologies
+        let mut clauses = Vec::new();
+        for clause in cnf.clauses.iter() {
+            let mut clause = clause.clone();
+            clause.sort_by_key(|l| (l.unsigned_abs(), *l));
+            clause.dedup();
+            if clause.windows(2).all(|w| w[0] != -w[1]) {
+                clauses.push(clause);
+            }
+        }
+        return Ok(Qbf {
+            universal,
+            outer,
+            clauses,
+            variables,
+        });
+    }
+}
+
+/// The truth of a 2QBF, with a certificate: the values of the outer block
+/// that make ∃X ∀Y φ true, or ∀X ∃Y φ false.
+pub struct QbfResult {
+    pub truth: bool,
+    pub certificate: Option<Model>,
+    pub iterations: u64,
+    pub profiler: SolverProfiler,
+}
+
+pub struct SolveQbf<'a> {
+    pub qbf: &'a Qbf,
+    pub use_bcp: bool,
+    pub seed: u64,
+}
+
+impl HeuristicsVisitor for SolveQbf<'_> {
+    type Output = QbfResult;
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> QbfResult {
+        let solver = || {
+            let mut h = H::with_options(options.clone());
+            h.set_use_bcp(self.use_bcp);
+            h.set_seed(self.seed);
+            let mut solver = Solver::new(Problem::new(), h);
+            // every variable of the formula exists in both solvers, so the
+            // fresh ones come after them
+            for _ in 0..self.qbf.variables {
+                solver.add_variable();
+            }
+            solver
+        };
+        let mut cegar = Cegar {
+            qbf: self.qbf,
+            outer: self.qbf.outer.iter().copied().collect(),
+            abstraction: solver(),
+            check: solver(),
+            iterations: 0,
+        };
+        let (truth, certificate) = cegar.solve();
+        let mut profiler = cegar.abstraction.profiler;
+        profiler.merge(&cegar.check.profiler);
+        return QbfResult {
+            truth,
+            certificate,
+            iterations: cegar.iterations,
+            profiler,
+        };
+    }
+}
+
+struct Cegar<'a, H: Heuristics> {
+    qbf: &'a Qbf,
+    outer: BTreeSet<u32>,
+    // over the outer block, and selectors
+    abstraction: Solver<H>,
+    // φ, or its negation, over all variables
+    check: Solver<H>,
+    iterations: u64,
+}
+
+impl<H: Heuristics> Cegar<'_, H> {
+    // the values of the outer block in x, false where it has none
+    fn certificate(&self, x: &Model) -> Model {
+        return self.qbf.outer.iter().map(|v| (*v, x.get(v).copied().unwrap_or(false))).collect();
+    }
+
+    fn solve(&mut self) -> (bool, Option<Model>) {
+        let interrupt = AtomicBool::new(false);
+        if self.qbf.clauses.iter().any(|c| c.is_empty()) {
+            // false for any x
+            return (false, self.qbf.universal.then(|| self.certificate(&Model::new())));
+        }
+        if self.qbf.universal {
+            for clause in self.qbf.clauses.iter() {
+                self.check.add_clause(&literals(clause));
+            }
+        } else {
+            // φ is false iff a selected clause has all its literals false
+            let mut selectors = Vec::new();
+            for clause in self.qbf.clauses.iter() {
+                let s = fresh_literal(&mut self.check);
+                for l in clause.iter() {
+                    self.check.add_clause(&[!s, !Literal::from_dimacs(*l)]);
+                }
+                selectors.push(s);
+            }
+            if selectors.is_empty() {
+                // the empty matrix is true
+                return (true, Some(self.certificate(&Model::new())));
+            }
+            self.check.add_clause(&selectors);
+            // the clauses over the outer block alone hold for any y
+            for clause in self.qbf.clauses.iter() {
+                if clause.iter().all(|l| self.outer.contains(&l.unsigned_abs())) {
+                    self.abstraction.add_clause(&literals(clause));
+                }
+            }
+        }
+
+        loop {
+            self.iterations += 1;
+            let Some(candidate) = self.abstraction.solve(&[], &interrupt) else {
+                // no x is left: ∃X ∀Y φ is false, ∀X ∃Y φ true
+                return (self.qbf.universal, None);
+            };
+            let x: Model = crate::enumerate::model(&candidate)
+                .into_iter()
+                .filter(|(v, _)| self.outer.contains(v))
+                .collect();
+            let assumptions: Vec<Literal> = self
+                .qbf
+                .outer
+                .iter()
+                .map(|v| Literal::from_dimacs(if x.get(v).copied().unwrap_or(false) { *v as i32 } else { -(*v as i32) }))
+                .collect();
+            info!(target: "qbf", "iteration {}: candidate {:?}", self.iterations, x);
+            let Some(answer) = self.check.solve(&assumptions, &interrupt) else {
+                // x is a certificate for ∃X ∀Y φ, a counterexample to ∀X ∃Y φ
+                return (!self.qbf.universal, Some(self.certificate(&x)));
+            };
+            let y = crate::enumerate::model(&answer);
+            // the clauses y does not satisfy, over the outer block
+            let value = |l: i32| y.get(&l.unsigned_abs()).copied().unwrap_or(false) == (l > 0);
+            let mut restricted = Vec::new();
+            for clause in self.qbf.clauses.iter() {
+                let inner = clause.iter().filter(|l| !self.outer.contains(&l.unsigned_abs()));
+                if inner.clone().any(|l| value(*l)) {
+                    continue;
+                }
+                let outer: Vec<i32> = clause.iter().copied().filter(|l| self.outer.contains(&l.unsigned_abs())).collect();
+                restricted.push(outer);
+            }
+            if self.qbf.universal {
+                self.refine_universal(&restricted);
+                if restricted.is_empty() {
+                    // y satisfies φ whatever x is
+                    return (true, None);
+                }
+            } else if restricted.iter().any(|c| c.is_empty()) {
+                // y falsifies φ whatever x is
+                return (false, None);
+            } else {
+                for clause in restricted.iter() {
+                    self.abstraction.add_clause(&literals(clause));
+                }
+            }
+        }
+    }
+
+    // The next x must falsify one of the clauses of φ(X, y).
+    fn refine_universal(&mut self, restricted: &[Vec<i32>]) {
+        if restricted.is_empty() {
+            return;
+        }
+        let mut selectors = Vec::new();
+        for clause in restricted.iter() {
+            let s = fresh_literal(&mut self.abstraction);
+            for l in clause.iter() {
+                self.abstraction.add_clause(&[!s, !Literal::from_dimacs(*l)]);
+            }
+            selectors.push(s);
+        }
+        self.abstraction.add_clause(&selectors);
+    }
+}
+
+fn literals(clause: &[i32]) -> Vec<Literal> {
+    clause.iter().map(|l| Literal::from_dimacs(*l)).collect()
+}
+
+fn fresh_literal(solver: &mut Solver<impl Heuristics>) -> Literal {
+    Literal {
+        variable: solver.add_variable(),
+        polarity: Polarity::On,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::registry::{self, HeuristicsConfig, HEURISTICS};
+    use crate::rng::Rng;
+    use crate::testing::temp_file;
+    use crate::verify;
+
+    fn read(text: &str) -> Result<Qbf, String> {
+        let path = temp_file("qbf.qdimacs", text);
+        let cnf = verify::read_cnf(&path);
+        std::fs::remove_file(&path).unwrap();
+        return Qbf::from_cnf(&cnf?);
+    }
+
+    // whether the clauses hold under the values of `mask`, bit v-1 for v
+    fn holds(qbf: &Qbf, mask: u32) -> bool {
+        return qbf.clauses.iter().all(|c| c.iter().any(|l| (mask >> (l.unsigned_abs() - 1) & 1 == 1) == (*l > 0)));
+    }
+
+    // The truth of the formula by trying every assignment, checking the
+    // answer and certificate of the solver under every heuristics.
+    fn solve(text: &str) -> bool {
+        let qbf = read(text).unwrap();
+        let outer: u32 = qbf.outer.iter().map(|v| 1 << (v - 1)).sum();
+        let all = (1u32 << qbf.variables) - 1;
+        // the assignments of the inner block that go with those of the outer
+        let extensions = |x: u32| (0..=all).filter(move |mask| mask & outer == x);
+        let outers: Vec<u32> = (0..=all).filter(|mask| mask & outer == *mask).collect();
+        let truth = if qbf.universal {
+            outers.iter().all(|x| extensions(*x).any(|mask| holds(&qbf, mask)))
+        } else {
+            outers.iter().any(|x| extensions(*x).all(|mask| holds(&qbf, mask)))
+        };
+        for entry in HEURISTICS.iter() {
+            let visitor = SolveQbf { qbf: &qbf, use_bcp: true, seed: 0 };
+            let result = registry::dispatch(entry.name, &HeuristicsConfig::default(), visitor).unwrap();
+            assert_eq!(result.truth, truth, "{}: {}", entry.name, text);
+            // a certificate of ∃X ∀Y φ being true or ∀X ∃Y φ false
+            assert_eq!(result.certificate.is_some(), truth != qbf.universal, "{}: {}", entry.name, text);
+            if let Some(certificate) = &result.certificate {
+                let x: u32 = certificate.iter().filter(|(_, value)| **value).map(|(v, _)| 1 << (v - 1)).sum();
+                assert_eq!(x & outer, x, "{}: {}", entry.name, text);
+                assert!(extensions(x).all(|mask| holds(&qbf, mask) != qbf.universal), "{}: {}", entry.name, text);
+            }
+        }
+        return truth;
+    }
+
+    #[test]
+    fn small_formulas_have_their_truth() {
+        // x can copy y, or be its negation, but not both
+        assert!(solve("p cnf 2 2\na 2 0\ne 1 0\n1 -2 0\n-1 2 0\n"));
+        assert!(!solve("p cnf 2 2\ne 1 0\na 2 0\n1 -2 0\n-1 2 0\n"));
+        // free variables are existential and outermost
+        assert!(solve("p cnf 2 1\na 2 0\n1 2 0\n"));
+        assert!(!solve("p cnf 2 1\na 2 0\n2 0\n"));
+        // a variable in no clause, and no prefix at all
+        assert!(solve("p cnf 3 1\na 3 0\ne 1 2 0\n1 -2 0\n"));
+        assert!(!solve("p cnf 1 2\n1 0\n-1 0\n"));
+        // blocks of the same quantifier are merged
+        assert!(!solve("p cnf 3 1\na 1 0\na 2 0\ne 3 0\n1 2 0\n"));
+    }
+
+    #[test]
+    fn only_two_blocks_are_supported() {
+        assert!(read("p cnf 3 1\ne 1 0\na 2 0\ne 3 0\n1 2 3 0\n").is_err());
+        assert!(read("p cnf 3 1\na 1 0\ne 2 0\n3 0\n").is_err());
+        assert!(read("p cnf 2 1\ne 1 2 0\na 2 0\n1 2 0\n").is_err());
+    }
+
+    #[test]
+    fn random_formulas_have_their_truth() {
+        let mut rng = Rng::new(0);
+        let mut truths = [0; 2];
+        for _ in 0..200 {
+            let n = 2 + rng.below(5) as u32;
+            let mut variables: Vec<u32> = (1..=n).collect();
+            rng.shuffle(&mut variables);
+            let split = 1 + rng.below(n as u64 - 1) as usize;
+            let (first, second) = if rng.below(2) == 0 { ('e', 'a') } else { ('a', 'e') };
+            let clauses = 1 + rng.below(2 * n as u64);
+            let mut text = format!("p cnf {} {}\n", n, clauses);
+            for (q, block) in [(first, &variables[..split]), (second, &variables[split..])] {
+                let block: Vec<String> = block.iter().map(|v| v.to_string()).collect();
+                text += &format!("{} {} 0\n", q, block.join(" "));
+            }
+            for _ in 0..clauses {
+                rng.shuffle(&mut variables);
+                let clause: Vec<String> = variables[..1 + rng.below(3.min(n as u64)) as usize]
+                    .iter()
+                    .map(|v| format!("{}{}", if rng.below(2) == 0 { "" } else { "-" }, v))
+                    .collect();
+                text += &format!("{} 0\n", clause.join(" "));
+            }
+            truths[solve(&text) as usize] += 1;
+        }
+        // both answers are tried
+        assert!(truths[0] > 0 && truths[1] > 0, "{:?}", truths);
+    }
+}
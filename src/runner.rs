@@ -0,0 +1,118 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::HeuristicsVisitor;
+use crate::parser;
+use crate::profiler::{Phase, SolverProfiler};
+use crate::sat_solver::*;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// Runs the solver once on a file under a wall-clock limit, for the tuning and
+// benchmarking harnesses. The limit is enforced by a watchdog thread raising
+// the same interrupt flag the portfolio uses.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Sat,
+    Unsat,
+    Timeout,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Sat => "SAT",
+            Outcome::Unsat => "UNSAT",
+            Outcome::Timeout => "TIMEOUT",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RunResult {
+    pub outcome: Outcome,
+    pub profiler: SolverProfiler,
+}
+
+impl RunResult {
+    /// Penalised run time: the solving time, or `penalty` times the time limit
+    /// for a run that did not finish (2 for PAR-2).
+    pub fn par(&self, timeout: Duration, penalty: f64) -> f64 {
+        match self.outcome {
+            Outcome::Timeout => penalty * timeout.as_secs_f64(),
+            _ => self.profiler.duration().as_secs_f64(),
+        }
+    }
+}
+
+pub struct Run<'a> {
+    pub input: &'a String,
+    pub use_bcp: bool,
+    pub seed: u64,
+    pub timeout: Duration,
+}
+
+impl HeuristicsVisitor for Run<'_> {
+    type Output = RunResult;
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> RunResult {
+        let mut h = H::with_options(options);
+        h.set_use_bcp(self.use_bcp);
+        h.set_seed(self.seed);
+        let mut profiler = SolverProfiler::new();
+        profiler.begin_phase(Phase::Parse);
+        let mut problem = parser::parse(self.input, &mut h);
+        profiler.end_phase(Phase::Parse);
+        let interrupt = AtomicBool::new(false);
+        let (done, watchdog) = mpsc::channel::<()>();
+
+        let timeout = self.timeout;
+        let interrupt = &interrupt;
+        let solution = thread::scope(|s| {
+            s.spawn(move || {
+                // the sender is dropped once the search returns
+                if watchdog.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+                    interrupt.store(true, Ordering::Relaxed);
+                }
+            });
+            profiler.reset_start_time();
+            let solution = dpll::dpll(&mut problem, &mut h, &mut profiler, interrupt);
+            profiler.calc_duration_till_now();
+            drop(done);
+            solution
+        });
+
+        let outcome = match solution {
+            Some(_) => Outcome::Sat,
+            None if interrupt.load(Ordering::Relaxed) => Outcome::Timeout,
+            None => Outcome::Unsat,
+        };
+        RunResult { outcome, profiler }
+    }
+}
+
+/// The `.cnf` files of `dir`, sorted by name.
+pub fn list_instances(dir: &str) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    let mut files: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|x| x == "cnf"))
+        .map(|p| p.display().to_string())
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("{}: no .cnf files", dir));
+    }
+    return Ok(files);
+}
+
+/// The limit of `--timeout`, which must be a positive number of seconds.
+pub fn timeout(secs: f64) -> Result<Duration, String> {
+    if !(secs.is_finite() && secs > 0.0) {
+        return Err(format!("--timeout must be a positive number of seconds, got {}", secs));
+    }
+    return Ok(Duration::from_secs_f64(secs));
+}
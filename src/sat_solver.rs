@@ -1,12 +1,27 @@
 use core::fmt;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::rc::Rc;
 
 // impl of DPLL algorithm
 pub mod dpll;
 
+// solving the same problem repeatedly under assumptions
+pub mod incremental;
+
+// re-applying a recorded trace to a Problem
+pub mod replay;
+
+// the implication graph at a conflict, as Graphviz DOT
+pub mod implication_graph;
+
+// XOR constraints, propagated by Gauss-Jordan elimination
+pub mod xor;
+
+// cardinality constraints, propagated with k+1 watched literals
+pub mod cardinality;
+
 // impl of data structure methods
 mod sat_structures;
 // pub use sat_structures::get_sample_problem;
@@ -83,8 +98,10 @@ pub enum BCPSubstituteWatchLiteralResult {
 pub struct Problem {
     // The benefit of using BTreeMap instead of a HashMap: when debug-printing
     // the contents of the former, entries are sorted in a human-friendly way.
-    pub list_of_variables: HashMap<Variable, VariableState>,
-    pub list_of_literal_infos: HashMap<Literal, Rc<RefCell<LiteralInfo>>>,
+    // Iterating over them also visits the entries in the same order on every
+    // run, which keeps runs reproducible.
+    pub list_of_variables: BTreeMap<Variable, VariableState>,
+    pub list_of_literal_infos: BTreeMap<Literal, Rc<RefCell<LiteralInfo>>>,
     pub list_of_clauses: Vec<Rc<RefCell<Clause>>>,
 
     // This container contains (reference to) clauses that need to have their
@@ -99,8 +116,22 @@ pub struct Problem {
     // is certainly not Unsatisfiable.
     // Corollary: This list must be empty when the solver declares SAT.
     pub list_of_clauses_to_check: BTreeSet<Rc<RefCell<Clause>>>,
+
+    // XOR and cardinality constraints, propagated apart from the clauses.
+    // Their explanations are added to list_of_clauses as they are needed.
+    pub xor_constraints: xor::XorStore,
+    pub cardinality_constraints: cardinality::CardinalityStore,
+
+    // ids of the explanation clauses added so far, by their sorted literals,
+    // so the same explanation is added once
+    pub explanations: BTreeMap<Vec<Literal>, u32>,
 }
 
+/// A literal implied by an XOR or cardinality constraint, with its
+/// explanation clause, which contains it and whose other literals are all
+/// false.
+pub type Implication = (Literal, Vec<Literal>);
+
 ////////////////////////////////////////////////////////
 // Data structures for the SAT Solution
 ////////////////////////////////////////////////////////
@@ -116,6 +147,8 @@ pub struct Assignment {
 pub struct SolutionStep {
     pub assignment: Assignment,
     assignment_type: SolutionStepType,
+    // id of the clause that forced this assignment (BCP and unit clauses)
+    reason: Option<u32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -128,6 +161,8 @@ pub enum SolutionStepType {
     ForcedAtBCP,
     // forced due to it belonging to a unit clause
     ForcedAtInit,
+    // forced because the caller asked to solve under this assumption
+    Assumed,
 }
 
 #[derive(Debug)]
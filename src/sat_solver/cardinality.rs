@@ -0,0 +1,133 @@
+use super::*;
+
+// Cardinality constraints "at least k of these literals are true", kept out of
+// the clause list like the XOR constraints: the plain CNF encoding of one
+// takes a clause per subset of n-k+1 literals.
+//
+// Propagation watches k+1 literals of every constraint. While they are all
+// not false the constraint can neither imply anything nor be in conflict.
+// When a watched literal becomes false, a literal that is not false takes its
+// place; once there is none, the watched literals that are not false must all
+// be true, and fewer than k of them is a conflict. The reason for an
+// implication is the clause of the implied literal and the false literals of
+// the constraint; a conflict is explained by n-k+1 of its false literals.
+
+#[derive(Debug)]
+pub struct CardinalityConstraint {
+    // distinct variables; the first min(k+1, n) literals are watched
+    pub literals: Vec<Literal>,
+    // at least this many literals are true, 1 < k <= n
+    pub bound: usize,
+}
+
+impl CardinalityConstraint {
+    fn watched(&self) -> usize {
+        (self.bound + 1).min(self.literals.len())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CardinalityStore {
+    pub constraints: Vec<CardinalityConstraint>,
+    // the constraints watching each literal
+    watches: BTreeMap<Literal, Vec<usize>>,
+    // constraints with a watched literal that became false since they were
+    // last examined
+    pub to_check: BTreeSet<usize>,
+}
+
+impl CardinalityStore {
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    /// Adds the constraint that at least `bound` of `literals` are true. The
+    /// literals must have distinct variables. The constraint is examined at
+    /// the next propagation, as it may imply all its literals right away.
+    pub fn add(&mut self, literals: &[Literal], bound: usize) {
+        self.to_check.insert(self.constraints.len());
+        let c = CardinalityConstraint {
+            literals: literals.to_vec(),
+            bound,
+        };
+        for l in c.literals[..c.watched()].iter() {
+            self.watches.entry(*l).or_default().push(self.constraints.len());
+        }
+        self.constraints.push(c);
+    }
+
+    /// Called when `l` becomes false.
+    pub fn literal_falsified(&mut self, l: Literal) {
+        if let Some(constraints) = self.watches.get(&l) {
+            self.to_check.extend(constraints.iter());
+        }
+    }
+
+    /// Examines the constraints in `to_check` under the literal states given
+    /// by `status`, until one of them implies something. Returns its
+    /// implied literals, or the explanation of a conflict.
+    pub fn propagate(&mut self, status: impl Fn(Literal) -> LiteralState) -> Result<Vec<Implication>, Vec<Literal>> {
+        while let Some(i) = self.to_check.pop_first() {
+            let c = &mut self.constraints[i];
+            let watched = c.watched();
+            let mut moved = Vec::new();
+            let mut next = watched;
+            for w in 0..watched {
+                if status(c.literals[w]) != LiteralState::Unsat {
+                    continue;
+                }
+                while next < c.literals.len() && status(c.literals[next]) == LiteralState::Unsat {
+                    next += 1;
+                }
+                if next == c.literals.len() {
+                    break;
+                }
+                moved.push((c.literals[w], c.literals[next]));
+                c.literals.swap(w, next);
+                next += 1;
+            }
+
+            let open: Vec<Literal> =
+                c.literals[..watched].iter().copied().filter(|l| status(*l) != LiteralState::Unsat).collect();
+            let falsified: Vec<Literal> =
+                c.literals.iter().copied().filter(|l| status(*l) == LiteralState::Unsat).collect();
+            let unassigned: Vec<Literal> =
+                open.iter().copied().filter(|l| status(*l) == LiteralState::Unknown).collect();
+            let (n, bound) = (c.literals.len(), c.bound);
+            for (old, new) in moved {
+                self.watches.get_mut(&old).unwrap().retain(|c| *c != i);
+                self.watches.entry(new).or_default().push(i);
+            }
+
+            if open.len() < bound {
+                return Err(falsified[..n - bound + 1].to_vec());
+            }
+            if open.len() == bound && !unassigned.is_empty() {
+                let implied = unassigned
+                    .into_iter()
+                    .map(|l| {
+                        let mut clause = falsified.clone();
+                        clause.push(l);
+                        (l, clause)
+                    })
+                    .collect();
+                return Ok(implied);
+            }
+        }
+        return Ok(vec![]);
+    }
+
+    /// Without BCP: the explanation of a constraint with too many false
+    /// literals, if there is one.
+    pub fn find_conflict(&mut self, status: impl Fn(Literal) -> LiteralState) -> Option<Vec<Literal>> {
+        self.to_check.clear();
+        for c in self.constraints.iter() {
+            let falsified: Vec<Literal> =
+                c.literals.iter().copied().filter(|l| status(*l) == LiteralState::Unsat).collect();
+            if falsified.len() > c.literals.len() - c.bound {
+                return Some(falsified[..c.literals.len() - c.bound + 1].to_vec());
+            }
+        }
+        return None;
+    }
+}
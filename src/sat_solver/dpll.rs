@@ -1,15 +1,37 @@
-use crate::{heuristics::heuristics::Heuristics, profiler::SolverProfiler};
+use crate::heuristics::heuristics::Heuristics;
+use crate::profiler::{Phase, SolverProfiler};
+use crate::trace::Event;
 
 use super::*;
 use log::{info, trace};
 use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tailcall::tailcall;
 
-// If the problem is UNSAT, we will return None
+// If the problem is UNSAT, we will return None.
+// The search is abandoned (also returning None) as soon as `interrupt` is
+// raised, e.g. by another portfolio worker; callers that share the flag must
+// check it before trusting a None.
 pub fn dpll(
-    mut p: &mut Problem,
+    p: &mut Problem,
     h: &mut impl Heuristics,
     prof: &mut SolverProfiler,
+    interrupt: &AtomicBool,
+) -> Option<SolutionStack> {
+    dpll_with_assumptions(p, h, prof, interrupt, &[])
+}
+
+// Same as dpll, but the literals in `assumptions` are forced before any free
+// choice is made. They are never flipped during backtracking, so None means
+// the problem is UNSAT under these assumptions.
+// Variables of assumptions that appear in no clause are registered with the
+// problem and the heuristics first.
+pub fn dpll_with_assumptions(
+    p: &mut Problem,
+    h: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+    interrupt: &AtomicBool,
+    assumptions: &[Literal],
 ) -> Option<SolutionStack> {
     let mut solution = SolutionStack { stack: vec![] };
 
@@ -29,50 +51,140 @@ pub fn dpll(
     // 4. Repeat
     // Resolve all variables before we return a solution
 
-    let ret = force_assignment_for_unit_clauses(&mut p, &mut solution, h, prof);
+    // an assumption may be on a variable that is in no clause (yet)
+    let mut unknown: Vec<Variable> =
+        assumptions.iter().map(|l| l.variable).filter(|v| !p.list_of_variables.contains_key(v)).collect();
+    unknown.sort();
+    unknown.dedup();
+    if !unknown.is_empty() {
+        trace!(target: "dpll", "registering the assumed variables {:?}", unknown);
+        let variables = p.add_free_variables(&unknown);
+        h.add_parsed_clause(&variables);
+    }
+
+    prof.begin_phase(Phase::Preprocess);
+    let ret = force_assignment_for_unit_clauses(p, &mut solution, h, prof, assumptions);
+    prof.end_phase(Phase::Preprocess);
     if !ret {
         return None;
     }
     trace!(target: "dpll", "solution stack: {:?}", solution);
 
+    prof.begin_phase(Phase::Search);
+    let found = search(p, h, prof, interrupt, &mut solution);
+    prof.end_phase(Phase::Search);
+    if !found {
+        return None;
+    }
+    Some(solution)
+}
+
+// Calls `on_model` with every model of the problem, until it returns false.
+// Models are enumerated in the order of the chronological backtracking: once
+// a model is found, the last free choice not flipped yet is flipped, as if
+// the model were a conflict, and the search goes on from there. No clause is
+// added, so every model is found exactly once.
+// Returns the number of models passed to `on_model`.
+pub fn enumerate(
+    p: &mut Problem,
+    h: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+    interrupt: &AtomicBool,
+    mut on_model: impl FnMut(&SolutionStack) -> bool,
+) -> u64 {
+    let mut solution = SolutionStack { stack: vec![] };
+    prof.begin_phase(Phase::Preprocess);
+    let ret = force_assignment_for_unit_clauses(p, &mut solution, h, prof, &[]);
+    prof.end_phase(Phase::Preprocess);
+    if !ret {
+        return 0;
+    }
+
+    prof.begin_phase(Phase::Search);
+    let mut models = 0;
+    while search(p, h, prof, interrupt, &mut solution) {
+        models += 1;
+        if !on_model(&solution) {
+            break;
+        }
+        trace!(target: "dpll", "model {} found, looking for the next one", models);
+        if !backtrack(p, &mut solution, h, prof) || !propagate(p, &mut solution, h, prof, interrupt) {
+            break;
+        }
+    }
+    prof.end_phase(Phase::Search);
+    models
+}
+
+// Steps 1 to 4 of dpll, after the unit clauses and assumptions are assigned.
+// Returns true with a model in `solution`.
+fn search(
+    p: &mut Problem,
+    h: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+    interrupt: &AtomicBool,
+    solution: &mut SolutionStack,
+) -> bool {
+
     while let Some(Literal {
         variable: var,
         polarity: pol,
-    }) = h.decide()
+    }) = h.decide_with_problem(p)
     {
+        if interrupt.load(Ordering::Relaxed) {
+            info!(target: "dpll", "interrupted");
+            return false;
+        }
         solution.push_free_choice_first_try(var, pol);
+        prof.note_decision_level(solution.decision_level());
+        prof.record(Event::Decide(Literal { variable: var, polarity: pol }));
         trace!(target: "dpll", "Assigning variable {:?}", var);
         trace!(target: "dpll", "solution stack: {:?}", solution);
 
         h.assign_variable(var);
-        mark_variable_assigned(&mut p, var);
-        update_literal_info(&mut p, var, pol, UpdateLiteralInfoCause::FreeAssignment, h);
+        mark_variable_assigned(p, var);
+        update_literal_info(p, var, pol, UpdateLiteralInfoCause::FreeAssignment, h);
         prof.bump_free_decisions();
+        prof.tick(solution.stack.len(), p.list_of_variables.len());
 
         // sanity check
         // panic_if_incoherent(&p, &solution);
-        if h.use_bcp() {
-            while !boolean_constraint_propagation(&mut p, &mut solution, h, prof) {
-                let resolved_all_conflicts =
-                    update_clause_state_and_resolve_conflict(&mut p, &mut solution, h, prof);
-                if !resolved_all_conflicts {
-                    return None;
-                }
-            }
-            trace!(target: "bcp", "No more implications");
-        } else {
-            let resolved_all_conflicts =
-                update_clause_state_and_resolve_conflict(&mut p, &mut solution, h, prof);
-            if !resolved_all_conflicts {
-                return None;
-            }
-            trace!(target: "dpll", "All conflicts cleared.")
+        if !propagate(p, solution, h, prof, interrupt) {
+            return false;
         }
     }
 
     info!(target: "dpll", "all variables are assigned");
 
-    Some(solution)
+    true
+}
+
+// Step 3 of dpll after an assignment: BCP (or, without BCP, checking the
+// affected clauses) and backtracking out of every conflict. Returns false if
+// the problem is UNSAT or the search was interrupted.
+fn propagate(
+    p: &mut Problem,
+    solution: &mut SolutionStack,
+    h: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+    interrupt: &AtomicBool,
+) -> bool {
+    if h.use_bcp() {
+        while !boolean_constraint_propagation(p, solution, h, prof) {
+            let resolved_all_conflicts = update_clause_state_and_resolve_conflict(p, solution, h, prof);
+            if !resolved_all_conflicts || interrupt.load(Ordering::Relaxed) {
+                return false;
+            }
+        }
+        trace!(target: "bcp", "No more implications");
+    } else {
+        let resolved_all_conflicts = update_clause_state_and_resolve_conflict(p, solution, h, prof);
+        if !resolved_all_conflicts {
+            return false;
+        }
+        trace!(target: "dpll", "All conflicts cleared.")
+    }
+    true
 }
 
 ////////////////////////////////////////////////////////
@@ -80,48 +192,63 @@ pub fn dpll(
 ////////////////////////////////////////////////////////
 
 /// Called once right after reading the problem from file. Aims to identify unit
-/// clauses and give them an assignment.
+/// clauses and give them an assignment. Assumptions (if any) are assigned in
+/// the same pass.
 /// This is function is necessary because the two-watch-variable algorithm used
 /// for BCP requires each clause to have at least two variables.  
 /// Returns true if no conflict occur during the call.
 ///
 /// Overview
 /// 1. Scan all clauses to hunt for unit clauses
-/// 1.1 Take note of variables and polarities to force-assign
-/// 1.2 Make sure no variable is forced to be both On and Off
+///    1.1 Take note of variables and polarities to force-assign
+///    1.2 Make sure no variable is forced to be both On and Off
 /// 2. Assign one variable at a time, performing BCP and conflict resolution
 ///    along the way
-/// 2.1 This step is much like how freely-assigned variables are handled.  
+///    2.1 This step is much like how freely-assigned variables are handled.
 pub fn force_assignment_for_unit_clauses(
     problem: &mut Problem,
     solution: &mut SolutionStack,
     heuristics: &mut impl Heuristics,
     prof: &mut SolverProfiler,
+    assumptions: &[Literal],
 ) -> bool {
+    // An empty clause can never be satisfied
+    if let Some(rc) = problem.list_of_clauses.iter().find(|rc| rc.borrow().list_of_literals.is_empty()) {
+        trace!(target: "unit_clause", "Clause {} is empty", rc.borrow().id);
+        prof.record(Event::Conflict(Some(rc.borrow().id)));
+        return false;
+    }
+
     // Go over all clauses, hunt for those that have only one literal
     let it_literals_to_force = problem
         .list_of_clauses
         .iter()
         .filter(|rc| rc.borrow().list_of_literals.len() == 1)
-        .map(|rc| rc.borrow().list_of_literals[0]);
+        .map(|rc| {
+            let c = rc.borrow();
+            (c.list_of_literals[0], SolutionStepType::ForcedAtInit, Some(c.id))
+        });
+
+    let it_assumptions = assumptions.iter().map(|l| (*l, SolutionStepType::Assumed, None));
 
-    let mut _temp_assignment_map = BTreeMap::<Variable, Polarity>::new();
+    let mut _temp_assignment_map = BTreeMap::<Variable, (Polarity, SolutionStepType, Option<u32>)>::new();
     let mut ret = true;
-    for l in it_literals_to_force {
+    for (l, ass_type, reason) in it_assumptions.chain(it_literals_to_force) {
         let this_v = l.variable;
         let this_p = l.polarity;
 
         match _temp_assignment_map.get(&this_v) {
-            Some(p) => {
+            Some((p, _, _)) => {
                 if *p != this_p {
                     // conflict!
                     trace!(target: "unit_clause", "Variable {:?} appeared with both polarities in various unit clauses", this_v);
+                    prof.record(Event::Conflict(reason));
                     ret = false;
                     break;
                 }
             }
             None => {
-                _temp_assignment_map.insert(this_v, this_p);
+                _temp_assignment_map.insert(this_v, (this_p, ass_type, reason));
                 trace!(target: "unit_clause", "Variable {:?} implied to be {:?}", this_v, this_p);
             }
         }
@@ -131,16 +258,32 @@ pub fn force_assignment_for_unit_clauses(
         return false;
     }
 
-    while let Some((ass_v, ass_p)) = _temp_assignment_map.pop_first() {
+    while let Some((ass_v, (ass_p, ass_type, reason))) = _temp_assignment_map.pop_first() {
         // it's possible a variable has already been implied during the BCP
         // phase
         if problem.list_of_variables[&ass_v] == VariableState::Assigned {
             trace!(target:"unit_clause", "Variable {:?} was already assigned", ass_v);
             trace!(target: "unit_clause", "solution stack: {:?}", solution);
+            // A unit clause contradicting the implied value is caught by BCP
+            // (the clause itself becomes UNSAT), but an assumption has no
+            // clause backing it.
+            let contradicted = solution.stack.iter().any(|step| {
+                step.assignment.variable == ass_v && step.assignment.polarity != ass_p
+            });
+            if ass_type == SolutionStepType::Assumed && contradicted {
+                trace!(target: "unit_clause", "Assumption on {:?} contradicts an implied value", ass_v);
+                prof.record(Event::Conflict(None));
+                return false;
+            }
             continue;
         }
 
-        solution.push_step(ass_v, ass_p, SolutionStepType::ForcedAtInit);
+        solution.push_step(ass_v, ass_p, ass_type, reason);
+        let l = Literal { variable: ass_v, polarity: ass_p };
+        prof.record(match ass_type {
+            SolutionStepType::Assumed => Event::Assume(l),
+            _ => Event::Imply(l, reason),
+        });
 
         trace!(target: "unit_clause", "Assigning variable {:?}", ass_v);
         trace!(target: "unit_clause", "solution stack: {:?}", solution);
@@ -175,9 +318,50 @@ pub fn force_assignment_for_unit_clauses(
         }
     }
 
+    // the XOR and cardinality constraints may imply or contradict something
+    // without any assignment to start from
+    if !problem.xor_constraints.is_empty() || !problem.cardinality_constraints.is_empty() {
+        return propagate(problem, solution, heuristics, prof, &AtomicBool::new(false));
+    }
+
     return true;
 }
 
+/// Undoes every assignment in the problem (including those forced at init) and
+/// tells the heuristics about it, so that the same Problem can be solved again,
+/// possibly after adding clauses or under different assumptions.
+pub fn reset_assignments(problem: &mut Problem, heuristics: &mut impl Heuristics) {
+    let assigned: Vec<Variable> = problem
+        .list_of_variables
+        .iter()
+        .filter(|(_, vs)| **vs == VariableState::Assigned)
+        .map(|(v, _)| *v)
+        .collect();
+
+    for var in assigned {
+        heuristics.unassign_variable(var);
+        undo_assignment(problem, var);
+    }
+    problem.list_of_clauses_to_check.clear();
+    // with nothing assigned, every cardinality constraint is examined again
+    problem.cardinality_constraints.to_check = (0..problem.cardinality_constraints.constraints.len()).collect();
+    trace!(target: "dpll", "all assignments are reset");
+}
+
+/// Marks a variable unassigned and both of its literals Unknown. Watch
+/// literals are left alone: they stay valid when assignments are undone.
+pub fn undo_assignment(problem: &mut Problem, var: Variable) {
+    mark_variable_unassigned(problem, var);
+    for pol in [Polarity::On, Polarity::Off] {
+        if let Some(li) = problem.list_of_literal_infos.get(&Literal {
+            variable: var,
+            polarity: pol,
+        }) {
+            li.borrow_mut().status = LiteralState::Unknown;
+        }
+    }
+}
+
 pub fn mark_variable_assigned(problem: &mut Problem, v: Variable) {
     // will panic if v is not in list_of_variables
     let vs = problem.list_of_variables.get_mut(&v).unwrap();
@@ -262,6 +446,7 @@ pub fn update_literal_info(
             }
         }
         *status_ref = LiteralState::Unsat;
+        problem.cardinality_constraints.literal_falsified(opposite_pol_literal);
 
         // For the UNSAT literal, it has the potential of changing a clause's
         // state.
@@ -287,27 +472,42 @@ pub fn boolean_constraint_propagation(
     heuristics: &mut impl Heuristics,
     prof: &mut SolverProfiler,
 ) -> bool {
-    let mut implied_assignments = BTreeMap::<Variable, Polarity>::new();
+    // implied polarity and the clause implying it
+    let mut implied_assignments = BTreeMap::<Variable, (Polarity, u32)>::new();
+
+    loop {
+        if problem.list_of_clauses_to_check.is_empty() && implied_assignments.is_empty() {
+            // The clauses imply nothing more, but the other constraints may.
+            if !native_propagation(problem, solution, heuristics, prof, &mut implied_assignments) {
+                return false;
+            }
+            if implied_assignments.is_empty() {
+                break;
+            }
+        }
 
-    while problem.list_of_clauses_to_check.len() > 0 || implied_assignments.len() > 0 {
         // Examine each clause, we either find a substitute variable to watch, or
         // are forced to assign the other watch variable.
         while let Some(rc) = problem.list_of_clauses_to_check.pop_first() {
             let mut c = rc.borrow_mut();
             trace!(target: "bcp", "Examining clause {}", c.id);
+            prof.bump_propagations();
 
             let substitution_result = c.try_substitute_watch_literal(problem);
             match substitution_result {
                 BCPSubstituteWatchLiteralResult::UnitClauseUnsat => {
                     trace!(target:"bcp", "Clause {} is unit clause and UNSAT", c.id);
                     trace!(target: "bcp", "{:?}", c);
+                    heuristics.add_conflict_clause(&c);
+                    prof.bump_conflicts();
+                    prof.record(Event::Conflict(Some(c.id)));
                     return false;
                 }
                 BCPSubstituteWatchLiteralResult::ForcedAssignment { l } => {
                     let mut conflict = false;
 
                     implied_assignments.entry(l.variable)
-                        .and_modify(|p|{ // we aren't really modifying anything
+                        .and_modify(|(p, _)|{ // we aren't really modifying anything
                             if *p != l.polarity {
                                 // conflict!
                                 trace!(target: "bcp", "Clause {}: Variable {:?} implied to be both polarities", c.id, l.variable);
@@ -321,9 +521,12 @@ pub fn boolean_constraint_propagation(
                         .or_insert_with(||{
                             trace!(target: "bcp", "Clause {}: Variable {:?} implied to be {:?}", c.id, l.variable, l.polarity);
                             trace!(target: "bcp", "{:?}", c);
-                            l.polarity
+                            (l.polarity, c.id)
                         });
                     if conflict {
+                        heuristics.add_conflict_clause(&c);
+                        prof.bump_conflicts();
+                        prof.record(Event::Conflict(Some(c.id)));
                         return false;
                     }
                 }
@@ -334,8 +537,9 @@ pub fn boolean_constraint_propagation(
         // At this point, we have finished examining all clauses affected by a
         // literal assignment, but we end up with a list of more implied assignments.
         // We try those implied assignments one at a time.
-        if let Some((v, p)) = implied_assignments.pop_first() {
-            solution.push_step(v, p, SolutionStepType::ForcedAtBCP);
+        if let Some((v, (p, reason))) = implied_assignments.pop_first() {
+            solution.push_step(v, p, SolutionStepType::ForcedAtBCP, Some(reason));
+            prof.record(Event::Imply(Literal { variable: v, polarity: p }, Some(reason)));
             trace!(target: "bcp", "Assinging variable {:?}", v);
             trace!(target: "bcp", "solution stack: {:?}", solution);
 
@@ -355,9 +559,137 @@ pub fn boolean_constraint_propagation(
     return true;
 }
 
+// Propagation of the cardinality and then the XOR constraints under the
+// current assignment. The implied literals go to `implied_assignments`, with
+// their explanations as the reasons. Returns false on a conflict, after adding
+// its explanation and recording it.
+fn native_propagation(
+    problem: &mut Problem,
+    solution: &SolutionStack,
+    heuristics: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+    implied_assignments: &mut BTreeMap<Variable, (Polarity, u32)>,
+) -> bool {
+    let mut implied = Vec::new();
+    if !problem.cardinality_constraints.to_check.is_empty() {
+        prof.bump_propagations();
+        let infos = &problem.list_of_literal_infos;
+        let status = |l: Literal| infos[&l].borrow().status;
+        match problem.cardinality_constraints.propagate(status) {
+            Ok(i) => implied = i,
+            Err(explanation) => {
+                trace!(target: "cardinality", "Cardinality constraint in conflict");
+                native_conflict(problem, solution, heuristics, prof, explanation);
+                return false;
+            }
+        }
+    }
+    if implied.is_empty() && !problem.xor_constraints.is_empty() {
+        prof.bump_propagations();
+        let infos = &problem.list_of_literal_infos;
+        match problem.xor_constraints.propagate(|v| variable_value(infos, v)) {
+            Ok(i) => implied = i,
+            Err(explanation) => {
+                trace!(target: "xor", "XOR constraints in conflict");
+                native_conflict(problem, solution, heuristics, prof, explanation);
+                return false;
+            }
+        }
+    }
+    for (l, explanation) in implied {
+        let id = add_explanation(problem, solution, prof, explanation);
+        if implied_assignments.get(&l.variable).is_some_and(|(p, _)| *p != l.polarity) {
+            trace!(target: "bcp", "Clause {}: Variable {:?} implied to be both polarities", id, l.variable);
+            heuristics.add_conflict_clause(&problem.list_of_clauses[id as usize].borrow());
+            prof.bump_conflicts();
+            prof.record(Event::Conflict(Some(id)));
+            return false;
+        }
+        trace!(target: "bcp", "Variable {:?} implied to be {:?} by explanation clause {}", l.variable, l.polarity, id);
+        implied_assignments.insert(l.variable, (l.polarity, id));
+    }
+    return true;
+}
+
+// Without BCP: looks for a conflict among the cardinality and XOR constraints
+// and records it. Returns false if there is one.
+fn native_constraints_hold(
+    problem: &mut Problem,
+    solution: &SolutionStack,
+    heuristics: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+) -> bool {
+    let infos = &problem.list_of_literal_infos;
+    let mut conflict = None;
+    if !problem.cardinality_constraints.is_empty() {
+        prof.bump_propagations();
+        conflict = problem.cardinality_constraints.find_conflict(|l| infos[&l].borrow().status);
+    }
+    if conflict.is_none() && !problem.xor_constraints.is_empty() {
+        prof.bump_propagations();
+        conflict = problem.xor_constraints.propagate(|v| variable_value(infos, v)).err();
+    }
+    match conflict {
+        Some(explanation) => {
+            native_conflict(problem, solution, heuristics, prof, explanation);
+            return false;
+        }
+        None => return true,
+    }
+}
+
+fn variable_value(infos: &BTreeMap<Literal, Rc<RefCell<LiteralInfo>>>, v: Variable) -> Option<bool> {
+    match infos[&Literal { variable: v, polarity: Polarity::On }].borrow().status {
+        LiteralState::Sat => Some(true),
+        LiteralState::Unsat => Some(false),
+        LiteralState::Unknown => None,
+    }
+}
+
+// Adds the explanation of a conflict of the XOR or cardinality constraints
+// and records the conflict.
+fn native_conflict(
+    problem: &mut Problem,
+    solution: &SolutionStack,
+    heuristics: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+    explanation: Vec<Literal>,
+) {
+    let id = add_explanation(problem, solution, prof, explanation);
+    trace!(target: "bcp", "Conflict explained by clause {}", id);
+    heuristics.add_conflict_clause(&problem.list_of_clauses[id as usize].borrow());
+    prof.bump_conflicts();
+    prof.record(Event::Conflict(Some(id)));
+}
+
+// Adds an explanation of the XOR or cardinality constraints to the clauses, unless it is
+// there already, and returns its id. The literals are in the order of the
+// trail, the unassigned one last, so that the clause watches the two
+// literals assigned last.
+fn add_explanation(
+    problem: &mut Problem,
+    solution: &SolutionStack,
+    prof: &mut SolverProfiler,
+    mut literals: Vec<Literal>,
+) -> u32 {
+    let mut key = literals.clone();
+    key.sort();
+    if let Some(id) = problem.explanations.get(&key) {
+        return *id;
+    }
+    let position: BTreeMap<Variable, usize> =
+        solution.stack.iter().enumerate().map(|(i, step)| (step.assignment.variable, i)).collect();
+    literals.sort_by_key(|l| position.get(&l.variable).copied().unwrap_or(usize::MAX));
+    let id = problem.add_clause(&literals).borrow().id;
+    problem.explanations.insert(key, id);
+    prof.record(Event::Explain(literals));
+    return id;
+}
+
 /// Returns true if all conflicts (if any) were successfully resolved. Returns false if
 /// the problem is UNSAT (i.e., we have tried both the on- and off-assignment for
 /// a variable but neither works).
+#[allow(unreachable_code)]
 #[tailcall]
 pub fn update_clause_state_and_resolve_conflict(
     problem: &mut Problem,
@@ -393,19 +725,42 @@ pub fn update_clause_state_and_resolve_conflict(
 
                 // register conflict clause with heuristics
                 heuristics.add_conflict_clause(&c);
+                prof.bump_conflicts();
+                prof.record(Event::Conflict(Some(c.id)));
                 break;
             }
         }
 
-        if !found_unsat {
+        if !found_unsat && native_constraints_hold(problem, solution_stack, heuristics, prof) {
             trace!(target: "backtrack", "All conflicts resolved.");
             return true;
         }
     }
 
     // We do have a conflict. Backtrack!
-    // Find the last variable that we have not tried both polarities
     trace!(target: "backtrack", "Trying to resolve conflict.");
+    if !backtrack(problem, solution_stack, heuristics, prof) {
+        return false;
+    }
+
+    if heuristics.use_bcp() {
+        return true;
+    }
+
+    // recursively call into this function to resolve any new conflicts
+    return update_clause_state_and_resolve_conflict(problem, solution_stack, heuristics, prof);
+}
+
+/// Drops the steps after the last free choice that has not been flipped yet
+/// and flips it. Returns false if there is no such free choice, i.e. every
+/// assignment on the stack has been tried with both polarities.
+pub fn backtrack(
+    problem: &mut Problem,
+    solution_stack: &mut SolutionStack,
+    heuristics: &mut impl Heuristics,
+    prof: &mut SolverProfiler,
+) -> bool {
+    // Find the last variable that we have not tried both polarities
     let f_step_can_try_other_polarity = |step: &SolutionStep| -> bool {
         matches!(step.assignment_type, SolutionStepType::FreeChoiceFirstTry)
     };
@@ -446,6 +801,7 @@ pub fn update_clause_state_and_resolve_conflict(
                 // list_of_variables
                 mark_variable_unassigned(problem, var);
                 prof.bump_backtracked_decisions();
+                prof.record(Event::Backtrack(var));
 
                 // update the list_of_literal_infos
                 if let Some(li) = problem.list_of_literal_infos.get(&Literal {
@@ -476,6 +832,7 @@ pub fn update_clause_state_and_resolve_conflict(
         // the very assignment that caused any Clause to be added to this list
         // have been invalidated, so it's okay to just clear the worklist.
         problem.list_of_clauses_to_check.clear();
+        problem.cardinality_constraints.to_check.clear();
 
         // Reverse the polarity of the last element in the current solution
         // stack, and update list_of_literal_infos and list_of_clauses_to_check.
@@ -498,25 +855,22 @@ pub fn update_clause_state_and_resolve_conflict(
             heuristics,
         );
         prof.bump_flipped_decisions();
+        prof.record(Event::Flip(Literal { variable: var, polarity: new_pol }));
 
         trace!(target: "backtrack", "solution stack: {:?}", solution_stack);
         // panic_if_incoherent(problem, solution_stack);
 
-        if heuristics.use_bcp() {
-            return true;
-        }
-
-        // recursively call into this function to resolve any new conflicts
-        return update_clause_state_and_resolve_conflict(problem, solution_stack, heuristics, prof);
+        return true;
     }
 }
 
-///
-/// OBSOLETE METHODS BELOW
-///
+//
+// OBSOLETE METHODS BELOW
+//
 
 /// Returns a variable that is unresolved, and a recommendation for which
 /// polarity to use. If all variables have been resolved, returns None.  
+#[allow(dead_code)]
 pub fn get_one_unresolved_var(problem: &Problem) -> Option<(Variable, Polarity)> {
     // heuristic: pick an unassigned variable that appears in the most amount
     // of clauses.
@@ -557,6 +911,7 @@ pub fn get_one_unresolved_var(problem: &Problem) -> Option<(Variable, Polarity)>
 /// Obsolete
 /// Sanity check solely for debug purpose. Does there exist incoherence in the
 /// representation? If so, panic!
+#[allow(dead_code, clippy::if_same_then_else)]
 pub fn panic_if_incoherent(problem: &Problem, solution_stack: &SolutionStack) {
     // does the Problem's variable states match with the current Solution?
     solution_stack.stack.iter().for_each(|step| {
@@ -0,0 +1,202 @@
+use super::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+// The implication graph at a conflict, rendered as Graphviz DOT. Nodes are the
+// assignments on the trail that lead to the conflict, edges go from the
+// antecedents of an implied assignment to it and are labelled with the id of
+// its reason clause.
+//
+// dpll does not learn clauses, but the first UIP cut is computed the way a
+// CDCL solver would: the conflict side of the cut is drawn as a cluster, the
+// UIP is filled and the edges crossing the cut are drawn in red. The clause a
+// CDCL solver would learn is given in the graph label.
+
+struct Node {
+    literal: Literal,
+    level: u64,
+    reason: Option<u32>,
+}
+
+fn label(n: &Node) -> String {
+    let value = if n.literal.polarity == Polarity::On { 1 } else { 0 };
+    format!("{}={}@{}", n.literal.variable.index, value, n.level)
+}
+
+// the variables of `clause` other than `var`
+fn antecedents(problem: &Problem, clause: u32, var: Variable) -> Vec<Variable> {
+    problem.list_of_clauses[clause as usize]
+        .borrow()
+        .list_of_literals
+        .iter()
+        .map(|l| l.variable)
+        .filter(|v| *v != var)
+        .collect()
+}
+
+/// Builds the implication graph of the trail in `solution` at the conflict
+/// in clause `conflict`, which must be falsified by the trail, except for at
+/// most one literal whose complement BCP had implied but not yet assigned.
+pub fn to_dot(problem: &Problem, solution: &SolutionStack, conflict: u32) -> Result<String, String> {
+    // the trail with decision levels; flipped decisions open a level too
+    let mut nodes = Vec::<Node>::new();
+    let mut level = 0;
+    for step in solution.stack.iter() {
+        if matches!(
+            step.assignment_type,
+            SolutionStepType::FreeChoiceFirstTry | SolutionStepType::FreeChoiceSecondTry
+        ) {
+            level += 1;
+        }
+        nodes.push(Node {
+            literal: Literal {
+                variable: step.assignment.variable,
+                polarity: step.assignment.polarity,
+            },
+            level,
+            reason: step.reason,
+        });
+    }
+    let mut position: HashMap<Variable, usize> = nodes.iter().enumerate().map(|(i, n)| (n.literal.variable, i)).collect();
+
+    let Some(rc) = problem.list_of_clauses.get(conflict as usize) else {
+        return Err(format!("unknown clause {}", conflict));
+    };
+    let conflict_literals = rc.borrow().list_of_literals.clone();
+    let is_false = |position: &HashMap<Variable, usize>, nodes: &Vec<Node>, l: &Literal| {
+        position.get(&l.variable).is_some_and(|i| nodes[*i].literal == !*l)
+    };
+    for l in conflict_literals.iter() {
+        if position.contains_key(&l.variable) {
+            if !is_false(&position, &nodes, l) {
+                return Err(format!("clause {} is not falsified", conflict));
+            }
+            continue;
+        }
+        // The pending implication of the complement is not on the trail; its
+        // reason is a clause that forces it under the trail.
+        let pending = !*l;
+        let reason = problem.list_of_literal_infos.get(&pending).and_then(|li| {
+            li.borrow()
+                .list_of_clauses
+                .iter()
+                .map(|c| c.borrow())
+                .find(|c| c.list_of_literals.iter().all(|o| *o == pending || is_false(&position, &nodes, o)))
+                .map(|c| c.id)
+        });
+        if reason.is_none() {
+            return Err(format!("clause {} is not falsified", conflict));
+        }
+        position.insert(pending.variable, nodes.len());
+        nodes.push(Node { literal: pending, level, reason });
+    }
+
+    // only the part of the trail the conflict depends on is drawn
+    let mut cone = BTreeSet::<usize>::new();
+    let mut todo: Vec<usize> = conflict_literals.iter().map(|l| position[&l.variable]).collect();
+    while let Some(i) = todo.pop() {
+        if !cone.insert(i) {
+            continue;
+        }
+        if let Some(r) = nodes[i].reason {
+            todo.extend(antecedents(problem, r, nodes[i].literal.variable).iter().map(|v| position[v]));
+        }
+    }
+
+    // First UIP: walk the trail backwards from the conflict, resolving on the
+    // assignments of the conflict level until only one of them is left.
+    let conflict_level = cone.iter().map(|i| nodes[*i].level).max().unwrap_or(0);
+    let mut conflict_side = BTreeSet::<usize>::new();
+    let mut uip = None;
+    if conflict_level > 0 {
+        let mut seen: BTreeSet<usize> = conflict_literals.iter().map(|l| position[&l.variable]).collect();
+        let mut open = seen.iter().filter(|i| nodes[**i].level == conflict_level).count();
+        for i in (0..nodes.len()).rev() {
+            if !seen.contains(&i) || nodes[i].level != conflict_level {
+                continue;
+            }
+            if open == 1 {
+                uip = Some(i);
+                break;
+            }
+            open -= 1;
+            conflict_side.insert(i);
+            // a decision is always the last one left at its level
+            let r = nodes[i].reason.unwrap();
+            for v in antecedents(problem, r, nodes[i].literal.variable) {
+                let j = position[&v];
+                if seen.insert(j) && nodes[j].level == conflict_level {
+                    open += 1;
+                }
+            }
+        }
+    }
+    // the edges into the conflict side (and into the conflict) cross the cut
+    let crosses = |from: usize, to: Option<usize>| {
+        uip.is_some() && !conflict_side.contains(&from) && to.is_none_or(|t| conflict_side.contains(&t))
+    };
+
+    let mut dot = String::new();
+    _ = writeln!(dot, "digraph implication_graph {{");
+    _ = writeln!(dot, "  rankdir=LR;");
+    if let Some(u) = uip {
+        // the complements of the assignments the cut edges start from
+        let mut learned = BTreeSet::<i32>::new();
+        for i in conflict_side.iter().copied() {
+            for v in antecedents(problem, nodes[i].reason.unwrap(), nodes[i].literal.variable) {
+                let from = position[&v];
+                if crosses(from, Some(i)) {
+                    learned.insert((!nodes[from].literal).to_dimacs());
+                }
+            }
+        }
+        for l in conflict_literals.iter() {
+            let from = position[&l.variable];
+            if crosses(from, None) {
+                learned.insert((!nodes[from].literal).to_dimacs());
+            }
+        }
+        let learned: Vec<String> = learned.iter().map(|l| l.to_string()).collect();
+        _ = writeln!(dot, "  label=\"conflict in clause {}, 1-UIP {}, learned clause ({})\";", conflict, label(&nodes[u]), learned.join(" "));
+    } else {
+        _ = writeln!(dot, "  label=\"conflict in clause {} at level 0\";", conflict);
+    }
+
+    for i in cone.iter() {
+        let n = &nodes[*i];
+        let mut attributes = format!("label=\"{}\"", label(n));
+        if n.reason.is_none() {
+            attributes += ", shape=box";
+        }
+        if uip == Some(*i) {
+            attributes += ", style=filled, fillcolor=orange";
+        } else if *i >= solution.stack.len() {
+            // implied, but not yet on the trail
+            attributes += ", style=dashed";
+        }
+        _ = writeln!(dot, "  v{} [{}];", n.literal.variable.index, attributes);
+    }
+    _ = writeln!(dot, "  conflict [label=\"conflict\", shape=doubleoctagon, color=red, fontcolor=red];");
+    if !conflict_side.is_empty() {
+        let members: Vec<String> = conflict_side.iter().map(|i| format!("v{};", nodes[*i].literal.variable.index)).collect();
+        _ = writeln!(dot, "  subgraph cluster_conflict_side {{ label=\"conflict side\"; style=dashed; color=red; {} }}", members.join(" "));
+    }
+
+    let edge = |dot: &mut String, from: usize, to: Option<usize>, clause: u32| {
+        let target = to.map_or(String::from("conflict"), |t| format!("v{}", nodes[t].literal.variable.index));
+        let colour = if crosses(from, to) { ", color=red, penwidth=2" } else { "" };
+        _ = writeln!(dot, "  v{} -> {} [label=\"{}\"{}];", nodes[from].literal.variable.index, target, clause, colour);
+    };
+    for i in cone.iter().copied() {
+        if let Some(r) = nodes[i].reason {
+            for v in antecedents(problem, r, nodes[i].literal.variable) {
+                edge(&mut dot, position[&v], Some(i), r);
+            }
+        }
+    }
+    for l in conflict_literals.iter() {
+        edge(&mut dot, position[&l.variable], None, conflict);
+    }
+    _ = writeln!(dot, "}}");
+    return Ok(dot);
+}
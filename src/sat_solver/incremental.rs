@@ -0,0 +1,109 @@
+use crate::{heuristics::heuristics::Heuristics, profiler::SolverProfiler};
+
+use super::*;
+use std::sync::atomic::AtomicBool;
+
+/// Keeps a Problem and its heuristics alive across several solve calls, so
+/// that different assumptions can be tried without re-parsing. Heuristics
+/// state (e.g. VSIDS scores) carries over between calls.
+pub struct Solver<H: Heuristics> {
+    pub problem: Problem,
+    pub heuristics: H,
+    pub profiler: SolverProfiler,
+}
+
+impl<H: Heuristics> Solver<H> {
+    pub fn new(problem: Problem, heuristics: H) -> Self {
+        Solver {
+            problem,
+            heuristics,
+            profiler: SolverProfiler::new(),
+        }
+    }
+
+    /// Solves the current set of clauses under `assumptions`. Any assignment
+    /// left over from the previous call is undone first.
+    pub fn solve(
+        &mut self,
+        assumptions: &[Literal],
+        interrupt: &AtomicBool,
+    ) -> Option<SolutionStack> {
+        dpll::reset_assignments(&mut self.problem, &mut self.heuristics);
+        dpll::dpll_with_assumptions(
+            &mut self.problem,
+            &mut self.heuristics,
+            &mut self.profiler,
+            interrupt,
+            assumptions,
+        )
+    }
+
+    /// Adds a clause over variables of the problem, e.g. one that blocks a
+    /// model already found. Any assignment left over from the previous call is
+    /// undone first.
+    pub fn add_clause(&mut self, literals: &[Literal]) {
+        assert!(!literals.is_empty(), "the empty clause cannot be added");
+        dpll::reset_assignments(&mut self.problem, &mut self.heuristics);
+        let clause = self.problem.add_clause(literals);
+        self.heuristics.add_parsed_clause(&clause.borrow());
+    }
+
+    /// Adds a variable after every variable of the problem, which no clause
+    /// mentions yet, and tells the heuristics about it.
+    pub fn add_variable(&mut self) -> Variable {
+        let (variable, clause) = self.problem.add_variable();
+        self.heuristics.add_parsed_clause(&clause);
+        return variable;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::registry::{self, HeuristicsConfig, HeuristicsVisitor, HEURISTICS};
+    use crate::heuristics::vsids::VSIDS;
+
+    #[test]
+    fn assumptions_on_variables_in_no_clause_hold() {
+        let mut solver = Solver::new(Problem::new(), VSIDS::with_options(Default::default()));
+        solver.add_clause(&[Literal::from_dimacs(1), Literal::from_dimacs(2)]);
+        let interrupt = AtomicBool::new(false);
+        let solution = solver.solve(&[Literal::from_dimacs(-1), Literal::from_dimacs(-3)], &interrupt).unwrap();
+        let model = crate::enumerate::model(&solution);
+        assert_eq!(model.get(&1), Some(&false));
+        assert_eq!(model.get(&2), Some(&true));
+        assert_eq!(model.get(&3), Some(&false));
+        // the variable is known from now on, and the assumption is gone
+        solver.add_clause(&[Literal::from_dimacs(3)]);
+        assert!(solver.solve(&[Literal::from_dimacs(-3)], &interrupt).is_none());
+        assert!(solver.solve(&[], &interrupt).is_some());
+    }
+
+    // solves with a variable in no clause, assumed or not
+    struct AddedVariables;
+
+    impl HeuristicsVisitor for AddedVariables {
+        type Output = ();
+
+        fn visit<H: Heuristics>(self, options: H::Options) {
+            let mut solver = Solver::new(Problem::new(), H::with_options(options));
+            let on = |variable| Literal { variable, polarity: Polarity::On };
+            let (x, y, z) = (solver.add_variable(), solver.add_variable(), solver.add_variable());
+            solver.add_clause(&[on(x)]);
+            let interrupt = AtomicBool::new(false);
+            let model = crate::enumerate::model(&solver.solve(&[!on(y)], &interrupt).unwrap());
+            assert_eq!(model.get(&x.index), Some(&true));
+            assert_eq!(model.get(&y.index), Some(&false));
+            assert!(model.contains_key(&z.index));
+            let w = solver.add_variable();
+            assert!(solver.solve(&[on(w), on(z)], &interrupt).is_some());
+        }
+    }
+
+    #[test]
+    fn added_variables_are_known_to_every_heuristics() {
+        for entry in HEURISTICS.iter() {
+            registry::dispatch(entry.name, &HeuristicsConfig::default(), AddedVariables).unwrap();
+        }
+    }
+}
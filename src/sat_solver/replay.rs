@@ -0,0 +1,168 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::trace::Event;
+
+use super::dpll::*;
+use super::*;
+
+/// Re-applies recorded trace events to a Problem, rebuilding the trail step
+/// by step. Every event is checked against the formula before it is applied:
+/// an implication must be forced by its reason clause, a conflict clause must
+/// be falsified and backtracking must follow the trail. An explanation of the
+/// XOR constraints is added to the clauses; it must be falsified but for at
+/// most one literal.
+///
+/// Only assignments are replayed; watch literals stay as they were parsed.
+pub struct Replayer<H: Heuristics> {
+    pub problem: Problem,
+    pub solution: SolutionStack,
+    heuristics: H,
+}
+
+impl<H: Heuristics> Replayer<H> {
+    pub fn new(problem: Problem, heuristics: H) -> Self {
+        Replayer {
+            problem,
+            solution: SolutionStack { stack: vec![] },
+            heuristics,
+        }
+    }
+
+    pub fn apply(&mut self, event: &Event) -> Result<(), String> {
+        match *event {
+            Event::Explain(ref c) => {
+                self.check_explanation(c)?;
+                self.problem.add_clause(c);
+            }
+            Event::Decide(l) => {
+                self.assign(l, SolutionStepType::FreeChoiceFirstTry, None)?;
+            }
+            Event::Assume(l) => {
+                self.assign(l, SolutionStepType::Assumed, None)?;
+            }
+            Event::Imply(l, reason) => {
+                let id = reason.ok_or("implication without a reason clause")?;
+                let unit = self.check_reason(id, l)?;
+                let ass_type = if unit {
+                    SolutionStepType::ForcedAtInit
+                } else {
+                    SolutionStepType::ForcedAtBCP
+                };
+                self.assign(l, ass_type, reason)?;
+            }
+            Event::Conflict(Some(id)) => {
+                self.check_conflict(id)?;
+            }
+            // assumptions contradicting each other or an implied value
+            Event::Conflict(None) => {}
+            Event::Backtrack(v) => {
+                match self.solution.stack.last() {
+                    Some(step)
+                        if step.assignment.variable == v
+                            && step.assignment_type != SolutionStepType::FreeChoiceFirstTry => {}
+                    _ => return Err(format!("variable {} is not on top of the trail", v.index)),
+                }
+                self.heuristics.unassign_variable(v);
+                undo_assignment(&mut self.problem, v);
+                self.solution.stack.pop();
+            }
+            Event::Flip(l) => {
+                let Some(step) = self.solution.stack.last_mut() else {
+                    return Err(String::from("the trail is empty"));
+                };
+                if step.assignment.variable != l.variable
+                    || step.assignment.polarity == l.polarity
+                    || step.assignment_type != SolutionStepType::FreeChoiceFirstTry
+                {
+                    return Err(format!("{:?} is not a first try of {}", step, l.variable.index));
+                }
+                step.assignment.polarity = l.polarity;
+                step.assignment_type = SolutionStepType::FreeChoiceSecondTry;
+                update_literal_info(
+                    &mut self.problem,
+                    l.variable,
+                    l.polarity,
+                    UpdateLiteralInfoCause::Backtrack,
+                    &mut self.heuristics,
+                );
+            }
+        }
+        // nothing propagates here, the trace says what comes next
+        self.problem.list_of_clauses_to_check.clear();
+        self.problem.cardinality_constraints.to_check.clear();
+        return Ok(());
+    }
+
+    fn assign(&mut self, l: Literal, ass_type: SolutionStepType, reason: Option<u32>) -> Result<(), String> {
+        match self.problem.list_of_variables.get(&l.variable) {
+            None => return Err(format!("unknown variable {}", l.variable.index)),
+            Some(VariableState::Assigned) => return Err(format!("variable {} is already assigned", l.variable.index)),
+            Some(VariableState::Unassigned) => {}
+        }
+        let cause = match ass_type {
+            SolutionStepType::ForcedAtBCP => UpdateLiteralInfoCause::BCPImplication,
+            SolutionStepType::ForcedAtInit | SolutionStepType::Assumed => UpdateLiteralInfoCause::UnitClauseImplication,
+            _ => UpdateLiteralInfoCause::FreeAssignment,
+        };
+        self.solution.push_step(l.variable, l.polarity, ass_type, reason);
+        self.heuristics.assign_variable(l.variable);
+        mark_variable_assigned(&mut self.problem, l.variable);
+        update_literal_info(&mut self.problem, l.variable, l.polarity, cause, &mut self.heuristics);
+        return Ok(());
+    }
+
+    fn status(&self, l: Literal) -> LiteralState {
+        self.problem.list_of_literal_infos[&l].borrow().status
+    }
+
+    fn clause(&self, id: u32) -> Result<Rc<RefCell<Clause>>, String> {
+        match self.problem.list_of_clauses.get(id as usize) {
+            Some(rc) => Ok(Rc::clone(rc)),
+            None => Err(format!("unknown clause {}", id)),
+        }
+    }
+
+    // the clause must contain `l` and all its other literals must be false;
+    // returns true for a unit clause
+    fn check_reason(&self, id: u32, l: Literal) -> Result<bool, String> {
+        let rc = self.clause(id)?;
+        let c = rc.borrow();
+        if !c.list_of_literals.contains(&l) {
+            return Err(format!("clause {} does not contain {}", id, l.to_dimacs()));
+        }
+        if let Some(other) = c.list_of_literals.iter().find(|o| **o != l && self.status(**o) != LiteralState::Unsat) {
+            return Err(format!("clause {} does not force {}, {} is not false", id, l.to_dimacs(), other.to_dimacs()));
+        }
+        return Ok(c.list_of_literals.iter().all(|o| *o == l));
+    }
+
+    fn check_explanation(&self, c: &[Literal]) -> Result<(), String> {
+        if let Some(l) = c.iter().find(|l| !self.problem.list_of_literal_infos.contains_key(l)) {
+            return Err(format!("unknown literal {}", l.to_dimacs()));
+        }
+        let open = c.iter().filter(|l| self.status(**l) != LiteralState::Unsat).count();
+        if open > 1 {
+            return Err(format!("the explanation has {} literals that are not false", open));
+        }
+        return Ok(());
+    }
+
+    // BCP reports a conflict as soon as a clause forces the complement of a
+    // pending implication, so one literal may still be unassigned
+    fn check_conflict(&self, id: u32) -> Result<(), String> {
+        let rc = self.clause(id)?;
+        let c = rc.borrow();
+        let open: Vec<LiteralState> = c
+            .list_of_literals
+            .iter()
+            .map(|l| self.status(*l))
+            .filter(|s| *s != LiteralState::Unsat)
+            .collect();
+        if open.contains(&LiteralState::Sat) {
+            return Err(format!("clause {} is satisfied", id));
+        }
+        if open.len() > 1 {
+            return Err(format!("clause {} has {} unassigned literals", id, open.len()));
+        }
+        return Ok(());
+    }
+}
@@ -2,7 +2,7 @@ use super::*;
 use std::ops::Not;
 
 /////////////////////////////////////////
-/// Implementation of SAT data structures
+// Implementation of SAT data structures
 /////////////////////////////////////////
 
 impl fmt::Debug for Variable {
@@ -37,6 +37,40 @@ impl fmt::Debug for Literal {
     }
 }
 
+impl Literal {
+    /// Converts a non-zero DIMACS integer (e.g. `-3`) into a Literal.
+    pub fn from_dimacs(val: i32) -> Literal {
+        assert!(
+            val != 0,
+            "Variable index must be non-zero, because zero is for NULL_VARIABLE, we got {}",
+            val
+        );
+        Literal {
+            variable: Variable {
+                index: val.unsigned_abs(),
+            },
+            polarity: if val > 0 { Polarity::On } else { Polarity::Off },
+        }
+    }
+
+    pub fn to_dimacs(self) -> i32 {
+        match self.polarity {
+            Polarity::On => self.variable.index as i32,
+            Polarity::Off => -(self.variable.index as i32),
+        }
+    }
+}
+
+impl Not for Literal {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        Literal {
+            variable: self.variable,
+            polarity: !self.polarity,
+        }
+    }
+}
+
 impl fmt::Debug for Clause {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // The point of this struct is to remove the strong reference to
@@ -62,7 +96,7 @@ impl fmt::Debug for Clause {
 }
 
 impl Clause {
-    pub fn recalculate_clause_state(&self, problem: &Problem) -> ClauseState {
+    pub fn recalculate_clause_state(&self, _problem: &Problem) -> ClauseState {
         let mut encountered_unknown_state = false;
 
         //for l in &self.list_of_literals {
@@ -83,11 +117,7 @@ impl Clause {
     }
 
     pub fn hits_watch_literals(&self, l: Literal) -> bool {
-        if self.watch_literals[0] != l && self.watch_literals[1] != l {
-            return false;
-        } else {
-            return true;
-        }
+        return self.watch_literals[0] == l || self.watch_literals[1] == l;
     }
 
     /// Precondition: one (and only one) of the watch literals of this clause is UNSAT
@@ -95,7 +125,7 @@ impl Clause {
     /// substituted with an Unknown-state literal; if returning ForcedAssignment, nothing is changed.
     pub fn try_substitute_watch_literal(
         &mut self,
-        problem: &Problem,
+        _problem: &Problem,
     ) -> BCPSubstituteWatchLiteralResult {
         // Examine if the clause is already SAT (a not-watched literal is SAT),
         // also examine the two watch literals
@@ -123,11 +153,9 @@ impl Clause {
         }
 
         // which watch literal became UNSAT?
-        let watch_index;
-
-        match (status_0, status_1) {
-            (LiteralState::Unsat, LiteralState::Unknown) => watch_index = 0,
-            (LiteralState::Unknown, LiteralState::Unsat) => watch_index = 1,
+        let watch_index = match (status_0, status_1) {
+            (LiteralState::Unsat, LiteralState::Unknown) => 0,
+            (LiteralState::Unknown, LiteralState::Unsat) => 1,
             (LiteralState::Sat, _) | (_, LiteralState::Sat) => {
                 return BCPSubstituteWatchLiteralResult::ClauseIsSAT;
             }
@@ -137,7 +165,7 @@ impl Clause {
             (LiteralState::Unsat, LiteralState::Unsat) => {
                 panic!("both of the watch literals of clause {:?} are unsat", self);
             }
-        }
+        };
 
         let substitute_literal = self
             .list_of_literals
@@ -164,6 +192,142 @@ impl Clause {
     }
 }
 
+impl Default for Problem {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl Problem {
+    pub fn new() -> Problem {
+        Problem {
+            list_of_variables: BTreeMap::<Variable, VariableState>::new(),
+            list_of_literal_infos: BTreeMap::<Literal, Rc<RefCell<LiteralInfo>>>::new(),
+            list_of_clauses: Vec::<Rc<RefCell<Clause>>>::new(),
+            list_of_clauses_to_check: BTreeSet::new(),
+            xor_constraints: xor::XorStore::default(),
+            cardinality_constraints: cardinality::CardinalityStore::default(),
+            explanations: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a new clause (and any variables/literals it mentions for the
+    /// first time). The two watch literals are the last two literals of the
+    /// clause; a unit clause only watches its single literal.
+    ///
+    /// The caller is responsible for telling the heuristics about the clause.
+    /// Clauses must only be added while no variable is assigned (e.g. before
+    /// solving, or after `dpll::reset_assignments`), otherwise the watch
+    /// literal invariants may not hold.
+    pub fn add_clause(&mut self, literals: &[Literal]) -> Rc<RefCell<Clause>> {
+        let clause = Rc::new(RefCell::new(Clause {
+            id: self.list_of_clauses.len() as u32,
+            list_of_literals: Vec::<Literal>::new(),
+            list_of_literal_infos: vec![],
+            watch_literals: [NULL_LITERAL; 2],
+        }));
+
+        let mut clause_lit_count = 0;
+        for literal in literals {
+            clause_lit_count += 1;
+
+            // register new variable
+            self.list_of_variables
+                .entry(literal.variable)
+                .or_insert(VariableState::Unassigned);
+
+            // register new literal
+            let li = self
+                .list_of_literal_infos
+                .entry(*literal)
+                .and_modify(|e| {
+                    (**e)
+                        .borrow_mut()
+                        .list_of_clauses
+                        .push(Rc::clone(&clause))
+                })
+                .or_insert_with(|| {
+                    let l = LiteralInfo {
+                        list_of_clauses: vec![Rc::clone(&clause)],
+                        status: LiteralState::Unknown,
+                    };
+                    Rc::new(RefCell::new(l))
+                });
+            let mut clause_mut = (*clause).borrow_mut();
+            clause_mut.list_of_literal_infos.push(Rc::clone(li));
+            clause_mut.list_of_literals.push(*literal);
+            clause_mut.watch_literals[clause_lit_count % 2] = *literal;
+        }
+
+        self.list_of_clauses.push(Rc::clone(&clause));
+        clause
+    }
+
+    /// Registers the constraint that the XOR of `literals` is true, and its
+    /// variables and literals. The returned clause holds both literals of
+    /// every variable and is not part of the problem: it is only for
+    /// introducing the variables to the heuristics.
+    pub fn add_xor(&mut self, literals: &[Literal]) -> Clause {
+        self.xor_constraints.add(literals);
+        self.register_literals(literals)
+    }
+
+    /// Registers the constraint that at least `bound` of `literals` are true,
+    /// like `add_xor`. The literals must have distinct variables, and the
+    /// bound must be between 2 and their number.
+    pub fn add_cardinality(&mut self, literals: &[Literal], bound: usize) -> Clause {
+        assert!(1 < bound && bound <= literals.len());
+        self.cardinality_constraints.add(literals, bound);
+        self.register_literals(literals)
+    }
+
+    /// Registers variables that no constraint mentions (yet), e.g. assumed or
+    /// declared ones, like `add_xor`, so that they can be assigned.
+    pub fn add_free_variables(&mut self, variables: &[Variable]) -> Clause {
+        let literals: Vec<Literal> =
+            variables.iter().map(|v| Literal { variable: *v, polarity: Polarity::On }).collect();
+        self.register_literals(&literals)
+    }
+
+    /// Registers a variable after every variable so far, e.g. an auxiliary
+    /// variable of an encoding added between solve calls, like
+    /// `add_free_variables`.
+    pub fn add_variable(&mut self) -> (Variable, Clause) {
+        let index = self.list_of_variables.keys().next_back().map_or(1, |v| v.index + 1);
+        let variable = Variable { index };
+        (variable, self.add_free_variables(&[variable]))
+    }
+
+    // Registers both literals of the variables of a constraint that is not a
+    // clause, and returns them as a detached clause.
+    fn register_literals(&mut self, literals: &[Literal]) -> Clause {
+        let mut both = Vec::<Literal>::new();
+        for literal in literals {
+            self.list_of_variables
+                .entry(literal.variable)
+                .or_insert(VariableState::Unassigned);
+            for polarity in [Polarity::On, Polarity::Off] {
+                let l = Literal { variable: literal.variable, polarity };
+                self.list_of_literal_infos.entry(l).or_insert_with(|| {
+                    Rc::new(RefCell::new(LiteralInfo {
+                        list_of_clauses: vec![],
+                        status: LiteralState::Unknown,
+                    }))
+                });
+                if !both.contains(&l) {
+                    both.push(l);
+                }
+            }
+        }
+        Clause {
+            id: u32::MAX,
+            list_of_literals: both,
+            list_of_literal_infos: vec![],
+            watch_literals: [NULL_LITERAL; 2],
+        }
+    }
+}
+
 impl fmt::Debug for SolutionStep {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -174,6 +338,7 @@ impl fmt::Debug for SolutionStep {
                 SolutionStepType::FreeChoiceSecondTry => "T",
                 SolutionStepType::ForcedAtBCP => "x",
                 SolutionStepType::ForcedAtInit => "I",
+                SolutionStepType::Assumed => "A",
             }
         )?;
         write!(f, "{}", self.assignment.variable.index)?;
@@ -283,17 +448,31 @@ impl fmt::Debug for SolutionStep {
 
 impl SolutionStack {
     pub fn push_free_choice_first_try(&mut self, var: Variable, pol: Polarity) {
-        self.push_step(var, pol, SolutionStepType::FreeChoiceFirstTry);
+        self.push_step(var, pol, SolutionStepType::FreeChoiceFirstTry, None);
     }
 
-    pub fn push_step(&mut self, var: Variable, pol: Polarity, ass_type: SolutionStepType) {
+    pub fn push_step(&mut self, var: Variable, pol: Polarity, ass_type: SolutionStepType, reason: Option<u32>) {
         let step = SolutionStep {
             assignment: Assignment {
                 variable: var,
                 polarity: pol,
             },
             assignment_type: ass_type,
+            reason,
         };
         self.stack.push(step);
     }
+
+    // number of free choices on the stack
+    pub fn decision_level(&self) -> u64 {
+        self.stack
+            .iter()
+            .filter(|step| {
+                matches!(
+                    step.assignment_type,
+                    SolutionStepType::FreeChoiceFirstTry | SolutionStepType::FreeChoiceSecondTry
+                )
+            })
+            .count() as u64
+    }
 }
@@ -0,0 +1,244 @@
+use super::*;
+
+// XOR constraints (the `x` lines of CryptoMiniSat's extended DIMACS), kept
+// out of the clause list: the CNF encoding of an XOR over n variables takes
+// 2^(n-1) clauses.
+//
+// Propagation is Gauss-Jordan elimination over GF(2). Every constraint is a
+// row of a bit matrix over the unassigned variables, with its parity adjusted
+// by the assigned ones. After elimination a row without variables and with
+// parity 1 is a conflict, and a row with a single variable implies it. Each
+// row remembers which constraints were added up to make it; the sum of those
+// constraints, evaluated under the current assignment, is the explanation of
+// the implication or conflict, returned as an ordinary clause.
+
+#[derive(Debug)]
+pub struct XorConstraint {
+    // sorted, without duplicates
+    pub variables: Vec<Variable>,
+    // the XOR of the variables must be this
+    pub parity: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct XorStore {
+    pub constraints: Vec<XorConstraint>,
+    // the column of each variable in the matrix, and the variable of each
+    // column
+    columns: BTreeMap<Variable, usize>,
+    variables: Vec<Variable>,
+}
+
+// a row of the matrix: the unassigned variables, the parity they must have and
+// the constraints it is the sum of
+#[derive(Clone)]
+struct Row {
+    bits: Vec<u64>,
+    parity: bool,
+    history: Vec<u64>,
+}
+
+fn test_bit(bits: &[u64], i: usize) -> bool {
+    bits[i / 64] >> (i % 64) & 1 == 1
+}
+
+fn set_bit(bits: &mut [u64], i: usize) {
+    bits[i / 64] |= 1 << (i % 64);
+}
+
+fn add_bits(bits: &mut [u64], other: &[u64]) {
+    for (b, o) in bits.iter_mut().zip(other.iter()) {
+        *b ^= o;
+    }
+}
+
+impl XorStore {
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    /// Adds the constraint that the XOR of `literals` is true. A negative
+    /// literal flips the parity, and a variable given twice cancels out.
+    pub fn add(&mut self, literals: &[Literal]) {
+        let mut variables = BTreeSet::new();
+        let mut parity = true;
+        for l in literals.iter() {
+            parity ^= l.polarity == Polarity::Off;
+            if !variables.insert(l.variable) {
+                variables.remove(&l.variable);
+            }
+        }
+        for v in variables.iter() {
+            if !self.columns.contains_key(v) {
+                self.columns.insert(*v, self.variables.len());
+                self.variables.push(*v);
+            }
+        }
+        self.constraints.push(XorConstraint {
+            variables: variables.into_iter().collect(),
+            parity,
+        });
+    }
+
+    /// Gauss-Jordan elimination under the assignment given by `value`.
+    /// Returns the implied literals, or the explanation of a conflict.
+    pub fn propagate(&self, value: impl Fn(Variable) -> Option<bool>) -> Result<Vec<Implication>, Vec<Literal>> {
+        let words = self.variables.len().div_ceil(64);
+        let history_words = self.constraints.len().div_ceil(64);
+        let mut rows: Vec<Row> = Vec::with_capacity(self.constraints.len());
+        for (i, x) in self.constraints.iter().enumerate() {
+            let mut row = Row {
+                bits: vec![0; words],
+                parity: x.parity,
+                history: vec![0; history_words],
+            };
+            set_bit(&mut row.history, i);
+            for v in x.variables.iter() {
+                match value(*v) {
+                    Some(b) => row.parity ^= b,
+                    None => set_bit(&mut row.bits, self.columns[v]),
+                }
+            }
+            rows.push(row);
+        }
+
+        let mut rank = 0;
+        for column in 0..self.variables.len() {
+            let Some(pivot) = (rank..rows.len()).find(|r| test_bit(&rows[*r].bits, column)) else {
+                continue;
+            };
+            rows.swap(rank, pivot);
+            let pivot = rows[rank].clone();
+            for (r, row) in rows.iter_mut().enumerate() {
+                if r != rank && test_bit(&row.bits, column) {
+                    add_bits(&mut row.bits, &pivot.bits);
+                    add_bits(&mut row.history, &pivot.history);
+                    row.parity ^= pivot.parity;
+                }
+            }
+            rank += 1;
+        }
+
+        // the rows past the rank have no variables left
+        if let Some(row) = rows[rank..].iter().find(|row| row.parity) {
+            return Err(self.explain(&row.history, None, &value));
+        }
+        let mut implied = Vec::new();
+        for row in rows[..rank].iter() {
+            if row.bits.iter().map(|w| w.count_ones()).sum::<u32>() != 1 {
+                continue;
+            }
+            let column = (0..self.variables.len()).find(|c| test_bit(&row.bits, *c)).unwrap();
+            let l = Literal {
+                variable: self.variables[column],
+                polarity: if row.parity { Polarity::On } else { Polarity::Off },
+            };
+            implied.push((l, self.explain(&row.history, Some(l), &value)));
+        }
+        return Ok(implied);
+    }
+
+    // The clause saying that the sum of the constraints in `history` cannot
+    // hold with the current values of its assigned variables, unless `implied`
+    // is true.
+    fn explain(&self, history: &[u64], implied: Option<Literal>, value: &impl Fn(Variable) -> Option<bool>) -> Vec<Literal> {
+        let mut variables = BTreeSet::new();
+        for (i, x) in self.constraints.iter().enumerate() {
+            if test_bit(history, i) {
+                for v in x.variables.iter() {
+                    if !variables.insert(*v) {
+                        variables.remove(v);
+                    }
+                }
+            }
+        }
+        let mut clause: Vec<Literal> = variables
+            .into_iter()
+            .filter(|v| implied.is_none_or(|l| l.variable != *v))
+            .map(|v| Literal {
+                variable: v,
+                polarity: if value(v) == Some(true) { Polarity::Off } else { Polarity::On },
+            })
+            .collect();
+        clause.extend(implied);
+        return clause;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    // the value of v in the total assignment `mask`
+    fn bit(mask: u32, v: Variable) -> bool {
+        return mask >> (v.index - 1) & 1 == 1;
+    }
+
+    fn satisfies(mask: u32, clause: &[Literal]) -> bool {
+        return clause.iter().any(|l| bit(mask, l.variable) == (l.polarity == Polarity::On));
+    }
+
+    fn holds(store: &XorStore, mask: u32) -> bool {
+        return store
+            .constraints
+            .iter()
+            .all(|x| x.variables.iter().filter(|v| bit(mask, **v)).count() % 2 == x.parity as usize);
+    }
+
+    // Random systems over a few variables under random partial assignments:
+    // a conflict means no extension satisfies the constraints, an implied
+    // literal holds in every extension that does and every literal that does
+    // is implied, and each explanation follows from the constraints, with its
+    // other literals false under the assignment.
+    #[test]
+    fn propagation_and_explanations_match_brute_force() {
+        let mut rng = Rng::new(0);
+        for _ in 0..2000 {
+            let n = 1 + rng.below(6) as u32;
+            let mut store = XorStore::default();
+            for _ in 0..1 + rng.below(4) {
+                let literals: Vec<Literal> = (0..1 + rng.below(4))
+                    .map(|_| Literal {
+                        variable: Variable { index: 1 + rng.below(n as u64) as u32 },
+                        polarity: if rng.below(2) == 0 { Polarity::On } else { Polarity::Off },
+                    })
+                    .collect();
+                store.add(&literals);
+            }
+            let assigned: Vec<Option<bool>> =
+                (0..n).map(|_| if rng.below(2) == 0 { None } else { Some(rng.below(2) == 0) }).collect();
+            let value = |v: Variable| assigned[v.index as usize - 1];
+            let extends =
+                |mask: u32| (1..=n).map(|i| Variable { index: i }).all(|v| value(v).is_none_or(|b| bit(mask, v) == b));
+            let models: Vec<u32> = (0..1u32 << n).filter(|mask| extends(*mask) && holds(&store, *mask)).collect();
+            let follows =
+                |clause: &[Literal]| (0..1u32 << n).all(|mask| !holds(&store, mask) || satisfies(mask, clause));
+            let is_false = |l: &Literal| value(l.variable) == Some(l.polarity == Polarity::Off);
+            match store.propagate(value) {
+                Err(clause) => {
+                    assert!(models.is_empty(), "{:?} under {:?}", store, assigned);
+                    assert!(clause.iter().all(is_false), "{:?} explained by {:?}", store, clause);
+                    assert!(follows(&clause), "{:?} explained by {:?}", store, clause);
+                }
+                Ok(implied) => {
+                    assert!(!models.is_empty(), "{:?} under {:?}", store, assigned);
+                    for (l, clause) in implied.iter() {
+                        assert!(models.iter().all(|mask| satisfies(*mask, &[*l])), "{:?} implies {:?}", store, l);
+                        assert!(clause.contains(l) && clause.iter().filter(|k| *k != l).all(is_false));
+                        assert!(follows(clause), "{:?} explains {:?} by {:?}", store, l, clause);
+                    }
+                    for i in 1..=n {
+                        let v = Variable { index: i };
+                        if value(v).is_some() || !store.variables.contains(&v) {
+                            continue;
+                        }
+                        if models.iter().all(|mask| bit(*mask, v)) || models.iter().all(|mask| !bit(*mask, v)) {
+                            assert!(implied.iter().any(|(l, _)| l.variable == v), "{:?} fixes {:?}", store, v);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::heuristics::heuristics::Heuristics;
+use crate::heuristics::registry::{self, HeuristicsConfig, HeuristicsVisitor};
+use crate::sat_solver::incremental::Solver;
+use crate::sat_solver::*;
+use crate::verify::{Cnf, Model};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::sync::atomic::AtomicBool;
+
+// A line protocol on stdin/stdout, so that other programs can drive one
+// long-lived incremental solver. Every command gets exactly one line back:
+//
+//   add <lits> 0      adds a clause to the current level          OK
+//   assume <lits> 0   assumes literals for the next solve only    OK
+//   solve                                                         SAT | UNSAT
+//   model             the model of the last solve                 MODEL <lits> 0
+//   core              failed assumptions of the last solve        CORE <lits> 0
+//   push              opens a level of clauses                    OK
+//   pop               drops the clauses of the last level         OK
+//   stats             the statistics so far                       STATS <json>
+//   reset             starts over with an empty solver            OK
+//
+// and `ERROR <message>` when it cannot be carried out. Empty lines and lines
+// starting with `c` are ignored.
+//
+// The solver cannot remove clauses, so a level is an activation literal: the
+// clauses added at a level hold only while its literal is assumed, which every
+// solve does for the open levels, and popping the level asserts the literal's
+// negation for good. User variables are mapped to solver variables as they
+// come, which keeps the activation literals out of their way.
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    #[arg(long, default_value_t = String::from("vsids"))]
+    pub heuristics: String,
+
+    #[arg(long)]
+    pub no_bcp: bool,
+
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+enum Answer {
+    Sat(Model),
+    // the core is found when first asked for
+    Unsat { assumptions: Vec<i32>, core: Option<Vec<i32>> },
+}
+
+struct Session<H: Heuristics> {
+    solver: Solver<H>,
+    // the solver variable of each user variable
+    variables: BTreeMap<u32, Variable>,
+    // the activation literal of each open level
+    levels: Vec<Literal>,
+    // the clauses of level 0 and of each open level, for checking models
+    clauses: Vec<Vec<Vec<i32>>>,
+    assumptions: Vec<i32>,
+    // an empty clause at level 0
+    refuted: bool,
+    // of the last solve, until the next change
+    answer: Option<Answer>,
+}
+
+impl<H: Heuristics> Session<H> {
+    fn new(heuristics: H) -> Self {
+        Session {
+            solver: Solver::new(Problem::new(), heuristics),
+            variables: BTreeMap::new(),
+            levels: Vec::new(),
+            clauses: vec![Vec::new()],
+            assumptions: Vec::new(),
+            refuted: false,
+            answer: None,
+        }
+    }
+
+    fn literal(&mut self, l: i32) -> Literal {
+        let solver = &mut self.solver;
+        let variable = *self.variables.entry(l.unsigned_abs()).or_insert_with(|| solver.add_variable());
+        Literal {
+            variable,
+            polarity: if l > 0 { Polarity::On } else { Polarity::Off },
+        }
+    }
+
+    fn add(&mut self, clause: Vec<i32>) {
+        self.answer = None;
+        let mut literals: Vec<Literal> = clause.iter().map(|l| self.literal(*l)).collect();
+        // the solver takes clauses without repeated literals; a tautology
+        // constrains nothing
+        literals.sort();
+        literals.dedup();
+        self.clauses.last_mut().unwrap().push(clause);
+        if literals.windows(2).any(|w| w[0].variable == w[1].variable) {
+            return;
+        }
+        if let Some(level) = self.levels.last() {
+            literals.push(!*level);
+        }
+        if literals.is_empty() {
+            self.refuted = true;
+            return;
+        }
+        self.solver.add_clause(&literals);
+    }
+
+    // solves the clauses of the open levels under the given user assumptions
+    fn solve_under(&mut self, assumptions: &[i32]) -> Option<Model> {
+        if self.refuted {
+            return None;
+        }
+        let mut literals = self.levels.clone();
+        literals.extend(assumptions.iter().map(|l| self.literal(*l)));
+        let solution = self.solver.solve(&literals, &AtomicBool::new(false))?;
+        let values = crate::enumerate::model(&solution);
+        // a variable the solution leaves out is false
+        let model = self
+            .variables
+            .iter()
+            .map(|(user, v)| (*user, values.get(&v.index).copied().unwrap_or(false)))
+            .collect();
+        return Some(model);
+    }
+
+    fn solve(&mut self) -> Result<String, String> {
+        let assumptions = std::mem::take(&mut self.assumptions);
+        match self.solve_under(&assumptions) {
+            Some(model) => {
+                let cnf = Cnf {
+                    declared_variables: None,
+                    clauses: self.clauses.iter().flatten().cloned().collect(),
+                    xors: Vec::new(),
+                    projection: None,
+                    prefix: Vec::new(),
+                };
+                let literals: Vec<Literal> = assumptions.iter().map(|l| Literal::from_dimacs(*l)).collect();
+                crate::icnf::check(&cnf, &literals, &model)?;
+                self.answer = Some(Answer::Sat(model));
+                return Ok(String::from("SAT"));
+            }
+            None => {
+                self.answer = Some(Answer::Unsat { assumptions, core: None });
+                return Ok(String::from("UNSAT"));
+            }
+        }
+    }
+
+    // A subset of the assumptions of the last solve that is unsatisfiable
+    // with the clauses, from which no assumption can be left out: each one is
+    // dropped in turn if the rest is still unsatisfiable.
+    fn core(&mut self) -> Result<Vec<i32>, String> {
+        let mut core = match &self.answer {
+            Some(Answer::Unsat { core: Some(core), .. }) => return Ok(core.clone()),
+            Some(Answer::Unsat { assumptions, .. }) => assumptions.clone(),
+            Some(Answer::Sat(_)) => return Err(String::from("the last solve was SAT")),
+            None => return Err(String::from("nothing solved since the last change")),
+        };
+        core.sort_by_key(|l| (l.unsigned_abs(), *l));
+        core.dedup();
+        let mut i = 0;
+        while i < core.len() {
+            let mut rest = core.clone();
+            rest.remove(i);
+            if self.solve_under(&rest).is_none() {
+                core = rest;
+            } else {
+                i += 1;
+            }
+        }
+        if let Some(Answer::Unsat { core: found, .. }) = &mut self.answer {
+            *found = Some(core.clone());
+        }
+        return Ok(core);
+    }
+
+    fn model(&self) -> Result<Vec<i32>, String> {
+        match &self.answer {
+            Some(Answer::Sat(model)) => {
+                return Ok(model.iter().map(|(v, value)| if *value { *v as i32 } else { -(*v as i32) }).collect());
+            }
+            Some(Answer::Unsat { .. }) => return Err(String::from("the last solve was UNSAT")),
+            None => return Err(String::from("nothing solved since the last change")),
+        }
+    }
+
+    fn push(&mut self) {
+        self.answer = None;
+        let level = Literal {
+            variable: self.solver.add_variable(),
+            polarity: Polarity::On,
+        };
+        self.levels.push(level);
+        self.clauses.push(Vec::new());
+    }
+
+    fn pop(&mut self) -> Result<(), String> {
+        let level = self.levels.pop().ok_or_else(|| String::from("no level to pop"))?;
+        self.answer = None;
+        self.clauses.pop();
+        self.solver.add_clause(&[!level]);
+        return Ok(());
+    }
+}
+
+// the literals of `add` and `assume`, which end with 0
+fn parse_literals(arguments: &[&str]) -> Result<Vec<i32>, String> {
+    let mut literals = Vec::new();
+    for (k, token) in arguments.iter().enumerate() {
+        // i32::MIN has no negation, so the model could not print it
+        let l: i32 = token
+            .parse()
+            .ok()
+            .filter(|l| *l != i32::MIN)
+            .ok_or_else(|| format!("bad literal '{}'", token))?;
+        if l == 0 {
+            if k + 1 < arguments.len() {
+                return Err(String::from("literals after the terminating 0"));
+            }
+            return Ok(literals);
+        }
+        literals.push(l);
+    }
+    return Err(String::from("missing the terminating 0"));
+}
+
+fn dimacs(literals: &[i32]) -> String {
+    let mut line: Vec<String> = literals.iter().map(|l| l.to_string()).collect();
+    line.push(String::from("0"));
+    return line.join(" ");
+}
+
+// The response to a line, or None for a line to ignore. `heuristics` makes
+// the heuristics of a fresh session.
+fn respond<H: Heuristics>(session: &mut Session<H>, line: &str, heuristics: &impl Fn() -> H) -> Option<String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let (command, arguments) = tokens.split_first()?;
+    let response = match *command {
+        "c" => return None,
+        "add" => parse_literals(arguments).map(|clause| {
+            session.add(clause);
+            String::from("OK")
+        }),
+        "assume" => parse_literals(arguments).map(|literals| {
+            session.answer = None;
+            session.assumptions.extend(literals);
+            String::from("OK")
+        }),
+        _ if !arguments.is_empty() => Err(format!("{} takes no arguments", command)),
+        "solve" => session.solve(),
+        "model" => session.model().map(|model| format!("MODEL {}", dimacs(&model))),
+        "core" => session.core().map(|core| format!("CORE {}", dimacs(&core))),
+        "push" => {
+            session.push();
+            Ok(String::from("OK"))
+        }
+        "pop" => session.pop().map(|_| String::from("OK")),
+        "stats" => Ok(format!("STATS {}", session.solver.profiler.to_json())),
+        "reset" => {
+            *session = Session::new(heuristics());
+            Ok(String::from("OK"))
+        }
+        _ => Err(format!("unknown command '{}'", command)),
+    };
+    return Some(response.unwrap_or_else(|message| format!("ERROR {}", message)));
+}
+
+struct Serve<'a> {
+    args: &'a ServeArgs,
+}
+
+impl HeuristicsVisitor for Serve<'_> {
+    type Output = Result<(), String>;
+
+    fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+        let heuristics = || {
+            let mut h = H::with_options(options.clone());
+            h.set_use_bcp(!self.args.no_bcp);
+            h.set_seed(self.args.seed);
+            h
+        };
+        let mut session = Session::new(heuristics());
+        for line in io::stdin().lock().lines() {
+            let line = line.map_err(|e| format!("stdin: {}", e))?;
+            if let Some(response) = respond(&mut session, &line, &heuristics) {
+                println!("{}", response);
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Answers commands from stdin until it is closed.
+pub fn serve(args: &ServeArgs) -> Result<(), String> {
+    return registry::dispatch(&args.heuristics, &HeuristicsConfig::default(), Serve { args })?;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristics::registry::HEURISTICS;
+
+    // the responses of a session to `lines`
+    struct Script<'a>(&'a [&'a str]);
+
+    impl HeuristicsVisitor for Script<'_> {
+        type Output = Vec<Option<String>>;
+
+        fn visit<H: Heuristics>(self, options: H::Options) -> Self::Output {
+            let heuristics = || H::with_options(options.clone());
+            let mut session = Session::new(heuristics());
+            return self.0.iter().map(|line| respond(&mut session, line, &heuristics)).collect();
+        }
+    }
+
+    #[test]
+    fn sessions_answer_every_command() {
+        // every model is forced, so that every heuristics gives the same
+        let transcript = [
+            ("c a comment", None),
+            ("", None),
+            ("add 1 0", Some("OK")),
+            ("assume 2 0", Some("OK")),
+            ("solve", Some("SAT")),
+            ("model", Some("MODEL 1 2 0")),
+            ("core", Some("ERROR the last solve was SAT")),
+            ("add -2 3 0", Some("OK")),
+            ("model", Some("ERROR nothing solved since the last change")),
+            // the level holds -3 until it is popped
+            ("push", Some("OK")),
+            ("add -3 0", Some("OK")),
+            ("assume 1 0", Some("OK")),
+            ("assume 2 0", Some("OK")),
+            ("solve", Some("UNSAT")),
+            ("core", Some("CORE 2 0")),
+            ("model", Some("ERROR the last solve was UNSAT")),
+            ("pop", Some("OK")),
+            ("pop", Some("ERROR no level to pop")),
+            ("assume 2 0", Some("OK")),
+            ("solve", Some("SAT")),
+            ("model", Some("MODEL 1 2 3 0")),
+            ("add 1 0 2 0", Some("ERROR literals after the terminating 0")),
+            ("add 1", Some("ERROR missing the terminating 0")),
+            ("assume -2147483648 0", Some("ERROR bad literal '-2147483648'")),
+            ("solve now", Some("ERROR solve takes no arguments")),
+            ("frobnicate", Some("ERROR unknown command 'frobnicate'")),
+            ("add 0", Some("OK")),
+            ("solve", Some("UNSAT")),
+            ("core", Some("CORE 0")),
+            ("reset", Some("OK")),
+            ("model", Some("ERROR nothing solved since the last change")),
+            ("add 4 0", Some("OK")),
+            ("solve", Some("SAT")),
+            ("model", Some("MODEL 4 0")),
+        ];
+        let lines: Vec<&str> = transcript.iter().map(|(line, _)| *line).collect();
+        for entry in HEURISTICS.iter() {
+            let responses = registry::dispatch(entry.name, &HeuristicsConfig::default(), Script(&lines)).unwrap();
+            for ((line, expected), response) in transcript.iter().zip(responses) {
+                assert_eq!(response.as_deref(), *expected, "{}: {}", entry.name, line);
+            }
+            let stats = registry::dispatch(entry.name, &HeuristicsConfig::default(), Script(&["stats"])).unwrap();
+            assert!(stats[0].as_ref().unwrap().starts_with("STATS {"), "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn literals_end_with_zero_and_fit_a_variable() {
+        assert_eq!(parse_literals(&["1", "-2", "0"]), Ok(vec![1, -2]));
+        assert_eq!(parse_literals(&["0"]), Ok(vec![]));
+        assert!(parse_literals(&["1"]).is_err());
+        assert!(parse_literals(&["1", "0", "2"]).is_err());
+        assert!(parse_literals(&["x", "0"]).is_err());
+        assert_eq!(parse_literals(&["-2147483648", "0"]), Err(String::from("bad literal '-2147483648'")));
+        assert_eq!(parse_literals(&["-2147483647", "0"]), Ok(vec![-i32::MAX]));
+    }
+}
@@ -0,0 +1,14 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Helpers for the unit tests.
+
+/// Writes `contents` to a file in the temporary directory and returns its
+/// path, which no other call returns: tests run in parallel threads of one
+/// process. `name` ends the path, e.g. with the extension a reader expects.
+pub fn temp_file(name: &str, contents: impl AsRef<[u8]>) -> String {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    let call = CALLS.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("sat_solver_test_{}_{}_{}", std::process::id(), call, name));
+    std::fs::write(&path, contents).unwrap();
+    return path.to_string_lossy().to_string();
+}
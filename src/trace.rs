@@ -0,0 +1,389 @@
+use crate::heuristics::ascending::Ascending;
+use crate::heuristics::heuristics::Heuristics;
+use crate::parser;
+use crate::sat_solver::implication_graph;
+use crate::sat_solver::replay::Replayer;
+use crate::sat_solver::*;
+use core::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+// A compact binary record of everything dpll does to the trail, so that two
+// runs (e.g. of two solver versions) can be compared event by event and the
+// state at any event can be reconstructed.
+//
+// The file starts with MAGIC and the format version, followed by one event
+// after another: a tag byte and its operands as LEB128 varints. Literals are
+// zigzag-encoded DIMACS literals; clause ids are stored plus one, with 0 for
+// "no clause" (assumptions). An explanation clause is its number of literals
+// followed by the literals.
+//
+// Version 2 added the explanation event; a version 1 trace is read as a
+// version 2 trace without any.
+
+const MAGIC: &[u8; 4] = b"RSTR";
+const VERSION: u8 = 2;
+
+const TAG_DECIDE: u8 = 1;
+const TAG_ASSUME: u8 = 2;
+const TAG_IMPLY: u8 = 3;
+const TAG_CONFLICT: u8 = 4;
+const TAG_BACKTRACK: u8 = 5;
+const TAG_FLIP: u8 = 6;
+const TAG_EXPLAIN: u8 = 7;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // a free choice of the heuristics
+    Decide(Literal),
+    // an assumption of the caller
+    Assume(Literal),
+    // forced by a clause, during BCP or because it is a unit clause
+    Imply(Literal, Option<u32>),
+    // the clause found unsatisfiable, if any
+    Conflict(Option<u32>),
+    // the variable is dropped from the trail while backtracking
+    Backtrack(Variable),
+    // the last free choice is retried with this literal
+    Flip(Literal),
+    // a clause explaining a propagation or conflict of the XOR or cardinality
+    // constraints, added with the next clause id
+    Explain(Vec<Literal>),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let clause = |c: &Option<u32>| match c {
+            Some(id) => format!("clause {}", id),
+            None => String::from("no clause"),
+        };
+        match self {
+            Event::Decide(l) => write!(f, "decide {}", l.to_dimacs()),
+            Event::Assume(l) => write!(f, "assume {}", l.to_dimacs()),
+            Event::Imply(l, c) => write!(f, "imply {} by {}", l.to_dimacs(), clause(c)),
+            Event::Conflict(c) => write!(f, "conflict in {}", clause(c)),
+            Event::Backtrack(v) => write!(f, "backtrack {}", v.index),
+            Event::Flip(l) => write!(f, "flip to {}", l.to_dimacs()),
+            Event::Explain(c) => {
+                let literals: Vec<String> = c.iter().map(|l| l.to_dimacs().to_string()).collect();
+                write!(f, "explain {}", literals.join(" "))
+            }
+        }
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        out.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
+
+fn put_literal(out: &mut Vec<u8>, l: Literal) {
+    let x = l.to_dimacs() as i64;
+    put_varint(out, ((x << 1) ^ (x >> 63)) as u64);
+}
+
+fn put_clause(out: &mut Vec<u8>, c: Option<u32>) {
+    put_varint(out, c.map_or(0, |id| id as u64 + 1));
+}
+
+fn encode(event: &Event, out: &mut Vec<u8>) {
+    match event {
+        Event::Decide(l) => {
+            out.push(TAG_DECIDE);
+            put_literal(out, *l);
+        }
+        Event::Assume(l) => {
+            out.push(TAG_ASSUME);
+            put_literal(out, *l);
+        }
+        Event::Imply(l, c) => {
+            out.push(TAG_IMPLY);
+            put_literal(out, *l);
+            put_clause(out, *c);
+        }
+        Event::Conflict(c) => {
+            out.push(TAG_CONFLICT);
+            put_clause(out, *c);
+        }
+        Event::Backtrack(v) => {
+            out.push(TAG_BACKTRACK);
+            put_varint(out, v.index as u64);
+        }
+        Event::Flip(l) => {
+            out.push(TAG_FLIP);
+            put_literal(out, *l);
+        }
+        Event::Explain(c) => {
+            out.push(TAG_EXPLAIN);
+            put_varint(out, c.len() as u64);
+            for l in c.iter() {
+                put_literal(out, *l);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.bytes.get(self.pos).ok_or("truncated trace")?;
+        self.pos += 1;
+        return Ok(b);
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut x = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            x |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(x);
+            }
+        }
+        return Err(format!("bad varint at byte {}", self.pos));
+    }
+
+    fn literal(&mut self) -> Result<Literal, String> {
+        let z = self.varint()?;
+        let x = ((z >> 1) as i64) ^ -((z & 1) as i64);
+        if x == 0 || x.unsigned_abs() > i32::MAX as u64 {
+            return Err(format!("bad literal at byte {}", self.pos));
+        }
+        return Ok(Literal::from_dimacs(x as i32));
+    }
+
+    fn clause(&mut self) -> Result<Option<u32>, String> {
+        return match self.varint()? {
+            0 => Ok(None),
+            x => u32::try_from(x - 1).map(Some).map_err(|_| format!("bad clause id at byte {}", self.pos)),
+        };
+    }
+
+    fn event(&mut self) -> Result<Event, String> {
+        let tag = self.byte()?;
+        return match tag {
+            TAG_DECIDE => Ok(Event::Decide(self.literal()?)),
+            TAG_ASSUME => Ok(Event::Assume(self.literal()?)),
+            TAG_IMPLY => Ok(Event::Imply(self.literal()?, self.clause()?)),
+            TAG_CONFLICT => Ok(Event::Conflict(self.clause()?)),
+            TAG_BACKTRACK => {
+                let index = u32::try_from(self.varint()?).map_err(|_| format!("bad variable at byte {}", self.pos))?;
+                Ok(Event::Backtrack(Variable { index }))
+            }
+            TAG_FLIP => Ok(Event::Flip(self.literal()?)),
+            TAG_EXPLAIN => {
+                let len = self.varint()?;
+                let mut c = Vec::new();
+                for _ in 0..len {
+                    c.push(self.literal()?);
+                }
+                Ok(Event::Explain(c))
+            }
+            _ => Err(format!("unknown event tag {} at byte {}", tag, self.pos - 1)),
+        };
+    }
+}
+
+/// Reads a whole trace file.
+pub fn read_trace(path: &str) -> Result<Vec<Event>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    if bytes.len() < 5 || &bytes[..4] != MAGIC {
+        return Err(format!("{}: not a trace file", path));
+    }
+    if !(1..=VERSION).contains(&bytes[4]) {
+        return Err(format!("{}: unsupported trace version {}", path, bytes[4]));
+    }
+    let mut decoder = Decoder { bytes: &bytes, pos: 5 };
+    let mut events = Vec::new();
+    while decoder.pos < bytes.len() {
+        events.push(decoder.event().map_err(|e| format!("{}: {}", path, e))?);
+    }
+    return Ok(events);
+}
+
+/// Appends events to a trace file. Write errors are kept until `finish`, so
+/// that recording never disturbs the search.
+#[derive(Debug)]
+pub struct TraceWriter {
+    path: String,
+    out: BufWriter<File>,
+    buffer: Vec<u8>,
+    events: u64,
+    error: Option<io::Error>,
+}
+
+impl TraceWriter {
+    pub fn create(path: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC).and_then(|_| out.write_all(&[VERSION])).map_err(|e| format!("{}: {}", path, e))?;
+        return Ok(TraceWriter {
+            path: String::from(path),
+            out,
+            buffer: Vec::new(),
+            events: 0,
+            error: None,
+        });
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if self.error.is_some() {
+            return;
+        }
+        self.buffer.clear();
+        encode(event, &mut self.buffer);
+        self.events += 1;
+        if let Err(e) = self.out.write_all(&self.buffer) {
+            self.error = Some(e);
+        }
+    }
+
+    /// Flushes the file; returns the number of events written.
+    pub fn finish(mut self) -> Result<u64, String> {
+        if let Some(e) = self.error.take() {
+            return Err(format!("{}: {}", self.path, e));
+        }
+        self.out.flush().map_err(|e| format!("{}: {}", self.path, e))?;
+        return Ok(self.events);
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ReplayArgs {
+    /// The CNF file the trace was recorded on
+    pub input: String,
+
+    /// Trace file written with --trace
+    pub trace: String,
+
+    /// Stop after applying event N (numbered from 1) and dump the state; 0
+    /// dumps the state before the first event
+    #[arg(long, value_name = "N")]
+    pub stop_at: Option<usize>,
+
+    /// Stop at the K-th conflict (numbered from 1)
+    #[arg(long, value_name = "K", conflicts_with_all = ["stop_at", "diff"])]
+    pub conflict: Option<usize>,
+
+    /// Write the implication graph at the conflict replay stops at to FILE
+    /// (Graphviz DOT)
+    #[arg(long, value_name = "FILE")]
+    pub dot: Option<String>,
+
+    /// Compare with a second trace and stop right before the first event
+    /// where the two differ
+    #[arg(long, value_name = "TRACE")]
+    pub diff: Option<String>,
+
+    /// Print every event as it is applied
+    #[arg(long)]
+    pub print: bool,
+}
+
+// number of leading events both traces agree on
+fn common_prefix(a: &[Event], b: &[Event]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+fn dump<H: Heuristics>(replayer: &Replayer<H>, applied: usize) {
+    println!("STATE after {} events", applied);
+    println!("{:#?}", replayer.problem);
+    println!("{:?}", replayer.solution);
+}
+
+pub fn replay(args: &ReplayArgs) -> Result<(), String> {
+    let events = read_trace(&args.trace)?;
+    let mut stop_at = args.stop_at.unwrap_or(events.len()).min(events.len());
+    if let Some(k) = args.conflict {
+        let mut conflicts = events.iter().enumerate().filter(|(_, e)| matches!(e, Event::Conflict(_)));
+        match conflicts.nth(k.max(1) - 1) {
+            Some((i, _)) => stop_at = i + 1,
+            None => return Err(format!("the trace has fewer than {} conflicts", k)),
+        }
+    }
+
+    if let Some(other) = &args.diff {
+        let theirs = read_trace(other)?;
+        let same = common_prefix(&events, &theirs);
+        if same == events.len() && same == theirs.len() {
+            println!("IDENTICAL: {} events", same);
+        } else {
+            let show = |e: Option<&Event>| e.map_or(String::from("end of trace"), |e| e.to_string());
+            println!("DIVERGED at event {}: {} vs {}", same + 1, show(events.get(same)), show(theirs.get(same)));
+        }
+        if args.stop_at.is_none() {
+            stop_at = same;
+        }
+    }
+
+    let mut h = Ascending::new();
+    let problem = parser::parse(&args.input, &mut h);
+    let mut replayer = Replayer::new(problem, h);
+    for (i, event) in events.iter().take(stop_at).enumerate() {
+        if args.print {
+            println!("{} {}", i + 1, event);
+        }
+        if let Err(e) = replayer.apply(event) {
+            dump(&replayer, i);
+            return Err(format!("event {} ({}): {}", i + 1, event, e));
+        }
+    }
+    dump(&replayer, stop_at);
+
+    if let Some(path) = &args.dot {
+        let conflict = match stop_at.checked_sub(1).map(|i| &events[i]) {
+            Some(Event::Conflict(Some(id))) => *id,
+            _ => return Err(String::from("--dot needs replay to stop at a conflict in a clause, see --conflict")),
+        };
+        let dot = implication_graph::to_dot(&replayer.problem, &replayer.solution, conflict)?;
+        fs::write(path, dot).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip() {
+        let path = std::env::temp_dir().join(format!("trace_test_{}.rstr", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let l = Literal::from_dimacs;
+        let events = vec![
+            Event::Assume(l(-3)),
+            Event::Decide(l(1)),
+            Event::Imply(l(70000), Some(0)),
+            Event::Imply(l(-2), None),
+            Event::Explain(vec![l(2), l(-1), l(70000)]),
+            Event::Conflict(Some(300)),
+            Event::Backtrack(Variable { index: 2 }),
+            Event::Flip(l(-1)),
+        ];
+        let mut writer = TraceWriter::create(&path).unwrap();
+        for e in events.iter() {
+            writer.record(e);
+        }
+        assert_eq!(writer.finish().unwrap(), events.len() as u64);
+        assert_eq!(read_trace(&path).unwrap(), events);
+
+        // version 1 had no explanations; later versions are unknown
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(5);
+        bytes[4] = 1;
+        encode(&Event::Decide(l(4)), &mut bytes);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_trace(&path).unwrap(), vec![Event::Decide(l(4))]);
+        bytes[4] = VERSION + 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(read_trace(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
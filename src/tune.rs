@@ -0,0 +1,317 @@
+use crate::heuristics::registry::{self, HeuristicsConfig};
+use crate::rng::Rng;
+use crate::runner::{self, Run};
+use log::info;
+use std::fs;
+use std::time::Duration;
+
+// Searches the options of one heuristics for the lowest cost over a set of
+// instances and writes the winner as a --heuristics-config file.
+//
+// The parameter space is a TOML file with one table per heuristics. A range
+// is written as an inline table, a categorical parameter as an array:
+//
+//     [vsids]
+//     decay = { min = 0.8, max = 1.0 }
+//     [lookahead]
+//     preselect = { min = 2, max = 64, log = true }
+//     [jw]
+//     two_sided = [true, false]
+//
+// A range over integers samples integers; `log = true` samples uniformly on
+// a log scale.
+
+const DEFAULT_SPACE: &str = r#"
+[vsids]
+decay = { min = 0.75, max = 1.0 }
+
+[lookahead]
+preselect = { min = 2, max = 64, log = true }
+
+[jw]
+two_sided = [true, false]
+
+[moms]
+k = { min = 0, max = 8 }
+
+[chb]
+alpha_start = { min = 0.1, max = 0.9 }
+alpha_min = { min = 0.01, max = 0.1 }
+alpha_decay = { min = 1e-7, max = 1e-4, log = true }
+
+[lrb]
+alpha_start = { min = 0.1, max = 0.9 }
+alpha_min = { min = 0.01, max = 0.1 }
+alpha_decay = { min = 1e-7, max = 1e-4, log = true }
+"#;
+
+// sampling gives up after this many draws per requested configuration
+const SAMPLE_ATTEMPTS: usize = 10;
+
+// racing never drops a configuration before it has seen this many instances
+const RACE_MIN_INSTANCES: usize = 3;
+
+#[derive(clap::Args, Debug)]
+pub struct TuneArgs {
+    /// Directory of .cnf instances to tune on
+    pub dir: String,
+
+    /// Heuristics whose options are tuned
+    #[arg(long, default_value_t = String::from("vsids"))]
+    pub heuristics: String,
+
+    /// Search method: "random" or "race"
+    #[arg(long, default_value_t = String::from("random"))]
+    pub method: String,
+
+    /// Number of configurations to try, the defaults included
+    #[arg(long, default_value_t = 20)]
+    pub budget: usize,
+
+    /// Time limit per run in seconds
+    #[arg(long, default_value_t = 10.0)]
+    pub timeout: f64,
+
+    /// Cost of a run: "par2" or "mean" (solving time, timeouts count once)
+    #[arg(long, default_value_t = String::from("par2"))]
+    pub objective: String,
+
+    /// TOML file describing the parameter space (built-in ranges otherwise)
+    #[arg(long, value_name = "FILE")]
+    pub space: Option<String>,
+
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Where to write the best configuration
+    #[arg(long, short, default_value_t = String::from("tuned.toml"))]
+    pub output: String,
+
+    #[arg(long)]
+    pub no_bcp: bool,
+}
+
+enum Domain {
+    Real { min: f64, max: f64, log: bool },
+    Integer { min: i64, max: i64, log: bool },
+    Choice(Vec<toml::Value>),
+}
+
+struct Parameter {
+    key: String,
+    domain: Domain,
+}
+
+fn parse_parameter(key: &str, value: &toml::Value) -> Result<Parameter, String> {
+    let bad = || format!("bad range for {}: {}", key, value);
+    let domain = match value {
+        toml::Value::Array(choices) if !choices.is_empty() => Domain::Choice(choices.clone()),
+        toml::Value::Table(range) => {
+            let log = range.get("log").and_then(|l| l.as_bool()).unwrap_or(false);
+            match (range.get("min"), range.get("max")) {
+                (Some(toml::Value::Integer(min)), Some(toml::Value::Integer(max))) if min <= max => {
+                    Domain::Integer { min: *min, max: *max, log: log && *min > 0 }
+                }
+                (Some(min), Some(max)) => {
+                    let min = min.as_float().or(min.as_integer().map(|x| x as f64)).ok_or_else(bad)?;
+                    let max = max.as_float().or(max.as_integer().map(|x| x as f64)).ok_or_else(bad)?;
+                    if min > max || (log && min <= 0.0) {
+                        return Err(bad());
+                    }
+                    Domain::Real { min, max, log }
+                }
+                _ => return Err(bad()),
+            }
+        }
+        _ => return Err(bad()),
+    };
+    return Ok(Parameter { key: String::from(key), domain });
+}
+
+fn parse_space(contents: &str, heuristics: &str) -> Result<Vec<Parameter>, String> {
+    let table: toml::Table = contents.parse().map_err(|e| format!("parameter space: {}", e))?;
+    let section = match table.get(heuristics).and_then(|s| s.as_table()) {
+        Some(section) if !section.is_empty() => section,
+        _ => return Err(format!("no parameters to tune for {}", heuristics)),
+    };
+    section.iter().map(|(k, v)| parse_parameter(k, v)).collect()
+}
+
+fn sample(domain: &Domain, rng: &mut Rng) -> toml::Value {
+    let uniform = |rng: &mut Rng, min: f64, max: f64, log: bool| {
+        if log {
+            (min.ln() + rng.next_f64() * (max.ln() - min.ln())).exp()
+        } else {
+            min + rng.next_f64() * (max - min)
+        }
+    };
+    match domain {
+        Domain::Real { min, max, log } => toml::Value::Float(uniform(rng, *min, *max, *log)),
+        Domain::Integer { min, max, log: true } => {
+            let x = uniform(rng, *min as f64, *max as f64 + 1.0, true).floor() as i64;
+            toml::Value::Integer(x.clamp(*min, *max))
+        }
+        Domain::Integer { min, max, log: false } => {
+            toml::Value::Integer(min + rng.below((max - min) as u64 + 1) as i64)
+        }
+        Domain::Choice(choices) => choices[rng.below(choices.len() as u64) as usize].clone(),
+    }
+}
+
+struct Tuner<'a> {
+    args: &'a TuneArgs,
+    timeout: Duration,
+    penalty: f64,
+}
+
+impl Tuner<'_> {
+    fn cost(&self, config: &HeuristicsConfig, instance: &String) -> Result<f64, String> {
+        let run = Run {
+            input: instance,
+            use_bcp: !self.args.no_bcp,
+            seed: self.args.seed,
+            timeout: self.timeout,
+        };
+        let result = registry::dispatch(&self.args.heuristics, config, run)?;
+        info!(target: "tune", "{} {:?} in {:?}", instance, result.outcome, result.profiler.duration());
+        return Ok(result.par(self.timeout, self.penalty));
+    }
+
+    // Evaluates every configuration on all instances. A configuration is
+    // abandoned as soon as its total cost exceeds the best total so far.
+    // Returns the index and mean cost of the winner.
+    fn random_search(&self, candidates: &[HeuristicsConfig], instances: &[String]) -> Result<(usize, f64), String> {
+        let mut best: Option<(usize, f64)> = None;
+        for (i, config) in candidates.iter().enumerate() {
+            let mut total = 0.0;
+            let mut capped = false;
+            for instance in instances.iter() {
+                total += self.cost(config, instance)?;
+                if best.is_some_and(|(_, b)| total > b) {
+                    capped = true;
+                    break;
+                }
+            }
+            if capped {
+                println!("config {}: capped", i);
+                continue;
+            }
+            println!("config {}: {:.3}", i, total / instances.len() as f64);
+            best = Some((i, total));
+        }
+        let (i, total) = best.unwrap();
+        return Ok((i, total / instances.len() as f64));
+    }
+
+    // Runs all surviving configurations instance by instance and drops those
+    // that are significantly worse than the current leader, by a paired
+    // comparison of their costs on the instances seen so far.
+    fn race(&self, candidates: &[HeuristicsConfig], instances: &[String]) -> Result<(usize, f64), String> {
+        let mut costs = vec![Vec::<f64>::new(); candidates.len()];
+        let mut alive: Vec<usize> = (0..candidates.len()).collect();
+        let mean = |c: &Vec<f64>| c.iter().sum::<f64>() / c.len() as f64;
+
+        for (seen, instance) in instances.iter().enumerate() {
+            for i in alive.iter() {
+                costs[*i].push(self.cost(&candidates[*i], instance)?);
+            }
+            if seen + 1 < RACE_MIN_INSTANCES || alive.len() == 1 {
+                continue;
+            }
+            let leader = *alive
+                .iter()
+                .min_by(|a, b| mean(&costs[**a]).total_cmp(&mean(&costs[**b])))
+                .unwrap();
+            alive.retain(|i| {
+                let keep = *i == leader || !significantly_worse(&costs[*i], &costs[leader]);
+                if !keep {
+                    println!("config {}: dropped after {} instances", i, seen + 1);
+                }
+                keep
+            });
+            if alive.len() == 1 {
+                break;
+            }
+        }
+
+        let winner = *alive
+            .iter()
+            .min_by(|a, b| mean(&costs[**a]).total_cmp(&mean(&costs[**b])))
+            .unwrap();
+        for i in alive.iter() {
+            println!("config {}: {:.3}", i, mean(&costs[*i]));
+        }
+        return Ok((winner, mean(&costs[winner])));
+    }
+}
+
+// true if the mean of the paired differences is above zero by more than two
+// standard errors
+fn significantly_worse(costs: &[f64], leader: &[f64]) -> bool {
+    let n = costs.len() as f64;
+    let diffs: Vec<f64> = costs.iter().zip(leader.iter()).map(|(c, l)| c - l).collect();
+    let mean = diffs.iter().sum::<f64>() / n;
+    let variance = diffs.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    return mean - 2.0 * (variance / n).sqrt() > 0.0;
+}
+
+pub fn tune(args: &TuneArgs) -> Result<(), String> {
+    let space = match &args.space {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => String::from(DEFAULT_SPACE),
+    };
+    let parameters = parse_space(&space, &args.heuristics)?;
+    let penalty = match args.objective.as_str() {
+        "par2" => 2.0,
+        "mean" => 1.0,
+        _ => return Err(format!("unknown objective '{}'", args.objective)),
+    };
+    let mut instances = runner::list_instances(&args.dir)?;
+    let mut rng = Rng::new(args.seed);
+
+    // The defaults take part as the first configuration. Duplicates are
+    // skipped, a small categorical space may thus yield fewer candidates.
+    let mut candidates = vec![HeuristicsConfig::default()];
+    let mut seen = vec![String::new()];
+    for _ in 0..SAMPLE_ATTEMPTS * args.budget {
+        if candidates.len() >= args.budget.max(1) {
+            break;
+        }
+        let mut config = HeuristicsConfig::default();
+        for p in parameters.iter() {
+            config.set(&args.heuristics, &p.key, sample(&p.domain, &mut rng))?;
+        }
+        registry::validate(&config)?;
+        let key = config.to_toml();
+        if !seen.contains(&key) {
+            seen.push(key);
+            candidates.push(config);
+        }
+    }
+    for (i, config) in candidates.iter().enumerate() {
+        info!(target: "tune", "config {}: {}", i, config.to_toml().replace('\n', " "));
+    }
+
+    let tuner = Tuner {
+        args,
+        timeout: runner::timeout(args.timeout)?,
+        penalty,
+    };
+    let (winner, cost) = match args.method.as_str() {
+        "random" => tuner.random_search(&candidates, &instances)?,
+        "race" => {
+            rng.shuffle(&mut instances);
+            tuner.race(&candidates, &instances)?
+        }
+        _ => return Err(format!("unknown tuning method '{}'", args.method)),
+    };
+
+    let header = format!(
+        "# tuned {} on {} ({} instances, {} method, {} {:.3})\n",
+        args.heuristics, args.dir, instances.len(), args.method, args.objective, cost
+    );
+    fs::write(&args.output, header + &candidates[winner].to_toml())
+        .map_err(|e| format!("{}: {}", args.output, e))?;
+    println!("BEST: config {} with {} {:.3}, written to {}", winner, args.objective, cost, args.output);
+    return Ok(());
+}
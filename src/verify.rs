@@ -0,0 +1,374 @@
+use core::fmt;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+// Checks a model against a CNF file without any of the solver's data
+// structures: the formula is read by its own DIMACS reader and every clause is
+// evaluated directly. Models may come from this solver or from any other one
+// that prints competition-style `v` lines.
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyModelArgs {
+    /// The CNF file
+    pub cnf: String,
+
+    /// File with `v` lines (or the ASSIGNMENT line of this solver)
+    pub model: String,
+}
+
+pub struct Cnf {
+    // from the `p cnf` line, if any
+    pub declared_variables: Option<u32>,
+    pub clauses: Vec<Vec<i32>>,
+    // `x` lines: the XOR of the literals must be true
+    pub xors: Vec<Vec<i32>>,
+    // the variables of the `c ind` / `c p show` lines, if any
+    pub projection: Option<Vec<u32>>,
+    // the QDIMACS quantifier prefix, outermost block first: whether the
+    // block is universal (an `a` line) or existential (`e`), and its variables
+    pub prefix: Vec<(bool, Vec<u32>)>,
+}
+
+/// Reads a DIMACS CNF file. Clauses may span several lines and end with 0;
+/// a `%` line ends the formula (as in the SATLIB benchmarks). Projection
+/// variables are read from `c ind ... 0` and `c p show ... 0` comment lines,
+/// XOR constraints from `x` lines (e.g. "x1 -2 3 0", on a single line), and
+/// the quantifier prefix of QDIMACS from `e` and `a` lines (e.g. "a 1 2 0").
+/// The prefix is kept as written; whether it is well formed is up to the
+/// QBF solver.
+pub fn read_cnf(path: &str) -> Result<Cnf, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut cnf = Cnf {
+        declared_variables: None,
+        clauses: Vec::new(),
+        xors: Vec::new(),
+        projection: None,
+        prefix: Vec::new(),
+    };
+    let mut clause = Vec::new();
+    for (n, line) in text.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if line.starts_with('%') {
+            break;
+        }
+        if let Some(vars) = line.strip_prefix("c ind ").or_else(|| line.strip_prefix("c p show ")) {
+            let projection = cnf.projection.get_or_insert_with(Vec::new);
+            for token in vars.split_whitespace() {
+                let var: u32 = token.parse().map_err(|_| format!("{}:{}: bad variable '{}'", path, n, token))?;
+                if var != 0 {
+                    projection.push(var);
+                }
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        if line.starts_with('p') {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["p", "cnf", vars, _] => {
+                    let vars = vars.parse().map_err(|_| format!("{}:{}: bad problem line", path, n))?;
+                    cnf.declared_variables = Some(vars);
+                }
+                _ => return Err(format!("{}:{}: bad problem line", path, n)),
+            }
+            continue;
+        }
+        let quantifier = match line.split_whitespace().next() {
+            Some("e") => Some(false),
+            Some("a") => Some(true),
+            _ => None,
+        };
+        if let Some(universal) = quantifier {
+            let mut block = Vec::new();
+            for token in line[1..].split_whitespace() {
+                let var: u32 = token.parse().map_err(|_| format!("{}:{}: bad variable '{}'", path, n, token))?;
+                if var == 0 {
+                    break;
+                }
+                block.push(var);
+            }
+            cnf.prefix.push((universal, block));
+            continue;
+        }
+        if let Some(literals) = line.strip_prefix('x') {
+            let mut xor = Vec::new();
+            for token in literals.split_whitespace() {
+                let literal: i32 = token.parse().map_err(|_| format!("{}:{}: bad literal '{}'", path, n, token))?;
+                if literal == 0 {
+                    break;
+                }
+                xor.push(literal);
+            }
+            cnf.xors.push(xor);
+            continue;
+        }
+        for token in line.split_whitespace() {
+            let literal: i32 = token.parse().map_err(|_| format!("{}:{}: bad literal '{}'", path, n, token))?;
+            if literal == 0 {
+                cnf.clauses.push(std::mem::take(&mut clause));
+            } else {
+                clause.push(literal);
+            }
+        }
+    }
+    // the last clause may lack its terminating 0
+    if !clause.is_empty() {
+        cnf.clauses.push(clause);
+    }
+    return Ok(cnf);
+}
+
+impl Cnf {
+    /// The largest variable of the clauses and XOR constraints.
+    pub fn max_variable(&self) -> u32 {
+        return self.clauses.iter().chain(self.xors.iter()).flatten().map(|l| l.unsigned_abs()).max().unwrap_or(0);
+    }
+
+    /// The XOR constraints as variables and parity: negative literals flip
+    /// the parity and a variable given twice cancels out.
+    pub fn xor_constraints(&self) -> Vec<(Vec<u32>, bool)> {
+        let mut constraints = Vec::new();
+        for xor in self.xors.iter() {
+            let mut vars = BTreeSet::new();
+            let mut parity = true;
+            for l in xor.iter() {
+                parity ^= *l < 0;
+                if !vars.insert(l.unsigned_abs()) {
+                    vars.remove(&l.unsigned_abs());
+                }
+            }
+            constraints.push((vars.into_iter().collect(), parity));
+        }
+        return constraints;
+    }
+}
+
+/// A (possibly partial) assignment, by variable.
+pub type Model = BTreeMap<u32, bool>;
+
+fn assign(model: &mut Model, literal: i32) -> Result<(), String> {
+    let (var, value) = (literal.unsigned_abs(), literal > 0);
+    match model.insert(var, value) {
+        Some(old) if old != value => Err(format!("variable {} is given both values", var)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a model from `v` lines, e.g. "v 1 -2 3 0", spread over any number of
+/// lines. The ASSIGNMENT line printed by this solver ("ASSIGNMENT: 1=0 2=1")
+/// is accepted too. Other lines are ignored, except for an "s" line that does
+/// not claim satisfiability.
+pub fn parse_model(text: &str) -> Result<Model, String> {
+    let mut model = Model::new();
+    let mut found = false;
+    for (n, line) in text.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if let Some(status) = line.strip_prefix("s ") {
+            if status.trim() != "SATISFIABLE" {
+                return Err(format!("line {}: the solver answered {}", n, status.trim()));
+            }
+        } else if let Some(values) = line.strip_prefix("v ").or((line == "v").then_some("")) {
+            found = true;
+            for token in values.split_whitespace() {
+                let literal: i32 = token.parse().map_err(|_| format!("line {}: bad literal '{}'", n, token))?;
+                if literal == 0 {
+                    break;
+                }
+                assign(&mut model, literal).map_err(|e| format!("line {}: {}", n, e))?;
+            }
+        } else if let Some(values) = line.strip_prefix("ASSIGNMENT:") {
+            found = true;
+            for token in values.split_whitespace() {
+                let bad = || format!("line {}: bad assignment '{}'", n, token);
+                let (var, value) = token.split_once('=').ok_or_else(bad)?;
+                let var: i32 = var.parse().ok().filter(|v| *v > 0).ok_or_else(bad)?;
+                let literal = match value {
+                    "1" => var,
+                    "0" => -var,
+                    _ => return Err(bad()),
+                };
+                assign(&mut model, literal).map_err(|e| format!("line {}: {}", n, e))?;
+            }
+        }
+    }
+    if !found {
+        return Err(String::from("no model (v lines) found"));
+    }
+    return Ok(model);
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub clauses: usize,
+    // indices (from 1) of clauses with every literal false
+    pub falsified: Vec<usize>,
+    // indices of clauses not satisfied that have unassigned literals
+    pub unassigned: Vec<usize>,
+    pub xors: usize,
+    // indices (from 1) of XOR constraints that are false, or that have
+    // unassigned literals
+    pub falsified_xors: Vec<usize>,
+    pub unassigned_xors: Vec<usize>,
+    // variables of the formula the model leaves out
+    pub missing: BTreeSet<u32>,
+}
+
+impl Report {
+    pub fn ok(&self) -> bool {
+        self.falsified.is_empty()
+            && self.unassigned.is_empty()
+            && self.falsified_xors.is_empty()
+            && self.unassigned_xors.is_empty()
+    }
+}
+
+/// Evaluates every clause and XOR constraint under `model`. Fails if the model assigns a
+/// variable outside the declared range of the formula.
+pub fn check(cnf: &Cnf, model: &Model) -> Result<Report, String> {
+    if let (Some(declared), Some(var)) = (cnf.declared_variables, model.keys().next_back()) {
+        if *var > declared {
+            return Err(format!("variable {} is not in the formula (p cnf declares {})", var, declared));
+        }
+    }
+    let mut report = Report {
+        clauses: cnf.clauses.len(),
+        xors: cnf.xors.len(),
+        ..Default::default()
+    };
+    for (i, clause) in cnf.clauses.iter().enumerate() {
+        let values: Vec<Option<bool>> =
+            clause.iter().map(|l| model.get(&l.unsigned_abs()).map(|v| *v == (*l > 0))).collect();
+        if values.contains(&Some(true)) {
+            continue;
+        }
+        if values.contains(&None) {
+            report.unassigned.push(i + 1);
+        } else {
+            report.falsified.push(i + 1);
+        }
+    }
+    for (i, xor) in cnf.xors.iter().enumerate() {
+        let values: Vec<Option<bool>> =
+            xor.iter().map(|l| model.get(&l.unsigned_abs()).map(|v| *v == (*l > 0))).collect();
+        if values.contains(&None) {
+            report.unassigned_xors.push(i + 1);
+        } else if values.iter().filter(|v| **v == Some(true)).count() % 2 == 0 {
+            report.falsified_xors.push(i + 1);
+        }
+    }
+    for clause in cnf.clauses.iter().chain(cnf.xors.iter()) {
+        report.missing.extend(clause.iter().map(|l| l.unsigned_abs()).filter(|v| !model.contains_key(v)));
+    }
+    return Ok(report);
+}
+
+// one line per clause that is not satisfied
+pub struct Details<'a>(pub &'a Cnf, pub &'a Report, pub &'a Model);
+
+impl fmt::Display for Details<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Details(cnf, report, model) = self;
+        let show = |i: &usize| {
+            let literals: Vec<String> = cnf.clauses[i - 1].iter().map(|l| l.to_string()).collect();
+            literals.join(" ")
+        };
+        for i in report.falsified.iter() {
+            writeln!(f, "falsified clause {}: {}", i, show(i))?;
+        }
+        for i in report.unassigned.iter() {
+            let missing: Vec<String> = cnf.clauses[i - 1]
+                .iter()
+                .map(|l| l.unsigned_abs())
+                .filter(|v| !model.contains_key(v))
+                .map(|v| v.to_string())
+                .collect();
+            writeln!(f, "unassigned clause {}: {} (no value for {})", i, show(i), missing.join(" "))?;
+        }
+        let show_xor = |i: &usize| {
+            let literals: Vec<String> = cnf.xors[i - 1].iter().map(|l| l.to_string()).collect();
+            literals.join(" ")
+        };
+        for i in report.falsified_xors.iter() {
+            writeln!(f, "falsified XOR {}: x{}", i, show_xor(i))?;
+        }
+        for i in report.unassigned_xors.iter() {
+            writeln!(f, "unassigned XOR {}: x{}", i, show_xor(i))?;
+        }
+        if !report.missing.is_empty() {
+            let missing: Vec<String> = report.missing.iter().map(|v| v.to_string()).collect();
+            writeln!(f, "missing variables: {}", missing.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn verify_model(args: &VerifyModelArgs) -> Result<(), String> {
+    let cnf = read_cnf(&args.cnf)?;
+    let text = fs::read_to_string(&args.model).map_err(|e| format!("{}: {}", args.model, e))?;
+    let model = parse_model(&text).map_err(|e| format!("{}: {}", args.model, e))?;
+    let report = check(&cnf, &model)?;
+    print!("{}", Details(&cnf, &report, &model));
+    if !report.ok() {
+        return Err(format!(
+            "{} of {} clauses falsified, {} only partially assigned",
+            report.falsified.len() + report.falsified_xors.len(),
+            report.clauses + report.xors,
+            report.unassigned.len() + report.unassigned_xors.len()
+        ));
+    }
+    if report.xors > 0 {
+        println!("VERIFIED: all {} clauses and {} XOR constraints satisfied", report.clauses, report.xors);
+        return Ok(());
+    }
+    println!("VERIFIED: all {} clauses satisfied", report.clauses);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_file;
+
+    fn cnf(text: &str) -> Cnf {
+        let path = temp_file("verify.cnf", text);
+        let cnf = read_cnf(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        return cnf;
+    }
+
+    #[test]
+    fn falsified_and_unassigned_clauses_are_reported() {
+        let cnf = cnf("p cnf 3 3\n1 2 0\n-1 3 0\n2 -3 0\nx1 2 0\n");
+        let model = parse_model("v -1 -2 0\n").unwrap();
+        let report = check(&cnf, &model).unwrap();
+        assert!(!report.ok());
+        assert_eq!(report.falsified, vec![1]);
+        assert_eq!(report.unassigned, vec![3]);
+        assert_eq!(report.falsified_xors, vec![1]);
+        assert_eq!(report.missing, BTreeSet::from([3]));
+        assert_eq!(
+            Details(&cnf, &report, &model).to_string(),
+            "falsified clause 1: 1 2\n\
+             unassigned clause 3: 2 -3 (no value for 3)\n\
+             falsified XOR 1: x1 2\n\
+             missing variables: 3\n"
+        );
+
+        let model = parse_model("s SATISFIABLE\nv -1 2\nv 3 0\n").unwrap();
+        let report = check(&cnf, &model).unwrap();
+        assert!(report.ok());
+        assert_eq!(Details(&cnf, &report, &model).to_string(), "");
+        // the model of this solver reads the same
+        assert_eq!(parse_model("ASSIGNMENT: 1=0 2=1 3=1").unwrap(), model);
+    }
+
+    #[test]
+    fn bad_models_are_errors() {
+        assert_eq!(parse_model("v 1 -2 0\nv 2 0\n").unwrap_err(), "line 2: variable 2 is given both values");
+        assert_eq!(parse_model("ASSIGNMENT: 1=1 1=0").unwrap_err(), "line 1: variable 1 is given both values");
+        assert_eq!(parse_model("s UNSATISFIABLE\n").unwrap_err(), "line 1: the solver answered UNSATISFIABLE");
+        assert_eq!(parse_model("c nothing\n").unwrap_err(), "no model (v lines) found");
+        let error = check(&cnf("p cnf 2 1\n1 2 0\n"), &parse_model("v 1 3 0").unwrap()).unwrap_err();
+        assert_eq!(error, "variable 3 is not in the formula (p cnf declares 2)");
+    }
+}